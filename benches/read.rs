@@ -0,0 +1,109 @@
+//! Compares decoding from an unbuffered file, a `BufReader` through `from_reader` and a
+//! `BufReader` through `from_buf_reader`. Run with `cargo bench --bench read`.
+
+extern crate serde;
+extern crate serde_cbor;
+#[macro_use]
+extern crate serde_derive;
+
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use serde::de::{DeserializeOwned, IgnoredAny};
+
+use serde_cbor::{from_buf_reader, from_reader, from_slice, to_vec, Value};
+
+type Records = Vec<BTreeMap<String, Value>>;
+
+/// A record with only its id, whose other fields are skipped.
+#[derive(Deserialize)]
+struct Id {
+    #[allow(dead_code)]
+    id: u64,
+}
+
+fn records() -> Records {
+    (0..20_000)
+        .map(|i| {
+            let mut record = BTreeMap::new();
+            record.insert("id".to_owned(), Value::U64(i));
+            record.insert("name".to_owned(), Value::String(format!("record number {}", i)));
+            record.insert("payload".to_owned(), Value::Bytes(vec![i as u8; 64]));
+            record.insert("score".to_owned(), Value::F64(i as f64 / 7.0));
+            record
+        })
+        .collect()
+}
+
+/// Records whose large fields are unknown to `Id`.
+fn large_records() -> Records {
+    (0..2_000)
+        .map(|i| {
+            let mut record = BTreeMap::new();
+            record.insert("id".to_owned(), Value::U64(i));
+            record.insert("log".to_owned(), Value::String("é".repeat(2048)));
+            record.insert("blob".to_owned(), Value::Bytes(vec![i as u8; 16 * 1024]));
+            record
+        })
+        .collect()
+}
+
+fn time<F, T>(name: &str, mut f: F)
+where
+    F: FnMut() -> T,
+{
+    const RUNS: u32 = 5;
+    let mut best = Duration::MAX;
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        f();
+        let elapsed = start.elapsed();
+        if elapsed < best {
+            best = elapsed;
+        }
+    }
+    println!("  {:<32} {:>10.2} ms", name, best.as_secs_f64() * 1000.0);
+}
+
+/// Times every input source decoding the file into `T`.
+fn compare<T>(path: &Path, bytes: &[u8])
+where
+    T: DeserializeOwned,
+{
+    time("from_reader(File)", || {
+        from_reader::<T, _>(File::open(path).unwrap()).unwrap()
+    });
+    time("from_reader(BufReader<File>)", || {
+        from_reader::<T, _>(BufReader::new(File::open(path).unwrap())).unwrap()
+    });
+    time("from_buf_reader(BufReader<File>)", || {
+        from_buf_reader::<T, _>(BufReader::new(File::open(path).unwrap())).unwrap()
+    });
+    time("from_slice", || from_slice::<T>(bytes).unwrap());
+}
+
+fn main() {
+    let bytes = to_vec(&records()).unwrap();
+    let path = std::env::temp_dir().join("serde_cbor_bench_read.cbor");
+    fs::write(&path, &bytes).unwrap();
+    println!("decoding {} bytes, best of 5 runs", bytes.len());
+
+    println!("into Value maps:");
+    compare::<Records>(&path, &bytes);
+    // Without allocating the values, the cost of reading the input dominates.
+    println!("into IgnoredAny:");
+    compare::<IgnoredAny>(&path, &bytes);
+    println!("into structs skipping all fields but one:");
+    compare::<Vec<Id>>(&path, &bytes);
+
+    let bytes = to_vec(&large_records()).unwrap();
+    fs::write(&path, &bytes).unwrap();
+    println!("decoding {} bytes of records with large fields", bytes.len());
+    println!("into structs skipping all fields but one:");
+    compare::<Vec<Id>>(&path, &bytes);
+
+    fs::remove_file(&path).unwrap();
+}
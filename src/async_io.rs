@@ -0,0 +1,263 @@
+//! Decoding from tokio's `AsyncRead` and encoding to its `AsyncWrite`.
+//!
+//! Input is read exactly up to the end of each item, so nothing is lost when an item is read
+//! from a stream that continues with other data. The bytes of an item are collected before it
+//! is decoded, while waiting for them is left to the executor. Reading a byte at a time is
+//! slow, so readers should be buffered, for example with `tokio::io::BufReader`.
+//!
+//! The offsets in errors are relative to the start of the item.
+//!
+//! This module requires the `async` feature.
+
+use core::cmp;
+use core::marker::PhantomData;
+use futures_core::Stream;
+use serde::de::DeserializeOwned;
+use serde::ser::Serialize;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+use de;
+use error::{Error, ErrorCode, Result};
+use scan::{Scan, Scanner};
+use ser;
+
+// Items announcing longer strings are read in steps of this size, so malicious input cannot
+// make the buffer grow beyond the data actually received.
+const MAX_READ_LEN: usize = 16 * 1024;
+
+/// Decodes a single item from an `AsyncRead`.
+///
+/// The returned future waits while the item is incomplete and fails if the input ends before
+/// the item does.
+pub fn from_async_reader<R, T>(reader: R) -> ReadItem<R, T>
+where
+    R: AsyncRead + Unpin,
+    T: DeserializeOwned,
+{
+    ReadItem {
+        reader: ItemReader::new(reader),
+        output: PhantomData,
+    }
+}
+
+/// Decodes a CBOR sequence, items following each other without a separator, from an
+/// `AsyncRead`.
+///
+/// The stream ends when the input ends between two items. It ends after the first error,
+/// since a malformed item leaves no way to find the start of the next one.
+pub fn stream_from_async_reader<R, T>(reader: R) -> AsyncStreamDeserializer<R, T>
+where
+    R: AsyncRead + Unpin,
+    T: DeserializeOwned,
+{
+    AsyncStreamDeserializer {
+        reader: ItemReader::new(reader),
+        done: false,
+        output: PhantomData,
+    }
+}
+
+/// Encodes a value to an `AsyncWrite` and flushes it.
+///
+/// The value is serialized up front, the returned future writes it.
+pub fn to_async_writer<W, T>(writer: W, value: &T) -> WriteItem<W>
+where
+    W: AsyncWrite + Unpin,
+    T: Serialize,
+{
+    let (buf, error) = match ser::to_vec(value) {
+        Ok(buf) => (buf, None),
+        Err(error) => (Vec::new(), Some(error)),
+    };
+    WriteItem {
+        writer,
+        buf,
+        pos: 0,
+        error,
+    }
+}
+
+/// Collects the bytes of one item at a time.
+struct ItemReader<R> {
+    reader: R,
+    buf: Vec<u8>,
+    scanner: Scanner,
+}
+
+impl<R> ItemReader<R>
+where
+    R: AsyncRead + Unpin,
+{
+    fn new(reader: R) -> ItemReader<R> {
+        ItemReader {
+            reader,
+            buf: Vec::new(),
+            scanner: Scanner::new(),
+        }
+    }
+
+    /// Reads until the buffer holds a complete item, or returns `None` if the input ends
+    /// before the next item starts.
+    fn poll_item(&mut self, cx: &mut Context) -> Poll<Result<Option<()>>> {
+        loop {
+            let missing = match self.scanner.scan(&self.buf)? {
+                Scan::Complete(_) => return Poll::Ready(Ok(Some(()))),
+                Scan::Incomplete(missing) => missing,
+            };
+
+            let start = self.buf.len();
+            self.buf.resize(start + cmp::min(missing, MAX_READ_LEN), 0);
+            let result = {
+                let mut read_buf = ReadBuf::new(&mut self.buf[start..]);
+                Pin::new(&mut self.reader)
+                    .poll_read(cx, &mut read_buf)
+                    .map_ok(|()| read_buf.filled().len())
+            };
+            match result {
+                Poll::Ready(Ok(0)) => {
+                    self.buf.truncate(start);
+                    if self.buf.is_empty() {
+                        return Poll::Ready(Ok(None));
+                    }
+                    return Poll::Ready(Err(Error::syntax(
+                        ErrorCode::EofWhileParsingValue,
+                        start as u64,
+                    )));
+                }
+                Poll::Ready(Ok(n)) => self.buf.truncate(start + n),
+                Poll::Ready(Err(e)) => {
+                    self.buf.truncate(start);
+                    return Poll::Ready(Err(Error::io(e)));
+                }
+                Poll::Pending => {
+                    self.buf.truncate(start);
+                    return Poll::Pending;
+                }
+            }
+        }
+    }
+
+    /// Decodes the complete item in the buffer.
+    fn decode<T>(&mut self) -> Result<T>
+    where
+        T: DeserializeOwned,
+    {
+        let value = de::from_slice(&self.buf);
+        self.buf.clear();
+        value
+    }
+}
+
+/// A future that decodes a single item from an `AsyncRead`.
+///
+/// Created by `from_async_reader`.
+pub struct ReadItem<R, T> {
+    reader: ItemReader<R>,
+    output: PhantomData<fn() -> T>,
+}
+
+impl<R, T> Future for ReadItem<R, T>
+where
+    R: AsyncRead + Unpin,
+    T: DeserializeOwned,
+{
+    type Output = Result<T>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<T>> {
+        let this = self.get_mut();
+        match this.reader.poll_item(cx) {
+            Poll::Ready(Ok(Some(()))) => Poll::Ready(this.reader.decode()),
+            Poll::Ready(Ok(None)) => Poll::Ready(Err(Error::syntax(
+                ErrorCode::EofWhileParsingValue,
+                0,
+            ))),
+            Poll::Ready(Err(e)) => Poll::Ready(Err(e)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+/// A stream of the items of a CBOR sequence read from an `AsyncRead`.
+///
+/// Created by `stream_from_async_reader`.
+pub struct AsyncStreamDeserializer<R, T> {
+    reader: ItemReader<R>,
+    done: bool,
+    output: PhantomData<fn() -> T>,
+}
+
+impl<R, T> AsyncStreamDeserializer<R, T> {
+    /// Unwraps the reader.
+    ///
+    /// The bytes of an item that has only been read in part are lost.
+    pub fn into_inner(self) -> R {
+        self.reader.reader
+    }
+}
+
+impl<R, T> Stream for AsyncStreamDeserializer<R, T>
+where
+    R: AsyncRead + Unpin,
+    T: DeserializeOwned,
+{
+    type Item = Result<T>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Result<T>>> {
+        let this = self.get_mut();
+        if this.done {
+            return Poll::Ready(None);
+        }
+        let item = match this.reader.poll_item(cx) {
+            Poll::Ready(Ok(Some(()))) => this.reader.decode(),
+            Poll::Ready(Ok(None)) => {
+                this.done = true;
+                return Poll::Ready(None);
+            }
+            Poll::Ready(Err(e)) => Err(e),
+            Poll::Pending => return Poll::Pending,
+        };
+        this.done = item.is_err();
+        Poll::Ready(Some(item))
+    }
+}
+
+/// A future that writes an encoded item to an `AsyncWrite`.
+///
+/// Created by `to_async_writer`.
+pub struct WriteItem<W> {
+    writer: W,
+    buf: Vec<u8>,
+    pos: usize,
+    error: Option<Error>,
+}
+
+impl<W> Future for WriteItem<W>
+where
+    W: AsyncWrite + Unpin,
+{
+    type Output = Result<()>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<()>> {
+        let this = self.get_mut();
+        if let Some(error) = this.error.take() {
+            return Poll::Ready(Err(error));
+        }
+        while this.pos < this.buf.len() {
+            match Pin::new(&mut this.writer).poll_write(cx, &this.buf[this.pos..]) {
+                Poll::Ready(Ok(0)) => {
+                    return Poll::Ready(Err(Error::io(::std::io::ErrorKind::WriteZero.into())));
+                }
+                Poll::Ready(Ok(n)) => this.pos += n,
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(Error::io(e))),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+        match Pin::new(&mut this.writer).poll_flush(cx) {
+            Poll::Ready(result) => Poll::Ready(result.map_err(Error::io)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
@@ -0,0 +1,42 @@
+//! Generates Rust types from a CDDL document.
+//!
+//! Usage: `cddl-codegen [FILE]`. The document is read from `FILE` or, if it is missing, from
+//! standard input. The generated code is written to standard output.
+
+extern crate serde_cbor;
+
+use std::env;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::process;
+
+use serde_cbor::cddl::Schema;
+
+fn run() -> Result<String, String> {
+    let mut args = env::args().skip(1);
+    let mut cddl = String::new();
+    match (args.next(), args.next()) {
+        (None, _) => io::stdin()
+            .read_to_string(&mut cddl)
+            .map_err(|e| format!("cannot read standard input: {}", e))?,
+        (Some(path), None) => File::open(&path)
+            .and_then(|mut file| file.read_to_string(&mut cddl))
+            .map_err(|e| format!("cannot read {}: {}", path, e))?,
+        (Some(_), Some(_)) => return Err("usage: cddl-codegen [FILE]".to_owned()),
+    };
+    let schema = Schema::parse(&cddl).map_err(|e| e.to_string())?;
+    schema.to_rust().map_err(|e| e.to_string())
+}
+
+fn main() {
+    match run() {
+        Ok(code) => {
+            let stdout = io::stdout();
+            let _ = stdout.lock().write_all(code.as_bytes());
+        }
+        Err(e) => {
+            eprintln!("cddl-codegen: {}", e);
+            process::exit(1);
+        }
+    }
+}
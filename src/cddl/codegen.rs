@@ -0,0 +1,1152 @@
+//! Generation of Rust types from CDDL rules.
+
+use std::collections::HashSet;
+use std::fmt::Write;
+
+use super::parse::{
+    EntryKind, Group, GroupEntry, Literal, Occur, Operator, Rule, RuleBody, Rules, Type, Type1,
+    Type2,
+};
+use super::{GenerateError, Schema};
+
+const BYTES: &str = "::serde_bytes::ByteBuf";
+const VALUE: &str = "::serde_cbor::Value";
+const OBJECT_KEY: &str = "::serde_cbor::ObjectKey";
+const MAP: &str = "::std::collections::BTreeMap";
+
+const KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "crate",
+    "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "if", "impl", "in",
+    "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref",
+    "return", "self", "static", "struct", "super", "trait", "true", "try", "type", "typeof",
+    "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+impl Schema {
+    /// Generates Rust types for the rules of the document.
+    ///
+    /// Every type rule becomes a struct, an enum, a type alias or, for rules that name a single
+    /// value, a constant. Group rules are inlined where they are used. The generated types
+    /// serialize exactly as described by the rules when used with `to_vec` and `from_slice`,
+    /// as long as the serializer is not in packed mode:
+    ///
+    /// * Maps with text keys become structs deriving `Serialize` and `Deserialize`. Maps with
+    ///   integer keys become structs with hand-written implementations that use the integers
+    ///   as keys. A field is named after the rule that defines its key, like `alg` for
+    ///   `? alg => int` with `alg = 1`, or after the key itself.
+    /// * Arrays of a single repeated type become vectors, all other arrays tuple structs.
+    ///   Vectors whose length is restricted, like for `[+ label]`, are wrapped in a tuple
+    ///   struct that checks the length when serializing and deserializing.
+    /// * Choices between text values become enums, choices between integer values enums
+    ///   with hand-written implementations, and other choices untagged enums.
+    ///   A choice between `null` and one other type becomes an `Option`.
+    /// * Control operators restrict values, but not their types, and are ignored.
+    ///
+    /// The generated code expects the `Serialize` and `Deserialize` derive macros to be in
+    /// scope and the `serde`, `serde_bytes` and `serde_cbor` crates to be available.
+    ///
+    /// Tags, group choices inside of maps and optional array elements are not supported.
+    pub fn to_rust(&self) -> Result<String, GenerateError> {
+        let mut generator = Generator {
+            rules: &self.rules,
+            items: Vec::new(),
+            names: HashSet::new(),
+        };
+        for rule in self.rules.rules.iter().filter(|rule| !rule.prelude) {
+            generator.names.insert(camel_case(&rule.name));
+        }
+        for rule in self.rules.rules.iter().filter(|rule| !rule.prelude) {
+            if let Err(message) = generator.rule(rule) {
+                return Err(GenerateError::new(rule.name.clone(), message));
+            }
+        }
+
+        let mut out = String::from("// Generated from CDDL by serde_cbor. Do not edit.\n");
+        for item in generator.items {
+            out.push('\n');
+            out.push_str(&item);
+        }
+        Ok(out)
+    }
+}
+
+struct Field {
+    name: String,
+    key: Literal,
+    ty: String,
+    optional: bool,
+}
+
+struct Generator<'a> {
+    rules: &'a Rules,
+    items: Vec<String>,
+    /// The names of all generated types.
+    names: HashSet<String>,
+}
+
+impl<'a> Generator<'a> {
+    fn rule(&mut self, rule: &'a Rule) -> Result<(), String> {
+        let ty = match rule.body {
+            RuleBody::Type(ref ty) => ty,
+            RuleBody::Group(_) => return Ok(()),
+        };
+        let doc = format!("/// Generated from the `{}` rule.\n", rule.name);
+        if let Some(Type2::Value(ref literal)) = ty.single() {
+            let (ty, value) = match *literal {
+                Literal::Int(v) => ("i64", v.to_string()),
+                Literal::Float(v) => ("f64", format!("{:?}", v)),
+                Literal::Text(ref v) => ("&str", format!("{:?}", v)),
+                Literal::Bytes(ref v) => ("&[u8]", format!("&{:?}", v)),
+            };
+            let name = snake_case(&rule.name).to_uppercase();
+            self.items
+                .push(format!("{}pub const {}: {} = {};\n", doc, name, ty, value));
+            return Ok(());
+        }
+        self.define(&camel_case(&rule.name), ty, &doc)
+    }
+
+    /// Generates an item with the given name for a type.
+    fn define(&mut self, name: &str, ty: &'a Type, doc: &str) -> Result<(), String> {
+        if let [Type1 { ref base, op: None }] = ty.0[..] {
+            match *base {
+                Type2::Map(ref group) => return self.map(name, group, doc),
+                Type2::Array(ref group) => match self.homogeneous(group) {
+                    Some((occur, element)) if is_bounded(occur) => {
+                        return self.bounded_vec(name, occur, element, doc);
+                    }
+                    Some(_) => {}
+                    None => return self.tuple(name, group, doc),
+                },
+                Type2::ChoiceFrom(ref group) => {
+                    let mut variants = Vec::new();
+                    self.enumeration(group, &mut variants, 0)?;
+                    return self.literal_enum(name, variants, doc);
+                }
+                _ => {}
+            }
+        }
+        if ty.0.len() > 1 && self.optional(ty).is_none() {
+            return self.choice(name, ty, doc);
+        }
+        let target = self.rust_type(ty, name)?;
+        self.items
+            .push(format!("{}pub type {} = {};\n", doc, name, target));
+        Ok(())
+    }
+
+    /// Returns an unused type name based on the given one.
+    fn fresh(&mut self, base: &str) -> String {
+        let mut name = base.to_owned();
+        let mut i = 2;
+        while self.names.contains(&name) {
+            name = format!("{}{}", base, i);
+            i += 1;
+        }
+        self.names.insert(name.clone());
+        name
+    }
+
+    fn rule_type(&self, name: &str) -> Option<&'a Type> {
+        match self.rules.get(name) {
+            Some(&Rule {
+                body: RuleBody::Type(ref ty),
+                ..
+            }) => Some(ty),
+            _ => None,
+        }
+    }
+
+    /// Resolves a type used as a group entry to the group it names, if any.
+    fn as_group(&self, ty: &'a Type) -> Option<&'a Group> {
+        match ty.single() {
+            Some(Type2::Name(name)) => match self.rules.get(name) {
+                Some(&Rule {
+                    body: RuleBody::Group(ref group),
+                    ..
+                }) => Some(group),
+                _ => None,
+            },
+            Some(Type2::Unwrap(name)) => match self.rule_type(name).and_then(Type::single) {
+                Some(Type2::Map(group)) | Some(Type2::Array(group)) => Some(group),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Returns the literal a type stands for together with the name of the rule defining it.
+    fn literal(&self, ty: &'a Type2) -> Option<(&'a Literal, Option<&'a str>)> {
+        match *ty {
+            Type2::Value(ref literal) => Some((literal, None)),
+            Type2::Name(ref name) => match self.rule_type(name).and_then(Type::single) {
+                Some(Type2::Value(literal)) => Some((literal, Some(name))),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Returns the other type of a choice between `null` and one other type.
+    fn optional(&self, ty: &'a Type) -> Option<&'a Type1> {
+        match ty.0[..] {
+            [ref a, ref b] if is_null(a) => Some(b),
+            [ref a, ref b] if is_null(b) => Some(a),
+            _ => None,
+        }
+    }
+
+    /// Returns the occurrence and element type of an array of a single repeated type.
+    fn homogeneous(&self, group: &'a Group) -> Option<(Occur, &'a Type)> {
+        match group.0[..] {
+            [ref entries] => match entries[..] {
+                [GroupEntry {
+                    occur,
+                    kind: EntryKind::Member(_, ref ty),
+                }] if occur != Occur::ONE && self.as_group(ty).is_none() => Some((occur, ty)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn rust_type(&mut self, ty: &'a Type, context: &str) -> Result<String, String> {
+        if let Some(other) = self.optional(ty) {
+            return Ok(format!("Option<{}>", self.rust_type1(other, context)?));
+        }
+        if ty.0.len() > 1 {
+            let name = self.fresh(context);
+            self.choice(&name, ty, "")?;
+            return Ok(name);
+        }
+        self.rust_type1(&ty.0[0], context)
+    }
+
+    fn rust_type1(&mut self, ty: &'a Type1, context: &str) -> Result<String, String> {
+        if let Some(Operator::Range(ref end, _)) = ty.op {
+            let bounds = (self.literal(&ty.base), self.literal(end));
+            return match bounds {
+                (Some((&Literal::Int(start), _)), Some((&Literal::Int(end), _))) => {
+                    Ok(int_type(start, end).to_owned())
+                }
+                (Some((&Literal::Float(_), _)), Some((&Literal::Float(_), _))) => {
+                    Ok("f64".to_owned())
+                }
+                _ => Err(format!("unsupported range `{}`", ty)),
+            };
+        }
+        self.rust_type2(&ty.base, context)
+    }
+
+    fn rust_type2(&mut self, ty: &'a Type2, context: &str) -> Result<String, String> {
+        let ty = match *ty {
+            Type2::Value(ref literal) => literal_type(literal).to_owned(),
+            Type2::Name(ref name) => return self.named(name),
+            Type2::Paren(ref ty) => return self.rust_type(ty, context),
+            Type2::Map(ref group) => {
+                if let [ref entries] = group.0[..] {
+                    if let [GroupEntry {
+                        occur,
+                        kind: EntryKind::Member(Some(ref key), ref value),
+                    }] = entries[..]
+                    {
+                        if occur.min == 0 && self.literal(&key.ty.base).is_none() {
+                            let key = self.rust_type1(&key.ty, &format!("{}Key", context))?;
+                            let value = self.rust_type(value, &format!("{}Value", context))?;
+                            return Ok(format!("{}<{}, {}>", MAP, key, value));
+                        }
+                    }
+                }
+                let name = self.fresh(context);
+                self.map(&name, group, "")?;
+                name
+            }
+            Type2::Array(ref group) => match self.homogeneous(group) {
+                Some((occur, element)) if is_bounded(occur) => {
+                    let name = self.fresh(context);
+                    self.bounded_vec(&name, occur, element, "")?;
+                    name
+                }
+                Some((_, element)) => format!(
+                    "Vec<{}>",
+                    self.rust_type(element, &format!("{}Item", context))?
+                ),
+                None => {
+                    let name = self.fresh(context);
+                    self.tuple(&name, group, "")?;
+                    name
+                }
+            },
+            Type2::ChoiceFrom(ref group) => {
+                let name = self.fresh(context);
+                let mut variants = Vec::new();
+                self.enumeration(group, &mut variants, 0)?;
+                self.literal_enum(&name, variants, "")?;
+                name
+            }
+            Type2::Major(0, None) => "u64".to_owned(),
+            Type2::Major(1, None) => "i64".to_owned(),
+            Type2::Major(2, None) => BYTES.to_owned(),
+            Type2::Major(3, None) => "String".to_owned(),
+            Type2::Major(7, Some(20)) | Type2::Major(7, Some(21)) => "bool".to_owned(),
+            Type2::Major(7, Some(22)) => "()".to_owned(),
+            Type2::Major(7, Some(25)) | Type2::Major(7, Some(26)) => "f32".to_owned(),
+            Type2::Major(7, Some(27)) => "f64".to_owned(),
+            Type2::Any => VALUE.to_owned(),
+            _ => return Err(format!("`{}` is not supported", ty)),
+        };
+        Ok(ty)
+    }
+
+    fn named(&mut self, name: &str) -> Result<String, String> {
+        let rule = match self.rules.get(name) {
+            Some(rule) => rule,
+            None => return Err(format!("socket `{}` has no definitions", name)),
+        };
+        if rule.prelude {
+            let ty = match name {
+                "uint" => "u64",
+                "nint" | "int" => "i64",
+                "bool" | "true" | "false" => "bool",
+                "tstr" | "text" => "String",
+                "bstr" | "bytes" => BYTES,
+                "float16" | "float32" | "float16-32" => "f32",
+                "float64" | "float32-64" | "float" => "f64",
+                "any" | "number" => VALUE,
+                "null" | "nil" => "()",
+                _ => return Err(format!("`{}` is not supported", name)),
+            };
+            return Ok(ty.to_owned());
+        }
+        match rule.body {
+            RuleBody::Type(ref ty) => match ty.single() {
+                Some(Type2::Value(literal)) => Ok(literal_type(literal).to_owned()),
+                _ => Ok(camel_case(name)),
+            },
+            RuleBody::Group(_) => Err(format!("group `{}` used as a type", name)),
+        }
+    }
+
+    fn map(&mut self, name: &str, group: &'a Group, doc: &str) -> Result<(), String> {
+        let entries = match group.0[..] {
+            [ref entries] => entries,
+            _ => return Err("group choices inside of maps are not supported".to_owned()),
+        };
+        let mut fields = Vec::new();
+        let mut extra = None;
+        self.fields(name, entries, &mut fields, &mut extra, 0)?;
+
+        let item = if fields.iter().all(|field| is_text(&field.key)) {
+            text_keyed_struct(name, &fields, extra, doc)
+        } else {
+            integer_keyed_struct(name, &fields, extra, doc)
+        };
+        self.items.push(item);
+        Ok(())
+    }
+
+    fn fields(
+        &mut self,
+        name: &str,
+        entries: &'a [GroupEntry],
+        fields: &mut Vec<Field>,
+        extra: &mut Option<(String, String)>,
+        depth: usize,
+    ) -> Result<(), String> {
+        if depth > 64 {
+            return Err("recursive group".to_owned());
+        }
+        for entry in entries {
+            let (key, value) = match entry.kind {
+                EntryKind::Member(Some(ref key), ref value) => (key, value),
+                EntryKind::Member(None, ref ty) => {
+                    let group = match self.as_group(ty) {
+                        Some(group) => group,
+                        // A group socket without definitions adds nothing.
+                        None if is_socket(ty) => continue,
+                        None => return Err(format!("map entry `{}` has no key", ty)),
+                    };
+                    match group.0[..] {
+                        [ref inner] if entry.occur == Occur::ONE => {
+                            self.fields(name, inner, fields, extra, depth + 1)?;
+                            continue;
+                        }
+                        _ => return Err(format!("`{}` is not supported inside of maps", entry)),
+                    }
+                }
+                EntryKind::Group(ref group) => match group.0[..] {
+                    [ref inner] if entry.occur == Occur::ONE => {
+                        self.fields(name, inner, fields, extra, depth + 1)?;
+                        continue;
+                    }
+                    _ => return Err(format!("`{}` is not supported inside of maps", entry)),
+                },
+            };
+
+            let literal = match key.ty.op {
+                None => self.literal(&key.ty.base),
+                Some(_) => None,
+            };
+            let (literal, rule) = match literal {
+                Some(literal) => literal,
+                None => {
+                    if extra.is_some() {
+                        return Err("more than one map entry without a fixed key".to_owned());
+                    }
+                    let key = self.rust_type1(&key.ty, &format!("{}Key", name))?;
+                    let value = self.rust_type(value, &format!("{}Value", name))?;
+                    *extra = Some((key, value));
+                    continue;
+                }
+            };
+            let optional = match entry.occur {
+                Occur { min: 1, max: 1 } => false,
+                Occur { min: 0, max: 1 } => true,
+                _ => return Err(format!("`{}` may occur more than once", key.ty)),
+            };
+            let field_name = match (rule, literal) {
+                (Some(rule), _) => snake_case(rule),
+                (None, Literal::Text(text)) => snake_case(text),
+                (None, &Literal::Int(v)) if v < 0 => format!("field_minus_{}", -v),
+                (None, &Literal::Int(v)) => format!("field_{}", v),
+                _ => return Err(format!("unsupported map key `{}`", literal)),
+            };
+            if let Literal::Int(v) = *literal {
+                if !is_i64(literal) {
+                    return Err(format!("map key `{}` is out of range", v));
+                }
+            }
+            if fields.iter().any(|field: &Field| field.name == field_name) {
+                return Err(format!("more than one field named `{}`", field_name));
+            }
+            let ty = self.rust_type(value, &format!("{}{}", name, camel_case(&field_name)))?;
+            fields.push(Field {
+                name: field_name,
+                key: literal.clone(),
+                ty,
+                optional,
+            });
+        }
+        Ok(())
+    }
+
+    /// Generates a tuple struct around a vector whose length is restricted by `occur`.
+    fn bounded_vec(
+        &mut self,
+        name: &str,
+        occur: Occur,
+        element: &'a Type,
+        doc: &str,
+    ) -> Result<(), String> {
+        let element = self.rust_type(element, &format!("{}Item", name))?;
+        let (condition, expected) = if occur.max == u64::MAX {
+            (
+                format!("len < {}", occur.min),
+                format!("at least {}", count_items(occur.min)),
+            )
+        } else if occur.min == occur.max {
+            (
+                format!("len != {}", occur.min),
+                format!("exactly {}", count_items(occur.min)),
+            )
+        } else {
+            (
+                format!("len < {} || len > {}", occur.min, occur.max),
+                format!("{} to {} items", occur.min, occur.max),
+            )
+        };
+
+        let mut item = format!(
+            "{}#[derive(Clone, Debug, PartialEq)]\npub struct {}(\n",
+            doc, name
+        );
+        let _ = writeln!(item, "    /// Must hold {}.", expected);
+        let _ = write!(
+            item,
+            "    pub Vec<{element}>,
+);
+
+impl ::serde::Serialize for {name} {{
+    fn serialize<S>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error>
+    where
+        S: ::serde::Serializer,
+    {{
+        let len = self.0.len();
+        if {condition} {{
+            return Err(::serde::ser::Error::custom(format_args!(
+                \"invalid length {{}}, expected {expected}\",
+                len
+            )));
+        }}
+        ::serde::Serialize::serialize(&self.0, serializer)
+    }}
+}}
+
+impl<'de> ::serde::Deserialize<'de> for {name} {{
+    fn deserialize<D>(deserializer: D) -> ::std::result::Result<{name}, D::Error>
+    where
+        D: ::serde::Deserializer<'de>,
+    {{
+        let items: Vec<{element}> = ::serde::Deserialize::deserialize(deserializer)?;
+        let len = items.len();
+        if {condition} {{
+            return Err(::serde::de::Error::invalid_length(len, &\"{expected}\"));
+        }}
+        Ok({name}(items))
+    }}
+}}
+",
+            element = element,
+            name = name,
+            condition = condition,
+            expected = expected
+        );
+        self.items.push(item);
+        Ok(())
+    }
+
+    fn tuple(&mut self, name: &str, group: &'a Group, doc: &str) -> Result<(), String> {
+        let entries = match group.0[..] {
+            [ref entries] => entries,
+            _ => return Err("group choices inside of arrays are not supported".to_owned()),
+        };
+        let mut elements = Vec::new();
+        self.elements(name, entries, &mut elements, 0)?;
+
+        let item = match elements.len() {
+            0 => return Err("empty arrays are not supported".to_owned()),
+            // A tuple struct with one field would be serialized as its content.
+            1 => format!("{}pub type {} = ({},);\n", doc, name, elements[0].1),
+            _ => {
+                let mut item = format!(
+                    "{}#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]\npub struct {}(\n",
+                    doc, name
+                );
+                for (label, ty) in elements {
+                    if let Some(label) = label {
+                        let _ = writeln!(item, "    /// `{}`", label);
+                    }
+                    let _ = writeln!(item, "    pub {},", ty);
+                }
+                item.push_str(");\n");
+                item
+            }
+        };
+        self.items.push(item);
+        Ok(())
+    }
+
+    fn elements(
+        &mut self,
+        name: &str,
+        entries: &'a [GroupEntry],
+        elements: &mut Vec<(Option<String>, String)>,
+        depth: usize,
+    ) -> Result<(), String> {
+        if depth > 64 {
+            return Err("recursive group".to_owned());
+        }
+        for entry in entries {
+            if entry.occur != Occur::ONE {
+                return Err(format!("`{}` is not supported inside of arrays", entry));
+            }
+            let (key, ty) = match entry.kind {
+                EntryKind::Member(ref key, ref ty) => match self.as_group(ty) {
+                    Some(group) => match group.0[..] {
+                        [ref inner] => {
+                            self.elements(name, inner, elements, depth + 1)?;
+                            continue;
+                        }
+                        _ => return Err(format!("`{}` is not supported inside of arrays", entry)),
+                    },
+                    None => (key, ty),
+                },
+                EntryKind::Group(ref group) => match group.0[..] {
+                    [ref inner] => {
+                        self.elements(name, inner, elements, depth + 1)?;
+                        continue;
+                    }
+                    _ => return Err(format!("`{}` is not supported inside of arrays", entry)),
+                },
+            };
+            let label = key.as_ref().map(|key| match key.ty.base {
+                Type2::Value(Literal::Text(ref text)) => text.clone(),
+                _ => key.ty.to_string(),
+            });
+            let context = match label {
+                Some(ref label) => format!("{}{}", name, camel_case(label)),
+                None => format!("{}{}", name, elements.len()),
+            };
+            let ty = self.rust_type(ty, &context)?;
+            elements.push((label, ty));
+        }
+        Ok(())
+    }
+
+    /// Collects the values of `&(...)`, named after their keys.
+    fn enumeration(
+        &self,
+        group: &'a Group,
+        variants: &mut Vec<(Option<String>, &'a Literal)>,
+        depth: usize,
+    ) -> Result<(), String> {
+        if depth > 64 {
+            return Err("recursive group".to_owned());
+        }
+        for entry in group.0.iter().flatten() {
+            let (key, ty) = match entry.kind {
+                EntryKind::Member(ref key, ref ty) => (key, ty),
+                EntryKind::Group(ref group) => {
+                    self.enumeration(group, variants, depth + 1)?;
+                    continue;
+                }
+            };
+            if key.is_none() {
+                if let Some(group) = self.as_group(ty) {
+                    self.enumeration(group, variants, depth + 1)?;
+                    continue;
+                }
+            }
+            let literal = match ty.single().and_then(|ty| self.literal(ty)) {
+                Some(literal) => literal,
+                None => return Err(format!("`{}` is not a value", ty)),
+            };
+            let name = match key {
+                Some(key) => match key.ty.base {
+                    Type2::Value(Literal::Text(ref text)) => Some(text.clone()),
+                    _ => None,
+                },
+                None => literal.1.map(str::to_owned),
+            };
+            variants.push((name, literal.0));
+        }
+        Ok(())
+    }
+
+    fn choice(&mut self, name: &str, ty: &'a Type, doc: &str) -> Result<(), String> {
+        let literals: Option<Vec<_>> =
+            ty.0.iter()
+                .map(|choice| match choice.op {
+                    None => self
+                        .literal(&choice.base)
+                        .map(|(literal, rule)| (rule.map(str::to_owned), literal)),
+                    Some(_) => None,
+                })
+                .collect();
+        if let Some(variants) = literals {
+            return self.literal_enum(name, variants, doc);
+        }
+
+        let mut item = format!(
+            "{}#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]\n#[serde(untagged)]\npub enum {} {{\n",
+            doc, name
+        );
+        let mut variants = HashSet::new();
+        for (i, choice) in ty.0.iter().enumerate() {
+            let base = match (&choice.base, &choice.op) {
+                (Type2::Name(name), None) => camel_case(name),
+                (Type2::Map(_), _) => "Map".to_owned(),
+                (Type2::Array(_), _) => "Array".to_owned(),
+                _ => format!("Variant{}", i),
+            };
+            let mut variant = base.clone();
+            let mut n = 2;
+            while !variants.insert(variant.clone()) {
+                variant = format!("{}{}", base, n);
+                n += 1;
+            }
+            if is_null(choice) {
+                let _ = writeln!(item, "    {},", variant);
+                continue;
+            }
+            if self.literal(&choice.base).is_some() {
+                return Err(format!("value `{}` in a choice between types", choice));
+            }
+            let ty = self.rust_type1(choice, &format!("{}{}", name, variant))?;
+            let _ = writeln!(item, "    {}({}),", variant, ty);
+        }
+        item.push_str("}\n");
+        self.items.push(item);
+        Ok(())
+    }
+
+    fn literal_enum(
+        &mut self,
+        name: &str,
+        variants: Vec<(Option<String>, &Literal)>,
+        doc: &str,
+    ) -> Result<(), String> {
+        let mut names = HashSet::new();
+        let mut named = Vec::new();
+        for (i, (variant, literal)) in variants.into_iter().enumerate() {
+            let variant = match (variant, literal) {
+                (Some(variant), _) => camel_case(&variant),
+                (None, Literal::Text(text)) => camel_case(text),
+                (None, &Literal::Int(v)) if v < 0 => format!("ValueMinus{}", -v),
+                (None, &Literal::Int(v)) => format!("Value{}", v),
+                (None, _) => format!("Variant{}", i),
+            };
+            if variant.is_empty() || !names.insert(variant.clone()) {
+                return Err(format!("cannot name the variant for `{}`", literal));
+            }
+            named.push((variant, literal));
+        }
+
+        let mut item = String::from(doc);
+        if named.iter().all(|&(_, literal)| is_text(literal)) {
+            let _ = write!(
+                item,
+                "#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]\npub enum {} {{\n",
+                name
+            );
+            for (variant, literal) in named {
+                let _ = writeln!(item, "    #[serde(rename = {})]\n    {},", literal, variant);
+            }
+            item.push_str("}\n");
+        } else if named.iter().all(|&(_, literal)| is_i64(literal)) {
+            let _ = writeln!(
+                item,
+                "#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]\npub enum {} {{",
+                name
+            );
+            for (variant, _) in &named {
+                let _ = writeln!(item, "    {},", variant);
+            }
+            item.push_str("}\n");
+            integer_enum_impls(&mut item, name, &named);
+        } else {
+            return Err(
+                "only choices between text or between integer values are supported".to_owned(),
+            );
+        }
+        self.items.push(item);
+        Ok(())
+    }
+}
+
+fn is_null(ty: &Type1) -> bool {
+    match (&ty.base, &ty.op) {
+        (Type2::Name(name), None) => name == "null" || name == "nil",
+        (Type2::Major(7, Some(22)), None) => true,
+        _ => false,
+    }
+}
+
+fn is_text(literal: &Literal) -> bool {
+    match *literal {
+        Literal::Text(_) => true,
+        _ => false,
+    }
+}
+
+fn is_i64(literal: &Literal) -> bool {
+    match *literal {
+        Literal::Int(v) => v >= i128::from(i64::MIN) && v <= i128::from(i64::MAX),
+        _ => false,
+    }
+}
+
+fn is_socket(ty: &Type) -> bool {
+    match ty.single() {
+        Some(Type2::Name(name)) => name.starts_with('$'),
+        _ => false,
+    }
+}
+
+fn literal_type(literal: &Literal) -> &'static str {
+    match *literal {
+        Literal::Int(_) => "i64",
+        Literal::Float(_) => "f64",
+        Literal::Text(_) => "String",
+        Literal::Bytes(_) => BYTES,
+    }
+}
+
+/// The smallest integer type holding all values of a range.
+fn int_type(start: i128, end: i128) -> &'static str {
+    let fits = |min: i128, max: i128| start >= min && end <= max;
+    if fits(0, i128::from(u8::MAX)) {
+        "u8"
+    } else if fits(0, i128::from(u16::MAX)) {
+        "u16"
+    } else if fits(0, i128::from(u32::MAX)) {
+        "u32"
+    } else if fits(0, i128::from(u64::MAX)) {
+        "u64"
+    } else if fits(i128::from(i8::MIN), i128::from(i8::MAX)) {
+        "i8"
+    } else if fits(i128::from(i16::MIN), i128::from(i16::MAX)) {
+        "i16"
+    } else if fits(i128::from(i32::MIN), i128::from(i32::MAX)) {
+        "i32"
+    } else {
+        "i64"
+    }
+}
+
+fn camel_case(name: &str) -> String {
+    let mut out = String::new();
+    for word in name.split(|c: char| !c.is_alphanumeric()) {
+        // Acronyms like `COSE` are written as `Cose`.
+        let acronym = !word.chars().any(char::is_lowercase);
+        let mut chars = word.chars();
+        if let Some(first) = chars.next() {
+            out.extend(first.to_uppercase());
+            for c in chars {
+                if acronym {
+                    out.extend(c.to_lowercase());
+                } else {
+                    out.push(c);
+                }
+            }
+        }
+    }
+    if out.starts_with(|c: char| c.is_numeric()) {
+        out.insert(0, 'T');
+    }
+    out
+}
+
+fn snake_case(name: &str) -> String {
+    let mut out = String::new();
+    let mut lower = false;
+    for c in name.chars() {
+        if c.is_uppercase() {
+            if lower {
+                out.push('_');
+            }
+            out.extend(c.to_lowercase());
+            lower = false;
+        } else if c.is_alphanumeric() {
+            out.push(c);
+            lower = true;
+        } else {
+            if !out.is_empty() && !out.ends_with('_') {
+                out.push('_');
+            }
+            lower = false;
+        }
+    }
+    while out.ends_with('_') {
+        out.pop();
+    }
+    if out.is_empty() || out.starts_with(|c: char| c.is_numeric()) {
+        out.insert_str(0, "field_");
+    }
+    if KEYWORDS.contains(&&out[..]) {
+        out.push('_');
+    }
+    out
+}
+
+fn text_keyed_struct(
+    name: &str,
+    fields: &[Field],
+    extra: Option<(String, String)>,
+    doc: &str,
+) -> String {
+    let mut item = format!(
+        "{}#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]\n",
+        doc
+    );
+    // CDDL maps are closed unless they say otherwise.
+    if extra.is_none() {
+        item.push_str("#[serde(deny_unknown_fields)]\n");
+    }
+    let _ = writeln!(item, "pub struct {} {{", name);
+    for field in fields {
+        let mut attributes = Vec::new();
+        if let Literal::Text(ref key) = field.key {
+            if *key != field.name {
+                attributes.push(format!("rename = {:?}", key));
+            }
+        }
+        if field.optional {
+            attributes.push("default".to_owned());
+            attributes.push("skip_serializing_if = \"Option::is_none\"".to_owned());
+        }
+        if !attributes.is_empty() {
+            let _ = writeln!(item, "    #[serde({})]", attributes.join(", "));
+        }
+        let _ = writeln!(item, "    pub {}: {},", field.name, field_type(field));
+    }
+    if let Some((key, value)) = extra {
+        let _ = writeln!(
+            item,
+            "    #[serde(flatten)]\n    pub extra: {}<{}, {}>,",
+            MAP, key, value
+        );
+    }
+    item.push_str("}\n");
+    item
+}
+
+fn field_type(field: &Field) -> String {
+    if field.optional {
+        format!("Option<{}>", field.ty)
+    } else {
+        field.ty.clone()
+    }
+}
+
+fn key_expr(key: &Literal) -> String {
+    match *key {
+        Literal::Int(v) => format!("{}i64", v),
+        _ => key.to_string(),
+    }
+}
+
+fn key_pattern(key: &Literal) -> String {
+    match *key {
+        Literal::Int(v) => format!("{}::Integer({})", OBJECT_KEY, v),
+        _ => format!("{}::String(ref key) if key == {}", OBJECT_KEY, key),
+    }
+}
+
+fn count_items(n: u64) -> String {
+    match n {
+        1 => "1 item".to_owned(),
+        n => format!("{} items", n),
+    }
+}
+
+/// Whether an occurrence restricts the number of items.
+fn is_bounded(occur: Occur) -> bool {
+    occur.min > 0 || occur.max != u64::MAX
+}
+
+fn integer_keyed_struct(
+    name: &str,
+    fields: &[Field],
+    extra: Option<(String, String)>,
+    doc: &str,
+) -> String {
+    let mut item = format!(
+        "{}#[derive(Clone, Debug, PartialEq)]\npub struct {} {{\n",
+        doc, name
+    );
+    for field in fields {
+        let _ = writeln!(item, "    /// Map key `{}`.", field.key);
+        let _ = writeln!(item, "    pub {}: {},", field.name, field_type(field));
+    }
+    if let Some((_, ref value)) = extra {
+        let _ = writeln!(item, "    /// All other map entries.");
+        let _ = writeln!(item, "    pub extra: {}<{}, {}>,", MAP, OBJECT_KEY, value);
+    }
+    item.push_str("}\n\n");
+
+    let required = fields.iter().filter(|field| !field.optional).count();
+    let mut terms = Vec::new();
+    if required > 0 {
+        terms.push(required.to_string());
+    }
+    for field in fields.iter().filter(|field| field.optional) {
+        terms.push(format!("self.{}.is_some() as usize", field.name));
+    }
+    if extra.is_some() {
+        terms.push("self.extra.len()".to_owned());
+    }
+    let len = if terms.is_empty() {
+        "0".to_owned()
+    } else {
+        terms.join(" + ")
+    };
+
+    let _ = write!(
+        item,
+        "impl ::serde::Serialize for {} {{
+    fn serialize<S>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error>
+    where
+        S: ::serde::Serializer,
+    {{
+        use ::serde::ser::SerializeMap;
+
+        let mut map = serializer.serialize_map(Some({}))?;
+",
+        name, len
+    );
+    for field in fields {
+        if field.optional {
+            let _ = write!(
+                item,
+                "        if let Some(ref value) = self.{} {{
+            map.serialize_entry(&{}, value)?;
+        }}
+",
+                field.name,
+                key_expr(&field.key)
+            );
+        } else {
+            let _ = writeln!(
+                item,
+                "        map.serialize_entry(&{}, &self.{})?;",
+                key_expr(&field.key),
+                field.name
+            );
+        }
+    }
+    if extra.is_some() {
+        item.push_str(
+            "        for (key, value) in &self.extra {
+            map.serialize_entry(key, value)?;
+        }
+",
+        );
+    }
+    item.push_str("        map.end()\n    }\n}\n\n");
+
+    let _ = write!(
+        item,
+        "impl<'de> ::serde::Deserialize<'de> for {name} {{
+    fn deserialize<D>(deserializer: D) -> ::std::result::Result<{name}, D::Error>
+    where
+        D: ::serde::Deserializer<'de>,
+    {{
+        struct Visitor;
+
+        impl<'de> ::serde::de::Visitor<'de> for Visitor {{
+            type Value = {name};
+
+            fn expecting(&self, formatter: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {{
+                formatter.write_str(\"a {name} map\")
+            }}
+
+            fn visit_map<A>(self, mut map: A) -> ::std::result::Result<{name}, A::Error>
+            where
+                A: ::serde::de::MapAccess<'de>,
+            {{
+",
+        name = name
+    );
+    for field in fields {
+        let _ = writeln!(item, "                let mut {} = None;", field.name);
+    }
+    if extra.is_some() {
+        let _ = writeln!(item, "                let mut extra = {}::new();", MAP);
+    }
+    let _ = write!(
+        item,
+        "                while let Some(key) = map.next_key::<{}>()? {{
+                    match key {{
+",
+        OBJECT_KEY
+    );
+    for field in fields {
+        let _ = write!(
+            item,
+            "                        {pattern} => {{
+                            if {field}.is_some() {{
+                                return Err(::serde::de::Error::duplicate_field({key:?}));
+                            }}
+                            {field} = Some(map.next_value()?);
+                        }}
+",
+            pattern = key_pattern(&field.key),
+            field = field.name,
+            key = field.key.to_string()
+        );
+    }
+    if extra.is_some() {
+        item.push_str(
+            "                        key => {
+                            extra.insert(key, map.next_value()?);
+                        }
+",
+        );
+    } else {
+        item.push_str(
+            "                        key => {
+                            return Err(::serde::de::Error::custom(format_args!(
+                                \"unexpected map key {:?}\",
+                                key
+                            )));
+                        }
+",
+        );
+    }
+    item.push_str("                    }\n                }\n");
+    let _ = writeln!(item, "                Ok({} {{", name);
+    for field in fields {
+        if field.optional {
+            let _ = writeln!(item, "                    {},", field.name);
+        } else {
+            let _ = writeln!(
+                item,
+                "                    {}: {}.ok_or_else(|| ::serde::de::Error::missing_field({:?}))?,",
+                field.name,
+                field.name,
+                field.key.to_string()
+            );
+        }
+    }
+    if extra.is_some() {
+        item.push_str("                    extra,\n");
+    }
+    item.push_str(
+        "                })
+            }
+        }
+
+        deserializer.deserialize_map(Visitor)
+    }
+}
+",
+    );
+    item
+}
+
+fn integer_enum_impls(item: &mut String, name: &str, variants: &[(String, &Literal)]) {
+    let _ = write!(
+        item,
+        "
+impl ::serde::Serialize for {name} {{
+    fn serialize<S>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error>
+    where
+        S: ::serde::Serializer,
+    {{
+        serializer.serialize_i64(match *self {{
+",
+        name = name
+    );
+    for &(ref variant, literal) in variants {
+        let _ = writeln!(item, "            {}::{} => {},", name, variant, literal);
+    }
+    let expected: Vec<_> = variants
+        .iter()
+        .map(|&(_, literal)| literal.to_string())
+        .collect();
+    let _ = write!(
+        item,
+        "        }})
+    }}
+}}
+
+impl<'de> ::serde::Deserialize<'de> for {name} {{
+    fn deserialize<D>(deserializer: D) -> ::std::result::Result<{name}, D::Error>
+    where
+        D: ::serde::Deserializer<'de>,
+    {{
+        match <i64 as ::serde::Deserialize>::deserialize(deserializer)? {{
+",
+        name = name
+    );
+    for &(ref variant, literal) in variants {
+        let _ = writeln!(
+            item,
+            "            {} => Ok({}::{}),",
+            literal, name, variant
+        );
+    }
+    let _ = write!(
+        item,
+        "            value => Err(::serde::de::Error::invalid_value(
+                ::serde::de::Unexpected::Signed(value),
+                &{:?},
+            )),
+        }}
+    }}
+}}
+",
+        format!("one of {}", expected.join(", "))
+    );
+}
@@ -0,0 +1,246 @@
+//! Validation of CBOR data against CDDL schemas.
+//!
+//! The Concise Data Definition Language (CDDL, [RFC 8610](https://tools.ietf.org/html/rfc8610))
+//! describes the structure of CBOR data. A `Schema` holds the rules of a CDDL document together
+//! with the standard prelude and checks whether data matches one of them.
+//!
+//! ```rust
+//! use serde_cbor::cddl::Schema;
+//!
+//! let schema = Schema::parse(r#"
+//!     person = {
+//!         name: tstr,
+//!         ? age: uint .le 150,
+//!         * tstr => any,
+//!     }
+//! "#).unwrap();
+//!
+//! // {"name": "Alice", "age": 200}
+//! let bytes = b"\xa2\x64name\x65Alice\x63age\x18\xc8";
+//! let error = schema.validate_slice(bytes, "person").unwrap_err();
+//! assert_eq!(error.path(), "/age");
+//! assert_eq!(error.rule(), "person");
+//! ```
+//!
+//! Groups, group and type choices, occurrence indicators, sockets, the unwrap and enumeration
+//! operators and the control operators `.size`, `.bits`, `.cbor`, `.cborseq`, `.regexp`, `.lt`,
+//! `.le`, `.gt`, `.ge`, `.eq`, `.ne`, `.default`, `.and` and `.within` are supported. `.regexp`,
+//! and its alias `.pcre`, need the `regex` feature. Generic rules are not supported.
+//!
+//! A `Value` carries no tags, so rules that demand a tag, like `tdate`, only ever match data
+//! validated with `validate_slice` or `validate_item`.
+//!
+//! `Schema::to_rust` turns the rules of a document into Rust types that serialize as the rules
+//! describe. The `cddl-codegen` binary does the same for a file.
+
+use std::error;
+use std::fmt;
+use std::str::FromStr;
+
+use syntax::{self, Item};
+use value::Value;
+
+mod codegen;
+mod parse;
+mod validate;
+
+use self::parse::Rules;
+use self::validate::Validator;
+
+/// The rules of a CDDL document.
+#[derive(Clone, Debug)]
+pub struct Schema {
+    rules: Rules,
+}
+
+impl Schema {
+    /// Parses a CDDL document.
+    ///
+    /// All names used in the document must be defined, either in the document or in the
+    /// prelude. Sockets (names starting with `$`) may be left without definitions.
+    pub fn parse(cddl: &str) -> Result<Schema, ParseError> {
+        Ok(Schema {
+            rules: Rules::parse(cddl)?,
+        })
+    }
+
+    /// Returns true if the document or the prelude defines a rule with the given name.
+    pub fn has_rule(&self, rule: &str) -> bool {
+        self.rules.get(rule).is_some()
+    }
+
+    /// Checks whether a value matches the type defined by a rule.
+    pub fn validate(&self, value: &Value, rule: &str) -> Result<(), ValidationError> {
+        self.validate_item(&Item::from_value(value), rule)
+    }
+
+    /// Checks whether a single encoded data item matches the type defined by a rule.
+    pub fn validate_slice(&self, bytes: &[u8], rule: &str) -> Result<(), ValidationError> {
+        match syntax::from_slice(bytes) {
+            Ok(item) => self.validate_item(&item, rule),
+            Err(e) => {
+                let message = format!("invalid CBOR: {}", e);
+                Err(ValidationError::new(
+                    "/".to_owned(),
+                    rule.to_owned(),
+                    message,
+                ))
+            }
+        }
+    }
+
+    /// Checks whether a data item matches the type defined by a rule.
+    pub fn validate_item(&self, item: &Item, rule: &str) -> Result<(), ValidationError> {
+        Validator::new(&self.rules).validate(item, rule)
+    }
+}
+
+impl FromStr for Schema {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Schema, ParseError> {
+        Schema::parse(s)
+    }
+}
+
+/// An error in a CDDL document.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    line: usize,
+    column: usize,
+    message: String,
+}
+
+impl ParseError {
+    fn new(src: &str, offset: usize, message: String) -> ParseError {
+        let before = &src[..offset];
+        let line = before.matches('\n').count() + 1;
+        let column = before.rfind('\n').map_or(offset, |i| offset - i - 1) + 1;
+        ParseError {
+            line,
+            column,
+            message,
+        }
+    }
+
+    /// The line of the document the error occurred on, starting at one.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// The column of the document the error occurred at, starting at one.
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
+    /// A description of the error.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} at line {} column {}",
+            self.message, self.line, self.column
+        )
+    }
+}
+
+impl error::Error for ParseError {
+    fn description(&self) -> &str {
+        &self.message
+    }
+}
+
+/// The reason why data does not match a rule.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ValidationError {
+    path: String,
+    rule: String,
+    message: String,
+}
+
+impl ValidationError {
+    fn new(path: String, rule: String, message: String) -> ValidationError {
+        ValidationError {
+            path,
+            rule,
+            message,
+        }
+    }
+
+    /// The location of the offending data item, like `/items/3/name`.
+    ///
+    /// Each segment is an array index or a map key; the root is `/`.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// The innermost rule of the document that the data item failed to match.
+    pub fn rule(&self) -> &str {
+        &self.rule
+    }
+
+    /// A description of the mismatch.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} at {} (rule `{}`)",
+            self.message, self.path, self.rule
+        )
+    }
+}
+
+impl error::Error for ValidationError {
+    fn description(&self) -> &str {
+        &self.message
+    }
+}
+
+/// The reason why no Rust type can be generated for a rule.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GenerateError {
+    rule: String,
+    message: String,
+}
+
+impl GenerateError {
+    fn new(rule: String, message: String) -> GenerateError {
+        GenerateError { rule, message }
+    }
+
+    /// The rule of the document that no type could be generated for.
+    pub fn rule(&self) -> &str {
+        &self.rule
+    }
+
+    /// A description of the problem.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for GenerateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "cannot generate a type for `{}`: {}",
+            self.rule, self.message
+        )
+    }
+}
+
+impl error::Error for GenerateError {
+    fn description(&self) -> &str {
+        &self.message
+    }
+}
@@ -0,0 +1,1310 @@
+//! Lexer, parser and syntax tree of CDDL.
+
+use std::collections::HashMap;
+use std::fmt;
+
+#[cfg(feature = "regex")]
+use regex::Regex;
+
+use super::ParseError;
+
+/// The standard prelude from appendix D of RFC 8610.
+const PRELUDE: &str = r#"
+any = #
+uint = #0
+nint = #1
+int = uint / nint
+bstr = #2
+bytes = bstr
+tstr = #3
+text = tstr
+tdate = #6.0(tstr)
+time = #6.1(number)
+number = int / float
+biguint = #6.2(bstr)
+bignint = #6.3(bstr)
+bigint = biguint / bignint
+integer = int / bigint
+unsigned = uint / biguint
+decfrac = #6.4([e10: int, m: integer])
+bigfloat = #6.5([e2: int, m: integer])
+eb64url = #6.21(any)
+eb64legacy = #6.22(any)
+eb16 = #6.23(any)
+encoded-cbor = #6.24(bstr)
+uri = #6.32(tstr)
+b64url = #6.33(tstr)
+b64legacy = #6.34(tstr)
+regexp = #6.35(tstr)
+mime-message = #6.36(tstr)
+cbor-any = #6.55799(any)
+float16 = #7.25
+float32 = #7.26
+float64 = #7.27
+float16-32 = float16 / float32
+float32-64 = float32 / float64
+float = float16-32 / float64
+false = #7.20
+true = #7.21
+bool = false / true
+nil = #7.22
+null = nil
+undefined = #7.23
+"#;
+
+/// A literal value.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Literal {
+    Int(i128),
+    Float(f64),
+    Text(String),
+    Bytes(Vec<u8>),
+}
+
+/// A choice between types: `a / b`.
+#[derive(Clone, Debug)]
+pub(crate) struct Type(pub Vec<Type1>);
+
+/// A type with an optional range or control operator: `a .size 4`.
+#[derive(Clone, Debug)]
+pub(crate) struct Type1 {
+    pub base: Type2,
+    pub op: Option<Operator>,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) enum Operator {
+    /// `..` when inclusive, `...` otherwise.
+    Range(Type2, bool),
+    Control(Control, Type2),
+}
+
+#[derive(Clone, Debug)]
+pub(crate) enum Control {
+    Size,
+    Bits,
+    Cbor,
+    Cborseq,
+    #[cfg(feature = "regex")]
+    Regexp(Regex),
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+    Default,
+    And,
+    Within,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) enum Type2 {
+    Value(Literal),
+    Name(String),
+    Paren(Type),
+    Map(Group),
+    Array(Group),
+    /// `~name`
+    Unwrap(String),
+    /// `&(group)` or `&name`
+    ChoiceFrom(Group),
+    /// `#6.n(type)`
+    Tag(Option<u64>, Box<Type>),
+    /// `#major.argument`
+    Major(u8, Option<u64>),
+    /// `#`
+    Any,
+}
+
+/// Choices between sequences of entries: `a, b // c`.
+#[derive(Clone, Debug)]
+pub(crate) struct Group(pub Vec<Vec<GroupEntry>>);
+
+#[derive(Clone, Debug)]
+pub(crate) struct GroupEntry {
+    pub occur: Occur,
+    pub kind: EntryKind,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) enum EntryKind {
+    /// A type, optionally preceded by a member key. A bare name may also refer to a group.
+    Member(Option<MemberKey>, Type),
+    /// A parenthesized group.
+    Group(Group),
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct MemberKey {
+    pub ty: Type1,
+    /// True for `^ =>` and for keys written as `name:` or `value:`.
+    pub cut: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Occur {
+    pub min: u64,
+    pub max: u64,
+}
+
+impl Occur {
+    pub const ONE: Occur = Occur { min: 1, max: 1 };
+}
+
+#[derive(Clone, Debug)]
+pub(crate) enum RuleBody {
+    Type(Type),
+    Group(Group),
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct Rule {
+    pub name: String,
+    pub body: RuleBody,
+    /// True for the rules of the standard prelude.
+    pub prelude: bool,
+}
+
+/// The rules of a schema in definition order, prelude first.
+#[derive(Clone, Debug)]
+pub(crate) struct Rules {
+    pub rules: Vec<Rule>,
+    index: HashMap<String, usize>,
+}
+
+impl Rules {
+    pub fn parse(src: &str) -> Result<Rules, ParseError> {
+        let mut rules = Rules {
+            rules: Vec::new(),
+            index: HashMap::new(),
+        };
+        Parser::new(PRELUDE)?.parse_rules(&mut rules, true)?;
+        Parser::new(src)?.parse_rules(&mut rules, false)?;
+        Ok(rules)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Rule> {
+        self.index.get(name).map(|&i| &self.rules[i])
+    }
+
+    fn define(
+        &mut self,
+        name: String,
+        assign: &Token,
+        entry: GroupEntry,
+        prelude: bool,
+    ) -> Result<(), String> {
+        let existing = self.index.get(&name).cloned();
+        match *assign {
+            Token::Assign => {
+                if existing.is_some() {
+                    return Err(format!("rule `{}` is defined more than once", name));
+                }
+                let body = match entry {
+                    GroupEntry {
+                        occur: Occur::ONE,
+                        kind: EntryKind::Group(group),
+                    } => RuleBody::Group(group),
+                    entry => match entry.bare_type() {
+                        Some(ty) => RuleBody::Type(ty.clone()),
+                        None => RuleBody::Group(Group(vec![vec![entry]])),
+                    },
+                };
+                self.index.insert(name.clone(), self.rules.len());
+                self.rules.push(Rule {
+                    name,
+                    body,
+                    prelude,
+                });
+            }
+            Token::TypeChoiceAssign => {
+                let ty = match entry.bare_type() {
+                    Some(ty) => ty.clone(),
+                    None => return Err(format!("`/=` for rule `{}` needs a type", name)),
+                };
+                match existing {
+                    Some(i) => match self.rules[i].body {
+                        RuleBody::Type(ref mut existing) => existing.0.extend(ty.0),
+                        RuleBody::Group(_) => {
+                            return Err(format!("`/=` used for group `{}`", name));
+                        }
+                    },
+                    None => {
+                        self.index.insert(name.clone(), self.rules.len());
+                        self.rules.push(Rule {
+                            name,
+                            body: RuleBody::Type(ty),
+                            prelude,
+                        });
+                    }
+                }
+            }
+            _ => {
+                let choices = match entry {
+                    GroupEntry {
+                        occur: Occur::ONE,
+                        kind: EntryKind::Group(group),
+                    } => group.0,
+                    entry => vec![vec![entry]],
+                };
+                match existing {
+                    Some(i) => {
+                        let body = &mut self.rules[i].body;
+                        let mut group = match *body {
+                            RuleBody::Type(ref ty) => Group(vec![vec![GroupEntry {
+                                occur: Occur::ONE,
+                                kind: EntryKind::Member(None, ty.clone()),
+                            }]]),
+                            RuleBody::Group(ref group) => group.clone(),
+                        };
+                        group.0.extend(choices);
+                        *body = RuleBody::Group(group);
+                    }
+                    None => {
+                        self.index.insert(name.clone(), self.rules.len());
+                        self.rules.push(Rule {
+                            name,
+                            body: RuleBody::Group(Group(choices)),
+                            prelude,
+                        });
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl GroupEntry {
+    /// Returns the type of an entry that consists of nothing but a type.
+    pub fn bare_type(&self) -> Option<&Type> {
+        match *self {
+            GroupEntry {
+                occur: Occur::ONE,
+                kind: EntryKind::Member(None, ref ty),
+            } => Some(ty),
+            _ => None,
+        }
+    }
+}
+
+impl Type {
+    /// Returns the single type without operator this type consists of.
+    pub fn single(&self) -> Option<&Type2> {
+        match self.0.as_slice() {
+            [Type1 { base, op: None }] => Some(base),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Id(String),
+    Int(i128),
+    Float(f64),
+    Text(String),
+    Bytes(Vec<u8>),
+    Assign,
+    TypeChoiceAssign,
+    GroupChoiceAssign,
+    Slash,
+    DoubleSlash,
+    LParen,
+    RParen,
+    LBrace,
+    RBrace,
+    LBracket,
+    RBracket,
+    LAngle,
+    RAngle,
+    Comma,
+    Colon,
+    Arrow,
+    Caret,
+    Question,
+    Star,
+    Plus,
+    Tilde,
+    Amp,
+    Hash(Option<u8>, Option<u64>),
+    Range(bool),
+    Control(String),
+    Eof,
+}
+
+struct Spanned {
+    token: Token,
+    start: usize,
+    end: usize,
+}
+
+fn is_ealpha(b: u8) -> bool {
+    b.is_ascii_alphabetic() || b == b'@' || b == b'_' || b == b'$'
+}
+
+struct Lexer<'s> {
+    src: &'s str,
+    bytes: &'s [u8],
+    pos: usize,
+}
+
+impl<'s> Lexer<'s> {
+    fn error<T>(&self, offset: usize, message: &str) -> Result<T, ParseError> {
+        Err(ParseError::new(self.src, offset, message.to_owned()))
+    }
+
+    fn peek_at(&self, n: usize) -> Option<u8> {
+        self.bytes.get(self.pos + n).cloned()
+    }
+
+    fn peek_is<F>(&self, n: usize, f: F) -> bool
+    where
+        F: FnOnce(u8) -> bool,
+    {
+        match self.peek_at(n) {
+            Some(b) => f(b),
+            None => false,
+        }
+    }
+
+    fn starts_with(&self, s: &str) -> bool {
+        self.bytes[self.pos..].starts_with(s.as_bytes())
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b) = self.peek_at(0) {
+            match b {
+                b' ' | b'\t' | b'\r' | b'\n' => self.pos += 1,
+                b';' => {
+                    while self.peek_is(0, |b| b != b'\n') {
+                        self.pos += 1;
+                    }
+                }
+                _ => break,
+            }
+        }
+    }
+
+    fn tokenize(mut self) -> Result<Vec<Spanned>, ParseError> {
+        let mut tokens = Vec::new();
+        loop {
+            self.skip_whitespace();
+            let start = self.pos;
+            let b = match self.peek_at(0) {
+                Some(b) => b,
+                None => {
+                    tokens.push(Spanned {
+                        token: Token::Eof,
+                        start,
+                        end: start,
+                    });
+                    return Ok(tokens);
+                }
+            };
+            let (token, len) = match b {
+                b'=' if self.peek_at(1) == Some(b'>') => (Token::Arrow, 2),
+                b'=' => (Token::Assign, 1),
+                b'/' if self.starts_with("//=") => (Token::GroupChoiceAssign, 3),
+                b'/' if self.starts_with("//") => (Token::DoubleSlash, 2),
+                b'/' if self.starts_with("/=") => (Token::TypeChoiceAssign, 2),
+                b'/' => (Token::Slash, 1),
+                b'(' => (Token::LParen, 1),
+                b')' => (Token::RParen, 1),
+                b'{' => (Token::LBrace, 1),
+                b'}' => (Token::RBrace, 1),
+                b'[' => (Token::LBracket, 1),
+                b']' => (Token::RBracket, 1),
+                b'<' => (Token::LAngle, 1),
+                b'>' => (Token::RAngle, 1),
+                b',' => (Token::Comma, 1),
+                b':' => (Token::Colon, 1),
+                b'^' => (Token::Caret, 1),
+                b'?' => (Token::Question, 1),
+                b'*' => (Token::Star, 1),
+                b'+' => (Token::Plus, 1),
+                b'~' => (Token::Tilde, 1),
+                b'&' => (Token::Amp, 1),
+                b'.' if self.starts_with("...") => (Token::Range(false), 3),
+                b'.' if self.starts_with("..") => (Token::Range(true), 2),
+                b'.' if self.peek_is(1, is_ealpha) => {
+                    self.pos += 1;
+                    let name = self.lex_id();
+                    tokens.push(Spanned {
+                        token: Token::Control(name),
+                        start,
+                        end: self.pos,
+                    });
+                    continue;
+                }
+                b'#' => {
+                    self.pos += 1;
+                    let token = self.lex_hash()?;
+                    tokens.push(Spanned {
+                        token,
+                        start,
+                        end: self.pos,
+                    });
+                    continue;
+                }
+                b'"' => {
+                    let token = Token::Text(self.lex_text()?);
+                    tokens.push(Spanned {
+                        token,
+                        start,
+                        end: self.pos,
+                    });
+                    continue;
+                }
+                b'\'' => {
+                    let token = Token::Bytes(self.lex_quoted_bytes()?);
+                    tokens.push(Spanned {
+                        token,
+                        start,
+                        end: self.pos,
+                    });
+                    continue;
+                }
+                b'-' | b'0'...b'9' => {
+                    let token = self.lex_number()?;
+                    tokens.push(Spanned {
+                        token,
+                        start,
+                        end: self.pos,
+                    });
+                    continue;
+                }
+                b if is_ealpha(b) => {
+                    let name = self.lex_id();
+                    let token = if self.peek_at(0) == Some(b'\'') && name == "h" {
+                        Token::Bytes(self.lex_hex()?)
+                    } else if self.peek_at(0) == Some(b'\'') && name == "b64" {
+                        Token::Bytes(self.lex_base64()?)
+                    } else {
+                        Token::Id(name)
+                    };
+                    tokens.push(Spanned {
+                        token,
+                        start,
+                        end: self.pos,
+                    });
+                    continue;
+                }
+                _ => return self.error(start, "unexpected character"),
+            };
+            self.pos += len;
+            tokens.push(Spanned {
+                token,
+                start,
+                end: self.pos,
+            });
+        }
+    }
+
+    fn lex_id(&mut self) -> String {
+        let start = self.pos;
+        self.pos += 1;
+        loop {
+            match self.peek_at(0) {
+                Some(b) if is_ealpha(b) || b.is_ascii_digit() => self.pos += 1,
+                Some(b'-') | Some(b'.') => {
+                    let mut n = 1;
+                    while let Some(b'-') | Some(b'.') = self.peek_at(n) {
+                        n += 1;
+                    }
+                    match self.peek_at(n) {
+                        Some(b) if is_ealpha(b) || b.is_ascii_digit() => self.pos += n + 1,
+                        _ => break,
+                    }
+                }
+                _ => break,
+            }
+        }
+        self.src[start..self.pos].to_owned()
+    }
+
+    fn lex_uint(&mut self) -> Result<u64, ParseError> {
+        let start = self.pos;
+        while self.peek_is(0, |b| b.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        match self.src[start..self.pos].parse() {
+            Ok(value) => Ok(value),
+            Err(_) => self.error(start, "invalid unsigned integer"),
+        }
+    }
+
+    fn lex_hash(&mut self) -> Result<Token, ParseError> {
+        let major = match self.peek_at(0) {
+            Some(b @ b'0'...b'9') => {
+                self.pos += 1;
+                b - b'0'
+            }
+            _ => return Ok(Token::Hash(None, None)),
+        };
+        let argument = match (self.peek_at(0), self.peek_at(1)) {
+            (Some(b'.'), Some(b'0'...b'9')) => {
+                self.pos += 1;
+                Some(self.lex_uint()?)
+            }
+            _ => None,
+        };
+        Ok(Token::Hash(Some(major), argument))
+    }
+
+    fn lex_number(&mut self) -> Result<Token, ParseError> {
+        let start = self.pos;
+        let negative = self.peek_at(0) == Some(b'-');
+        if negative {
+            self.pos += 1;
+        }
+        if !self.peek_is(0, |b| b.is_ascii_digit()) {
+            return self.error(start, "expected a number");
+        }
+
+        let radix = match (self.peek_at(0), self.peek_at(1)) {
+            (Some(b'0'), Some(b'x')) => 16,
+            (Some(b'0'), Some(b'b')) => 2,
+            _ => 10,
+        };
+        if radix != 10 {
+            self.pos += 2;
+            let digits = self.pos;
+            while self.peek_is(0, |b| (b as char).is_digit(radix)) {
+                self.pos += 1;
+            }
+            let value = match u64::from_str_radix(&self.src[digits..self.pos], radix) {
+                Ok(value) => i128::from(value),
+                Err(_) => return self.error(start, "invalid integer"),
+            };
+            return Ok(Token::Int(if negative { -value } else { value }));
+        }
+
+        let mut float = false;
+        while self.peek_is(0, |b| b.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        if self.peek_at(0) == Some(b'.') && self.peek_is(1, |b| b.is_ascii_digit()) {
+            float = true;
+            self.pos += 1;
+            while self.peek_is(0, |b| b.is_ascii_digit()) {
+                self.pos += 1;
+            }
+        }
+        if let Some(b'e') | Some(b'E') = self.peek_at(0) {
+            let digits = match self.peek_at(1) {
+                Some(b'+') | Some(b'-') => 2,
+                _ => 1,
+            };
+            if self.peek_is(digits, |b| b.is_ascii_digit()) {
+                float = true;
+                self.pos += digits;
+                while self.peek_is(0, |b| b.is_ascii_digit()) {
+                    self.pos += 1;
+                }
+            }
+        }
+
+        let text = &self.src[start..self.pos];
+        if float {
+            match text.parse() {
+                Ok(value) => Ok(Token::Float(value)),
+                Err(_) => self.error(start, "invalid number"),
+            }
+        } else {
+            match text.parse::<i128>() {
+                Ok(value) if (-(1 << 64)..1 << 64).contains(&value) => Ok(Token::Int(value)),
+                _ => self.error(start, "integer out of range"),
+            }
+        }
+    }
+
+    fn lex_text(&mut self) -> Result<String, ParseError> {
+        let start = self.pos;
+        self.pos += 1;
+        let mut text = String::new();
+        loop {
+            let rest = &self.src[self.pos..];
+            let c = match rest.chars().next() {
+                Some(c) => c,
+                None => return self.error(start, "unterminated text string"),
+            };
+            self.pos += c.len_utf8();
+            match c {
+                '"' => return Ok(text),
+                '\\' => {
+                    let escape = self.pos - 1;
+                    let c = match self.peek_at(0) {
+                        Some(b) => b,
+                        None => return self.error(start, "unterminated text string"),
+                    };
+                    self.pos += 1;
+                    match c {
+                        b'"' | b'\\' | b'/' | b'\'' => text.push(c as char),
+                        b'b' => text.push('\u{8}'),
+                        b'f' => text.push('\u{c}'),
+                        b'n' => text.push('\n'),
+                        b'r' => text.push('\r'),
+                        b't' => text.push('\t'),
+                        b'u' => text.push(self.lex_unicode_escape(escape)?),
+                        _ => return self.error(escape, "invalid escape sequence"),
+                    }
+                }
+                c => text.push(c),
+            }
+        }
+    }
+
+    fn lex_hex4(&mut self, escape: usize) -> Result<u32, ParseError> {
+        let digits = self.src.get(self.pos..self.pos + 4);
+        match digits.and_then(|digits| u32::from_str_radix(digits, 16).ok()) {
+            Some(value) => {
+                self.pos += 4;
+                Ok(value)
+            }
+            None => self.error(escape, "invalid unicode escape"),
+        }
+    }
+
+    fn lex_unicode_escape(&mut self, escape: usize) -> Result<char, ParseError> {
+        let mut code = self.lex_hex4(escape)?;
+        if (0xd800..0xdc00).contains(&code) && self.starts_with("\\u") {
+            self.pos += 2;
+            let low = self.lex_hex4(escape)?;
+            code = 0x10000 + ((code - 0xd800) << 10) + (low.wrapping_sub(0xdc00) & 0x3ff);
+        }
+        match ::std::char::from_u32(code) {
+            Some(c) => Ok(c),
+            None => self.error(escape, "invalid unicode escape"),
+        }
+    }
+
+    fn lex_quoted_bytes(&mut self) -> Result<Vec<u8>, ParseError> {
+        let start = self.pos;
+        self.pos += 1;
+        let mut bytes = Vec::new();
+        loop {
+            match self.peek_at(0) {
+                None => return self.error(start, "unterminated byte string"),
+                Some(b'\'') => {
+                    self.pos += 1;
+                    return Ok(bytes);
+                }
+                Some(b'\\') if self.peek_at(1).is_some() => {
+                    bytes.push(self.bytes[self.pos + 1]);
+                    self.pos += 2;
+                }
+                Some(b) => {
+                    bytes.push(b);
+                    self.pos += 1;
+                }
+            }
+        }
+    }
+
+    /// Returns the content of a prefixed byte string without whitespace.
+    fn lex_prefixed(&mut self) -> Result<(usize, Vec<u8>), ParseError> {
+        let start = self.pos;
+        self.pos += 1;
+        let mut content = Vec::new();
+        loop {
+            match self.peek_at(0) {
+                None => return self.error(start, "unterminated byte string"),
+                Some(b'\'') => {
+                    self.pos += 1;
+                    return Ok((start, content));
+                }
+                Some(b' ') | Some(b'\t') | Some(b'\r') | Some(b'\n') => self.pos += 1,
+                Some(b) => {
+                    content.push(b);
+                    self.pos += 1;
+                }
+            }
+        }
+    }
+
+    fn lex_hex(&mut self) -> Result<Vec<u8>, ParseError> {
+        let (start, content) = self.lex_prefixed()?;
+        if content.len() % 2 != 0 {
+            return self.error(start, "odd number of hex digits");
+        }
+        let mut bytes = Vec::with_capacity(content.len() / 2);
+        for pair in content.chunks(2) {
+            let digits = ::std::str::from_utf8(pair).ok();
+            match digits.and_then(|digits| u8::from_str_radix(digits, 16).ok()) {
+                Some(byte) => bytes.push(byte),
+                None => return self.error(start, "invalid hex digit"),
+            }
+        }
+        Ok(bytes)
+    }
+
+    fn lex_base64(&mut self) -> Result<Vec<u8>, ParseError> {
+        let (start, content) = self.lex_prefixed()?;
+        let mut bytes = Vec::with_capacity(content.len() * 3 / 4);
+        let mut acc = 0u32;
+        let mut bits = 0;
+        for &b in content.iter().filter(|&&b| b != b'=') {
+            let sextet = match b {
+                b'A'...b'Z' => b - b'A',
+                b'a'...b'z' => b - b'a' + 26,
+                b'0'...b'9' => b - b'0' + 52,
+                b'+' | b'-' => 62,
+                b'/' | b'_' => 63,
+                _ => return self.error(start, "invalid base64 digit"),
+            };
+            acc = (acc << 6) | u32::from(sextet);
+            bits += 6;
+            if bits >= 8 {
+                bits -= 8;
+                bytes.push((acc >> bits) as u8);
+            }
+        }
+        Ok(bytes)
+    }
+}
+
+struct Parser<'s> {
+    src: &'s str,
+    tokens: Vec<Spanned>,
+    pos: usize,
+    /// Names referenced by the rules together with their offset.
+    references: Vec<(String, usize)>,
+}
+
+impl<'s> Parser<'s> {
+    fn new(src: &'s str) -> Result<Parser<'s>, ParseError> {
+        let lexer = Lexer {
+            src,
+            bytes: src.as_bytes(),
+            pos: 0,
+        };
+        Ok(Parser {
+            src,
+            tokens: lexer.tokenize()?,
+            pos: 0,
+            references: Vec::new(),
+        })
+    }
+
+    fn peek(&self) -> &Token {
+        &self.tokens[self.pos].token
+    }
+
+    fn peek_at(&self, n: usize) -> &Token {
+        let i = ::std::cmp::min(self.pos + n, self.tokens.len() - 1);
+        &self.tokens[i].token
+    }
+
+    /// Returns true if the next token directly follows the previous one.
+    fn adjacent(&self) -> bool {
+        self.pos > 0 && self.tokens[self.pos - 1].end == self.tokens[self.pos].start
+    }
+
+    fn bump(&mut self) -> Token {
+        let token = self.tokens[self.pos].token.clone();
+        if token != Token::Eof {
+            self.pos += 1;
+        }
+        token
+    }
+
+    fn error<T, S>(&self, message: S) -> Result<T, ParseError>
+    where
+        S: Into<String>,
+    {
+        Err(ParseError::new(
+            self.src,
+            self.tokens[self.pos].start,
+            message.into(),
+        ))
+    }
+
+    fn expect(&mut self, token: Token, what: &str) -> Result<(), ParseError> {
+        if *self.peek() == token {
+            self.bump();
+            Ok(())
+        } else {
+            self.error(format!("expected {}", what))
+        }
+    }
+
+    fn parse_rules(&mut self, rules: &mut Rules, prelude: bool) -> Result<(), ParseError> {
+        while *self.peek() != Token::Eof {
+            let start = self.tokens[self.pos].start;
+            let name = match *self.peek() {
+                Token::Id(ref name) => name.clone(),
+                _ => return self.error("expected a rule name"),
+            };
+            self.bump();
+            if *self.peek() == Token::LAngle && self.adjacent() {
+                return self.error("generic rules are not supported");
+            }
+            let assign = match *self.peek() {
+                Token::Assign | Token::TypeChoiceAssign | Token::GroupChoiceAssign => self.bump(),
+                _ => return self.error("expected `=`, `/=` or `//=`"),
+            };
+            let entry = self.parse_grpent()?;
+            if let Err(message) = rules.define(name, &assign, entry, prelude) {
+                return Err(ParseError::new(self.src, start, message));
+            }
+        }
+
+        for &(ref name, offset) in &self.references {
+            if !name.starts_with('$') && rules.get(name).is_none() {
+                let message = format!("undefined rule `{}`", name);
+                return Err(ParseError::new(self.src, offset, message));
+            }
+        }
+        Ok(())
+    }
+
+    fn parse_occur(&mut self) -> Result<Occur, ParseError> {
+        let min = match *self.peek() {
+            Token::Question => {
+                self.bump();
+                return Ok(Occur { min: 0, max: 1 });
+            }
+            Token::Plus => {
+                self.bump();
+                return Ok(Occur {
+                    min: 1,
+                    max: u64::MAX,
+                });
+            }
+            Token::Int(n) if *self.peek_at(1) == Token::Star => {
+                if n < 0 {
+                    return self.error("occurrence must not be negative");
+                }
+                self.bump();
+                n as u64
+            }
+            Token::Star => 0,
+            _ => return Ok(Occur::ONE),
+        };
+        self.bump();
+        let max = match *self.peek() {
+            Token::Int(n) if self.adjacent() => {
+                if n < min as i128 {
+                    return self.error("invalid occurrence");
+                }
+                self.bump();
+                n as u64
+            }
+            _ => u64::MAX,
+        };
+        Ok(Occur { min, max })
+    }
+
+    fn parse_group(&mut self, close: Token) -> Result<Group, ParseError> {
+        let mut choices = Vec::new();
+        let mut entries = Vec::new();
+        loop {
+            match *self.peek() {
+                Token::DoubleSlash => {
+                    self.bump();
+                    choices.push(entries);
+                    entries = Vec::new();
+                    continue;
+                }
+                Token::Eof => return self.error("unexpected end of input"),
+                ref token if *token == close => break,
+                _ => {}
+            }
+            entries.push(self.parse_grpent()?);
+            if *self.peek() == Token::Comma {
+                self.bump();
+            }
+        }
+        choices.push(entries);
+        Ok(Group(choices))
+    }
+
+    fn parse_grpent(&mut self) -> Result<GroupEntry, ParseError> {
+        let occur = self.parse_occur()?;
+
+        if *self.peek_at(1) == Token::Colon {
+            let key = match *self.peek() {
+                Token::Id(ref name) => Some(Literal::Text(name.clone())),
+                Token::Int(v) => Some(Literal::Int(v)),
+                Token::Float(v) => Some(Literal::Float(v)),
+                Token::Text(ref v) => Some(Literal::Text(v.clone())),
+                Token::Bytes(ref v) => Some(Literal::Bytes(v.clone())),
+                _ => None,
+            };
+            if let Some(key) = key {
+                self.bump();
+                self.bump();
+                let key = MemberKey {
+                    ty: Type1 {
+                        base: Type2::Value(key),
+                        op: None,
+                    },
+                    cut: true,
+                };
+                let value = self.parse_type()?;
+                return Ok(GroupEntry {
+                    occur,
+                    kind: EntryKind::Member(Some(key), value),
+                });
+            }
+        }
+
+        let base = if *self.peek() == Token::LParen {
+            self.bump();
+            let group = self.parse_group(Token::RParen)?;
+            self.expect(Token::RParen, "`)`")?;
+            match group.bare_type() {
+                Some(ty) => Type2::Paren(ty.clone()),
+                None => {
+                    return Ok(GroupEntry {
+                        occur,
+                        kind: EntryKind::Group(group),
+                    });
+                }
+            }
+        } else {
+            self.parse_type2()?
+        };
+        let first = self.parse_type1_rest(base)?;
+
+        let cut = match *self.peek() {
+            Token::Caret => {
+                self.bump();
+                self.expect(Token::Arrow, "`=>`")?;
+                true
+            }
+            Token::Arrow => {
+                self.bump();
+                false
+            }
+            _ => {
+                let ty = self.parse_type_rest(first)?;
+                return Ok(GroupEntry {
+                    occur,
+                    kind: EntryKind::Member(None, ty),
+                });
+            }
+        };
+        let value = self.parse_type()?;
+        Ok(GroupEntry {
+            occur,
+            kind: EntryKind::Member(Some(MemberKey { ty: first, cut }), value),
+        })
+    }
+
+    fn parse_type(&mut self) -> Result<Type, ParseError> {
+        let first = self.parse_type1()?;
+        self.parse_type_rest(first)
+    }
+
+    fn parse_type_rest(&mut self, first: Type1) -> Result<Type, ParseError> {
+        let mut choices = vec![first];
+        while *self.peek() == Token::Slash {
+            self.bump();
+            choices.push(self.parse_type1()?);
+        }
+        Ok(Type(choices))
+    }
+
+    fn parse_type1(&mut self) -> Result<Type1, ParseError> {
+        let base = self.parse_type2()?;
+        self.parse_type1_rest(base)
+    }
+
+    fn parse_type1_rest(&mut self, base: Type2) -> Result<Type1, ParseError> {
+        let op = match *self.peek() {
+            Token::Range(inclusive) => {
+                self.bump();
+                Some(Operator::Range(self.parse_type2()?, inclusive))
+            }
+            Token::Control(ref name) => {
+                let name = name.clone();
+                self.bump();
+                let start = self.pos;
+                let arg = self.parse_type2()?;
+                let control = self.control(&name, &arg, start)?;
+                Some(Operator::Control(control, arg))
+            }
+            _ => None,
+        };
+        Ok(Type1 { base, op })
+    }
+
+    fn control(&self, name: &str, arg: &Type2, start: usize) -> Result<Control, ParseError> {
+        let control = match name {
+            "size" => Control::Size,
+            "bits" => Control::Bits,
+            "cbor" => Control::Cbor,
+            "cborseq" => Control::Cborseq,
+            "regexp" | "pcre" => return self.regexp(arg, start),
+            "lt" => Control::Lt,
+            "le" => Control::Le,
+            "gt" => Control::Gt,
+            "ge" => Control::Ge,
+            "eq" => Control::Eq,
+            "ne" => Control::Ne,
+            "default" => Control::Default,
+            "and" => Control::And,
+            "within" => Control::Within,
+            _ => {
+                let message = format!("unknown control operator `.{}`", name);
+                return Err(ParseError::new(
+                    self.src,
+                    self.tokens[start - 1].start,
+                    message,
+                ));
+            }
+        };
+        Ok(control)
+    }
+
+    #[cfg(feature = "regex")]
+    fn regexp(&self, arg: &Type2, start: usize) -> Result<Control, ParseError> {
+        let offset = self.tokens[start].start;
+        let pattern = match *arg {
+            Type2::Value(Literal::Text(ref pattern)) => pattern,
+            _ => {
+                let message = "`.regexp` needs a text string".to_owned();
+                return Err(ParseError::new(self.src, offset, message));
+            }
+        };
+        // CDDL regular expressions always match the whole string.
+        match Regex::new(&format!("^(?:{})$", pattern)) {
+            Ok(regex) => Ok(Control::Regexp(regex)),
+            Err(e) => Err(ParseError::new(self.src, offset, e.to_string())),
+        }
+    }
+
+    #[cfg(not(feature = "regex"))]
+    fn regexp(&self, _arg: &Type2, start: usize) -> Result<Control, ParseError> {
+        let message = "`.regexp` requires the `regex` feature".to_owned();
+        Err(ParseError::new(
+            self.src,
+            self.tokens[start - 1].start,
+            message,
+        ))
+    }
+
+    fn parse_name(&mut self) -> Result<String, ParseError> {
+        let offset = self.tokens[self.pos].start;
+        let name = match *self.peek() {
+            Token::Id(ref name) => name.clone(),
+            _ => return self.error("expected a name"),
+        };
+        self.bump();
+        if *self.peek() == Token::LAngle && self.adjacent() {
+            return self.error("generic arguments are not supported");
+        }
+        self.references.push((name.clone(), offset));
+        Ok(name)
+    }
+
+    fn parse_type2(&mut self) -> Result<Type2, ParseError> {
+        let ty = match self.peek().clone() {
+            Token::Int(v) => Type2::Value(Literal::Int(v)),
+            Token::Float(v) => Type2::Value(Literal::Float(v)),
+            Token::Text(v) => Type2::Value(Literal::Text(v)),
+            Token::Bytes(v) => Type2::Value(Literal::Bytes(v)),
+            Token::Id(_) => return Ok(Type2::Name(self.parse_name()?)),
+            Token::LParen => {
+                self.bump();
+                let ty = self.parse_type()?;
+                self.expect(Token::RParen, "`)`")?;
+                return Ok(Type2::Paren(ty));
+            }
+            Token::LBrace => {
+                self.bump();
+                let group = self.parse_group(Token::RBrace)?;
+                self.expect(Token::RBrace, "`}`")?;
+                return Ok(Type2::Map(group));
+            }
+            Token::LBracket => {
+                self.bump();
+                let group = self.parse_group(Token::RBracket)?;
+                self.expect(Token::RBracket, "`]`")?;
+                return Ok(Type2::Array(group));
+            }
+            Token::Tilde => {
+                self.bump();
+                return Ok(Type2::Unwrap(self.parse_name()?));
+            }
+            Token::Amp => {
+                self.bump();
+                if *self.peek() == Token::LParen {
+                    self.bump();
+                    let group = self.parse_group(Token::RParen)?;
+                    self.expect(Token::RParen, "`)`")?;
+                    return Ok(Type2::ChoiceFrom(group));
+                }
+                let name = Type2::Name(self.parse_name()?);
+                let entry = GroupEntry {
+                    occur: Occur::ONE,
+                    kind: EntryKind::Member(
+                        None,
+                        Type(vec![Type1 {
+                            base: name,
+                            op: None,
+                        }]),
+                    ),
+                };
+                return Ok(Type2::ChoiceFrom(Group(vec![vec![entry]])));
+            }
+            Token::Hash(None, _) => Type2::Any,
+            Token::Hash(Some(major), argument) => {
+                if major > 7 {
+                    return self.error("invalid major type");
+                }
+                self.bump();
+                if major == 6 && *self.peek() == Token::LParen && self.adjacent() {
+                    self.bump();
+                    let ty = self.parse_type()?;
+                    self.expect(Token::RParen, "`)`")?;
+                    return Ok(Type2::Tag(argument, Box::new(ty)));
+                }
+                return Ok(Type2::Major(major, argument));
+            }
+            _ => return self.error("expected a type"),
+        };
+        self.bump();
+        Ok(ty)
+    }
+}
+
+impl Group {
+    /// Returns the type of a group that consists of a single bare type.
+    pub fn bare_type(&self) -> Option<&Type> {
+        match self.0.as_slice() {
+            [entries] if entries.len() == 1 => entries[0].bare_type(),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Literal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Literal::Int(v) => write!(f, "{}", v),
+            Literal::Float(v) => write!(f, "{:?}", v),
+            Literal::Text(ref v) => write!(f, "{:?}", v),
+            Literal::Bytes(ref v) => {
+                f.write_str("h'")?;
+                for b in v {
+                    write!(f, "{:02x}", b)?;
+                }
+                f.write_str("'")
+            }
+        }
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, choice) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(" / ")?;
+            }
+            write!(f, "{}", choice)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Type1 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.base)?;
+        match self.op {
+            Some(Operator::Range(ref end, true)) => write!(f, "..{}", end),
+            Some(Operator::Range(ref end, false)) => write!(f, "...{}", end),
+            Some(Operator::Control(ref control, ref arg)) => {
+                let name = match *control {
+                    Control::Size => "size",
+                    Control::Bits => "bits",
+                    Control::Cbor => "cbor",
+                    Control::Cborseq => "cborseq",
+                    #[cfg(feature = "regex")]
+                    Control::Regexp(_) => "regexp",
+                    Control::Lt => "lt",
+                    Control::Le => "le",
+                    Control::Gt => "gt",
+                    Control::Ge => "ge",
+                    Control::Eq => "eq",
+                    Control::Ne => "ne",
+                    Control::Default => "default",
+                    Control::And => "and",
+                    Control::Within => "within",
+                };
+                write!(f, " .{} {}", name, arg)
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+impl fmt::Display for Type2 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Type2::Value(ref v) => write!(f, "{}", v),
+            Type2::Name(ref name) => f.write_str(name),
+            Type2::Paren(ref ty) => write!(f, "({})", ty),
+            Type2::Map(ref group) => write!(f, "{{{}}}", group),
+            Type2::Array(ref group) => write!(f, "[{}]", group),
+            Type2::Unwrap(ref name) => write!(f, "~{}", name),
+            Type2::ChoiceFrom(ref group) => write!(f, "&({})", group),
+            Type2::Tag(Some(tag), ref ty) => write!(f, "#6.{}({})", tag, ty),
+            Type2::Tag(None, ref ty) => write!(f, "#6({})", ty),
+            Type2::Major(major, Some(argument)) => write!(f, "#{}.{}", major, argument),
+            Type2::Major(major, None) => write!(f, "#{}", major),
+            Type2::Any => f.write_str("#"),
+        }
+    }
+}
+
+impl fmt::Display for Group {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, choice) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(" // ")?;
+            }
+            for (j, entry) in choice.iter().enumerate() {
+                if j > 0 {
+                    f.write_str(", ")?;
+                }
+                write!(f, "{}", entry)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for GroupEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.occur {
+            Occur { min: 1, max: 1 } => {}
+            Occur { min: 0, max: 1 } => f.write_str("? ")?,
+            Occur {
+                min: 1,
+                max: u64::MAX,
+            } => f.write_str("+ ")?,
+            Occur { min, max } => {
+                if min > 0 {
+                    write!(f, "{}", min)?;
+                }
+                f.write_str("*")?;
+                if max < u64::MAX {
+                    write!(f, "{}", max)?;
+                }
+                f.write_str(" ")?;
+            }
+        }
+        match self.kind {
+            EntryKind::Member(Some(ref key), ref value) => {
+                let arrow = if key.cut { "^ =>" } else { "=>" };
+                write!(f, "{} {} {}", key.ty, arrow, value)
+            }
+            EntryKind::Member(None, ref value) => write!(f, "{}", value),
+            EntryKind::Group(ref group) => write!(f, "({})", group),
+        }
+    }
+}
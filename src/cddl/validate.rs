@@ -0,0 +1,777 @@
+//! Matching of data items against CDDL rules.
+
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::fmt;
+
+use super::parse::{
+    Control, EntryKind, Group, GroupEntry, Literal, Occur, Operator, Rule, RuleBody, Rules, Type,
+    Type1, Type2,
+};
+use super::ValidationError;
+use syntax::{self, Argument, Array, Float, Item, Length, Map, Str};
+
+/// The maximum number of rules that may be expanded inside of each other.
+const MAX_DEPTH: usize = 512;
+
+/// The value of a group socket without definitions.
+static EMPTY: Group = Group(Vec::new());
+
+type Continuation<'v, 'a> = &'v mut dyn FnMut(&mut Validator<'a>, usize) -> bool;
+
+pub(crate) struct Validator<'a> {
+    rules: &'a Rules,
+    /// The location of the data item being matched.
+    path: Vec<String>,
+    /// The rules currently being matched, innermost last.
+    stack: Vec<&'a str>,
+    /// Errors are not recorded while matching keys and probing controller types.
+    quiet: usize,
+    error: Option<ValidationError>,
+    error_depth: usize,
+}
+
+impl<'a> Validator<'a> {
+    pub fn new(rules: &'a Rules) -> Validator<'a> {
+        Validator {
+            rules,
+            path: Vec::new(),
+            stack: Vec::new(),
+            quiet: 0,
+            error: None,
+            error_depth: 0,
+        }
+    }
+
+    pub fn validate(mut self, item: &Item, rule: &str) -> Result<(), ValidationError> {
+        let rule = match self.rules.get(rule) {
+            Some(rule) => rule,
+            None => {
+                let message = format!("no rule named `{}`", rule);
+                return Err(ValidationError::new(
+                    "/".to_owned(),
+                    rule.to_owned(),
+                    message,
+                ));
+            }
+        };
+        if let RuleBody::Group(_) = rule.body {
+            let message = format!("`{}` is a group, not a type", rule.name);
+            return Err(ValidationError::new(
+                "/".to_owned(),
+                rule.name.clone(),
+                message,
+            ));
+        }
+        self.stack.push(&rule.name);
+        if self.match_name(&rule.name, item) {
+            return Ok(());
+        }
+        match self.error.take() {
+            Some(error) => Err(error),
+            None => {
+                let message = format!("expected `{}`, found {}", rule.name, Describe(item));
+                Err(ValidationError::new(
+                    "/".to_owned(),
+                    rule.name.clone(),
+                    message,
+                ))
+            }
+        }
+    }
+
+    /// Records an error unless a deeper one has been recorded before. Always returns false.
+    fn fail(&mut self, message: fmt::Arguments) -> bool {
+        self.record(message, true)
+    }
+
+    /// Records an error unless an error at the same depth or deeper has been recorded before.
+    /// Always returns false.
+    fn fail_weak(&mut self, message: fmt::Arguments) -> bool {
+        self.record(message, false)
+    }
+
+    fn record(&mut self, message: fmt::Arguments, overwrite: bool) -> bool {
+        let depth = self.path.len();
+        let replace = match self.error {
+            None => true,
+            Some(_) if overwrite => depth >= self.error_depth,
+            Some(_) => depth > self.error_depth,
+        };
+        if self.quiet == 0 && replace {
+            let rules = self.rules;
+            let rule = self
+                .stack
+                .iter()
+                .rev()
+                .find(|name| rules.get(name).into_iter().any(|rule| !rule.prelude))
+                .or_else(|| self.stack.first())
+                .map_or("", |name| name);
+            let path = format!("/{}", self.path.join("/"));
+            self.error = Some(ValidationError::new(
+                path,
+                rule.to_owned(),
+                message.to_string(),
+            ));
+            self.error_depth = depth;
+        }
+        false
+    }
+
+    fn quietly<F>(&mut self, f: F) -> bool
+    where
+        F: FnOnce(&mut Validator<'a>) -> bool,
+    {
+        self.quiet += 1;
+        let matched = f(self);
+        self.quiet -= 1;
+        matched
+    }
+
+    fn match_type(&mut self, ty: &'a Type, item: &Item) -> bool {
+        if ty.0.iter().any(|choice| self.match_type1(choice, item)) {
+            return true;
+        }
+        if ty.0.len() > 1 {
+            return self.fail(format_args!("expected `{}`, found {}", ty, Describe(item)));
+        }
+        false
+    }
+
+    fn match_type1(&mut self, ty: &'a Type1, item: &Item) -> bool {
+        match ty.op {
+            None => self.match_type2(&ty.base, item),
+            Some(Operator::Range(ref end, inclusive)) => {
+                self.match_range(&ty.base, end, inclusive, item)
+                    || self.fail(format_args!("expected `{}`, found {}", ty, Describe(item)))
+            }
+            Some(Operator::Control(ref control, ref arg)) => {
+                self.match_type2(&ty.base, item) && self.match_control(ty, control, arg, item)
+            }
+        }
+    }
+
+    fn match_type2(&mut self, ty: &'a Type2, item: &Item) -> bool {
+        match *ty {
+            Type2::Value(ref literal) => {
+                literal_matches(literal, item)
+                    || self.fail(format_args!(
+                        "expected {}, found {}",
+                        literal,
+                        Describe(item)
+                    ))
+            }
+            Type2::Name(ref name) | Type2::Unwrap(ref name) => self.match_name(name, item),
+            Type2::Paren(ref ty) => self.match_type(ty, item),
+            Type2::Map(ref group) => match *item {
+                Item::Map(ref map) => self.match_map(group, map),
+                _ => self.fail(format_args!("expected a map, found {}", Describe(item))),
+            },
+            Type2::Array(ref group) => match *item {
+                Item::Array(ref array) => self.match_array(group, &array.items),
+                _ => self.fail(format_args!("expected an array, found {}", Describe(item))),
+            },
+            Type2::ChoiceFrom(ref group) => {
+                let mut choices = Vec::new();
+                self.choices_from(group, &mut choices, 0);
+                choices.iter().any(|ty| self.match_type(ty, item))
+                    || self.fail(format_args!("expected `{}`, found {}", ty, Describe(item)))
+            }
+            Type2::Tag(tag, ref ty) => match *item {
+                Item::Tag(ref argument, ref content)
+                    if tag.is_none() || tag == Some(argument.value) =>
+                {
+                    self.match_type(ty, content)
+                }
+                _ => self.fail(format_args!("expected `{}`, found {}", ty, Describe(item))),
+            },
+            Type2::Major(major, argument) => {
+                major_matches(major, argument, item)
+                    || self.fail(format_args!("expected `{}`, found {}", ty, Describe(item)))
+            }
+            Type2::Any => true,
+        }
+    }
+
+    fn match_name(&mut self, name: &'a str, item: &Item) -> bool {
+        let rule = match self.rules.get(name) {
+            Some(rule) => rule,
+            None => return self.fail(format_args!("socket `{}` has no definitions", name)),
+        };
+        let ty = match rule.body {
+            RuleBody::Type(ref ty) => ty,
+            RuleBody::Group(_) => {
+                return self.fail(format_args!("group `{}` used as a type", name))
+            }
+        };
+        if self.stack.len() == MAX_DEPTH {
+            return self.fail(format_args!("recursion limit exceeded"));
+        }
+
+        self.stack.push(&rule.name);
+        let matched = self.match_type(ty, item);
+        self.stack.pop();
+        // The prelude rules are described by their name rather than by their definition.
+        if !matched && rule.prelude {
+            return self.fail(format_args!(
+                "expected `{}`, found {}",
+                name,
+                Describe(item)
+            ));
+        }
+        matched
+    }
+
+    /// Resolves a type used as a group entry to the group it names, if any.
+    fn as_group(&self, ty: &'a Type) -> Option<&'a Group> {
+        let mut ty = ty;
+        for _ in 0..MAX_DEPTH {
+            match ty.single() {
+                Some(Type2::Name(name)) => match self.rules.get(name) {
+                    Some(&Rule {
+                        body: RuleBody::Group(ref group),
+                        ..
+                    }) => return Some(group),
+                    Some(&Rule {
+                        body: RuleBody::Type(ref alias),
+                        ..
+                    }) => ty = alias,
+                    None if name.starts_with("$$") => return Some(&EMPTY),
+                    None => return None,
+                },
+                Some(Type2::Unwrap(name)) => return self.unwrap(name),
+                _ => return None,
+            }
+        }
+        None
+    }
+
+    /// Returns the group of the map or array type `~name` refers to.
+    fn unwrap(&self, name: &'a str) -> Option<&'a Group> {
+        let mut name = name;
+        for _ in 0..MAX_DEPTH {
+            let ty = match self.rules.get(name) {
+                Some(&Rule {
+                    body: RuleBody::Type(ref ty),
+                    ..
+                }) => ty,
+                _ => return None,
+            };
+            match ty.single() {
+                Some(Type2::Map(group)) | Some(Type2::Array(group)) => return Some(group),
+                Some(Type2::Name(alias)) => name = alias,
+                _ => return None,
+            }
+        }
+        None
+    }
+
+    /// Collects the types of all entries of a group, looking into nested groups.
+    fn choices_from(&self, group: &'a Group, choices: &mut Vec<&'a Type>, depth: usize) {
+        if depth == MAX_DEPTH {
+            return;
+        }
+        for entry in group.0.iter().flatten() {
+            match entry.kind {
+                EntryKind::Member(_, ref ty) => match self.as_group(ty) {
+                    Some(group) => self.choices_from(group, choices, depth + 1),
+                    None => choices.push(ty),
+                },
+                EntryKind::Group(ref group) => self.choices_from(group, choices, depth + 1),
+            }
+        }
+    }
+
+    /// Returns the literal a type stands for, following rules that name a single value.
+    fn literal(&self, ty: &'a Type2) -> Option<&'a Literal> {
+        let mut ty = ty;
+        for _ in 0..MAX_DEPTH {
+            match *ty {
+                Type2::Value(ref literal) => return Some(literal),
+                Type2::Paren(ref inner) => ty = inner.single()?,
+                Type2::Name(ref name) => match self.rules.get(name) {
+                    Some(&Rule {
+                        body: RuleBody::Type(ref inner),
+                        ..
+                    }) => ty = inner.single()?,
+                    _ => return None,
+                },
+                _ => return None,
+            }
+        }
+        None
+    }
+
+    fn match_range(
+        &mut self,
+        start: &'a Type2,
+        end: &'a Type2,
+        inclusive: bool,
+        item: &Item,
+    ) -> bool {
+        let in_range = |ordering: Option<Ordering>| match ordering {
+            Some(Ordering::Less) => true,
+            Some(Ordering::Equal) => inclusive,
+            _ => false,
+        };
+        match (self.literal(start), self.literal(end)) {
+            (Some(&Literal::Int(start)), Some(&Literal::Int(end))) => match int_value(item) {
+                Some(v) => start <= v && in_range(v.partial_cmp(&end)),
+                None => false,
+            },
+            (Some(&Literal::Float(start)), Some(&Literal::Float(end))) => match *item {
+                Item::Float(ref float) => {
+                    let v = float.to_f64();
+                    start <= v && in_range(v.partial_cmp(&end))
+                }
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    fn match_control(
+        &mut self,
+        ty: &'a Type1,
+        control: &'a Control,
+        arg: &'a Type2,
+        item: &Item,
+    ) -> bool {
+        let matched = match *control {
+            Control::Size => match *item {
+                Item::Unsigned(argument) => {
+                    // The smallest number of bytes the value fits into.
+                    let needed = 8 - u64::from(argument.value.leading_zeros()) / 8;
+                    (needed..=8).any(|n| self.quietly(|v| v.match_type2(arg, &uint(n))))
+                }
+                Item::Bytes(_) | Item::Text(_) => {
+                    let len = string_len(item);
+                    self.quietly(|v| v.match_type2(arg, &uint(len)))
+                }
+                _ => false,
+            },
+            Control::Bits => {
+                let bits: Vec<u64> = match *item {
+                    Item::Unsigned(argument) => {
+                        (0..64).filter(|&i| argument.value >> i & 1 == 1).collect()
+                    }
+                    Item::Bytes(_) => {
+                        let bytes = bytes(item).unwrap_or_default();
+                        (0..bytes.len() as u64 * 8)
+                            .filter(|&i| bytes[(i / 8) as usize] >> (i % 8) & 1 == 1)
+                            .collect()
+                    }
+                    _ => {
+                        return self.fail(format_args!(
+                            "expected `{}`, found {}",
+                            ty,
+                            Describe(item)
+                        ))
+                    }
+                };
+                bits.into_iter()
+                    .all(|bit| self.quietly(|v| v.match_type2(arg, &uint(bit))))
+            }
+            Control::Cbor | Control::Cborseq => {
+                let bytes = match bytes(item) {
+                    Some(bytes) => bytes,
+                    None => return false,
+                };
+                let embedded = if let Control::Cbor = *control {
+                    syntax::from_slice(&bytes)
+                } else {
+                    syntax::seq_from_slice(&bytes).map(|items| {
+                        Item::Array(Array {
+                            length: Length::Indefinite,
+                            items,
+                        })
+                    })
+                };
+                // Errors inside of the embedded data are more useful than a summary.
+                return match embedded {
+                    Ok(embedded) => self.match_type2(arg, &embedded),
+                    Err(e) => self.fail(format_args!("invalid embedded CBOR: {}", e)),
+                };
+            }
+            #[cfg(feature = "regex")]
+            Control::Regexp(ref regex) => match text(item) {
+                Some(text) => regex.is_match(&text),
+                None => false,
+            },
+            Control::Lt | Control::Le | Control::Gt | Control::Ge => {
+                let ordering = self.literal(arg).and_then(|literal| compare(item, literal));
+                match (control, ordering) {
+                    (&Control::Lt, Some(ordering)) => ordering == Ordering::Less,
+                    (&Control::Le, Some(ordering)) => ordering != Ordering::Greater,
+                    (&Control::Gt, Some(ordering)) => ordering == Ordering::Greater,
+                    (&Control::Ge, Some(ordering)) => ordering != Ordering::Less,
+                    _ => false,
+                }
+            }
+            Control::Eq => self.quietly(|v| v.match_type2(arg, item)),
+            Control::Ne => !self.quietly(|v| v.match_type2(arg, item)),
+            Control::Default => true,
+            Control::And | Control::Within => return self.match_type2(arg, item),
+        };
+        matched || self.fail(format_args!("expected `{}`, found {}", ty, Describe(item)))
+    }
+
+    fn match_map(&mut self, group: &'a Group, map: &Map) -> bool {
+        let entries = &map.entries;
+        for choice in &group.0 {
+            let mut used = vec![false; entries.len()];
+            if !self.map_entries(choice, entries, &mut used) {
+                continue;
+            }
+            match used.iter().position(|&used| !used) {
+                None => return true,
+                Some(i) => {
+                    self.path.push(key_segment(&entries[i].0));
+                    self.fail_weak(format_args!("unexpected map entry"));
+                    self.path.pop();
+                }
+            }
+        }
+        false
+    }
+
+    fn map_group(
+        &mut self,
+        group: &'a Group,
+        entries: &[(Item, Item)],
+        used: &mut Vec<bool>,
+    ) -> bool {
+        for choice in &group.0 {
+            let saved = used.clone();
+            if self.map_entries(choice, entries, used) {
+                return true;
+            }
+            *used = saved;
+        }
+        false
+    }
+
+    fn map_entries(
+        &mut self,
+        choice: &'a [GroupEntry],
+        entries: &[(Item, Item)],
+        used: &mut Vec<bool>,
+    ) -> bool {
+        choice
+            .iter()
+            .all(|entry| self.map_entry(entry, entries, used))
+    }
+
+    fn map_entry(
+        &mut self,
+        entry: &'a GroupEntry,
+        entries: &[(Item, Item)],
+        used: &mut Vec<bool>,
+    ) -> bool {
+        let (key, value) = match entry.kind {
+            EntryKind::Member(Some(ref key), ref value) => (key, value),
+            EntryKind::Member(None, ref ty) => {
+                return match self.as_group(ty) {
+                    Some(group) => self.map_repeat(entry.occur, group, entries, used),
+                    None => self.fail(format_args!("`{}` used in a map is not a group", ty)),
+                };
+            }
+            EntryKind::Group(ref group) => {
+                return self.map_repeat(entry.occur, group, entries, used);
+            }
+        };
+
+        let mut count = 0;
+        for (i, (k, v)) in entries.iter().enumerate() {
+            if count == entry.occur.max {
+                break;
+            }
+            if used[i] || !self.quietly(|validator| validator.match_type1(&key.ty, k)) {
+                continue;
+            }
+            self.path.push(key_segment(k));
+            let matched = self.match_type(value, v);
+            self.path.pop();
+            if matched {
+                used[i] = true;
+                count += 1;
+            } else if key.cut {
+                return false;
+            }
+        }
+        count >= entry.occur.min || self.fail(format_args!("missing map entry `{}`", key.ty))
+    }
+
+    fn map_repeat(
+        &mut self,
+        occur: Occur,
+        group: &'a Group,
+        entries: &[(Item, Item)],
+        used: &mut Vec<bool>,
+    ) -> bool {
+        let mut count = 0;
+        while count < occur.max {
+            let before = used.clone();
+            if !self.map_group(group, entries, used) {
+                break;
+            }
+            count += 1;
+            if *used == before {
+                // A match that consumes nothing can be repeated as often as needed.
+                return true;
+            }
+        }
+        count >= occur.min
+    }
+
+    fn match_array(&mut self, group: &'a Group, items: &[Item]) -> bool {
+        let len = items.len();
+        self.array_group(group, items, 0, &mut |validator, pos| {
+            if pos == len {
+                return true;
+            }
+            validator.path.push(pos.to_string());
+            validator.fail_weak(format_args!("unexpected array element"));
+            validator.path.pop();
+            false
+        })
+    }
+
+    // The array matchers call the continuation with every position up to which the entries
+    // could be matched, longest match first, and backtrack if it returns false.
+
+    fn array_group(
+        &mut self,
+        group: &'a Group,
+        items: &[Item],
+        pos: usize,
+        k: Continuation<'_, 'a>,
+    ) -> bool {
+        group
+            .0
+            .iter()
+            .any(|choice| self.array_entries(choice, items, pos, k))
+    }
+
+    fn array_entries(
+        &mut self,
+        entries: &'a [GroupEntry],
+        items: &[Item],
+        pos: usize,
+        k: Continuation<'_, 'a>,
+    ) -> bool {
+        match entries.split_first() {
+            None => k(self, pos),
+            Some((entry, rest)) => self.array_entry(entry, items, pos, &mut |validator, pos| {
+                validator.array_entries(rest, items, pos, k)
+            }),
+        }
+    }
+
+    fn array_entry(
+        &mut self,
+        entry: &'a GroupEntry,
+        items: &[Item],
+        pos: usize,
+        k: Continuation<'_, 'a>,
+    ) -> bool {
+        let group = match entry.kind {
+            // Member keys only serve as documentation inside of arrays.
+            EntryKind::Member(_, ref ty) => match self.as_group(ty) {
+                Some(group) => group,
+                None => return self.array_items(entry.occur, ty, items, pos, k),
+            },
+            EntryKind::Group(ref group) => group,
+        };
+        self.array_repeat(entry.occur, group, 0, items, pos, k)
+    }
+
+    fn array_items(
+        &mut self,
+        occur: Occur,
+        ty: &'a Type,
+        items: &[Item],
+        pos: usize,
+        k: Continuation<'_, 'a>,
+    ) -> bool {
+        let mut n = 0;
+        while (n as u64) < occur.max && pos + n < items.len() {
+            self.path.push((pos + n).to_string());
+            let matched = self.match_type(ty, &items[pos + n]);
+            self.path.pop();
+            if !matched {
+                break;
+            }
+            n += 1;
+        }
+        if (n as u64) < occur.min {
+            if pos + n == items.len() {
+                self.path.push(items.len().to_string());
+                self.fail(format_args!("missing array element `{}`", ty));
+                self.path.pop();
+            }
+            return false;
+        }
+        (occur.min as usize..=n)
+            .rev()
+            .any(|count| k(self, pos + count))
+    }
+
+    fn array_repeat(
+        &mut self,
+        occur: Occur,
+        group: &'a Group,
+        count: u64,
+        items: &[Item],
+        pos: usize,
+        k: Continuation<'_, 'a>,
+    ) -> bool {
+        if count < occur.max {
+            let matched = self.array_group(group, items, pos, &mut |validator, next| {
+                if next == pos {
+                    k(validator, next)
+                } else {
+                    validator.array_repeat(occur, group, count + 1, items, next, k)
+                }
+            });
+            if matched {
+                return true;
+            }
+        }
+        count >= occur.min && k(self, pos)
+    }
+}
+
+fn uint(value: u64) -> Item {
+    Item::Unsigned(Argument::new(value))
+}
+
+fn int_value(item: &Item) -> Option<i128> {
+    match *item {
+        Item::Unsigned(argument) => Some(i128::from(argument.value)),
+        Item::Negative(argument) => Some(-1 - i128::from(argument.value)),
+        _ => None,
+    }
+}
+
+fn bytes(item: &Item) -> Option<Cow<'_, [u8]>> {
+    match *item {
+        Item::Bytes(Str::Definite(ref chunk)) => Some(Cow::Borrowed(&chunk.data)),
+        Item::Bytes(Str::Indefinite(ref chunks)) => Some(Cow::Owned(
+            chunks
+                .iter()
+                .flat_map(|chunk| chunk.data.iter().cloned())
+                .collect(),
+        )),
+        _ => None,
+    }
+}
+
+fn text(item: &Item) -> Option<Cow<'_, str>> {
+    match *item {
+        Item::Text(Str::Definite(ref chunk)) => Some(Cow::Borrowed(&chunk.data)),
+        Item::Text(Str::Indefinite(ref chunks)) => Some(Cow::Owned(
+            chunks.iter().map(|chunk| &chunk.data[..]).collect(),
+        )),
+        _ => None,
+    }
+}
+
+fn string_len(item: &Item) -> u64 {
+    let len = match *item {
+        Item::Bytes(Str::Definite(ref chunk)) => chunk.data.len(),
+        Item::Bytes(Str::Indefinite(ref chunks)) => {
+            chunks.iter().map(|chunk| chunk.data.len()).sum()
+        }
+        Item::Text(Str::Definite(ref chunk)) => chunk.data.len(),
+        Item::Text(Str::Indefinite(ref chunks)) => {
+            chunks.iter().map(|chunk| chunk.data.len()).sum()
+        }
+        _ => 0,
+    };
+    len as u64
+}
+
+fn literal_matches(literal: &Literal, item: &Item) -> bool {
+    match *literal {
+        Literal::Int(v) => int_value(item) == Some(v),
+        Literal::Float(v) => match *item {
+            Item::Float(ref float) => float.to_f64() == v,
+            _ => false,
+        },
+        Literal::Text(ref v) => text(item).as_ref().map(|text| &text[..]) == Some(&v[..]),
+        Literal::Bytes(ref v) => bytes(item).as_ref().map(|bytes| &bytes[..]) == Some(&v[..]),
+    }
+}
+
+fn compare(item: &Item, literal: &Literal) -> Option<Ordering> {
+    let value = match *item {
+        Item::Float(ref float) => float.to_f64(),
+        _ => {
+            let v = int_value(item)?;
+            return match *literal {
+                Literal::Int(l) => Some(v.cmp(&l)),
+                Literal::Float(l) => (v as f64).partial_cmp(&l),
+                _ => None,
+            };
+        }
+    };
+    match *literal {
+        Literal::Int(l) => value.partial_cmp(&(l as f64)),
+        Literal::Float(l) => value.partial_cmp(&l),
+        _ => None,
+    }
+}
+
+fn major_matches(major: u8, argument: Option<u64>, item: &Item) -> bool {
+    let (actual, value) = match *item {
+        Item::Unsigned(a) => (0, a.value),
+        Item::Negative(a) => (1, a.value),
+        Item::Bytes(_) => (2, string_len(item)),
+        Item::Text(_) => (3, string_len(item)),
+        Item::Array(ref array) => (4, array.items.len() as u64),
+        Item::Map(ref map) => (5, map.entries.len() as u64),
+        Item::Tag(a, _) => (6, a.value),
+        Item::Simple(a) => (7, a.value),
+        Item::Float(Float::Half(_)) => (7, 25),
+        Item::Float(Float::Single(_)) => (7, 26),
+        Item::Float(Float::Double(_)) => (7, 27),
+    };
+    actual == major && (argument.is_none() || argument == Some(value))
+}
+
+/// The path segment of a map entry with the given key.
+fn key_segment(key: &Item) -> String {
+    if let Some(text) = text(key) {
+        return text.into_owned();
+    }
+    match int_value(key) {
+        Some(v) => v.to_string(),
+        None => Describe(key).to_string(),
+    }
+}
+
+/// Describes a data item in error messages.
+struct Describe<'a>(&'a Item);
+
+impl<'a> fmt::Display for Describe<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self.0 {
+            Item::Unsigned(a) => write!(f, "unsigned integer {}", a.value),
+            Item::Negative(a) => write!(f, "negative integer {}", -1 - i128::from(a.value)),
+            Item::Bytes(_) => f.write_str("byte string"),
+            Item::Text(_) => f.write_str("text string"),
+            Item::Array(_) => f.write_str("array"),
+            Item::Map(_) => f.write_str("map"),
+            Item::Tag(a, _) => write!(f, "tag {}", a.value),
+            Item::Simple(a) => match a.value {
+                20 => f.write_str("false"),
+                21 => f.write_str("true"),
+                22 => f.write_str("null"),
+                23 => f.write_str("undefined"),
+                v => write!(f, "simple value {}", v),
+            },
+            Item::Float(ref float) => write!(f, "float {}", float.to_f64()),
+        }
+    }
+}
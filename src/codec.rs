@@ -0,0 +1,150 @@
+//! A tokio-util codec for CBOR sequences.
+//!
+//! This module requires the `codec` feature.
+
+use bytes::{BufMut, BytesMut};
+use core::marker::PhantomData;
+use serde::de::DeserializeOwned;
+use serde::ser::Serialize;
+use tokio_util::codec::{Decoder, Encoder};
+
+use de;
+use error::{Error, ErrorCode, Result};
+use scan::{Scan, Scanner};
+use ser::IoWrite;
+use ser::SerializerBuilder;
+use value::Value;
+
+/// The maximum frame length of a new `Codec`, 8 MiB.
+pub const DEFAULT_MAX_FRAME_LEN: usize = 8 * 1024 * 1024;
+
+/// Splits a byte stream into top-level CBOR items and decodes them into `T`.
+///
+/// Item boundaries are found without decoding, so incomplete items simply wait for more input.
+/// Items longer than the maximum frame length are rejected in both directions, before they are
+/// buffered when decoding. Offsets in errors are relative to the start of the item.
+///
+/// ```rust
+/// # extern crate bytes;
+/// # extern crate serde_cbor;
+/// # extern crate tokio_util;
+/// use bytes::BytesMut;
+/// use serde_cbor::codec::Codec;
+/// use tokio_util::codec::Decoder;
+///
+/// # fn main() {
+/// let mut codec = Codec::<Vec<u32>>::new();
+/// let mut buf = BytesMut::from(&b"\x82\x01"[..]);
+/// assert_eq!(codec.decode(&mut buf).unwrap(), None);
+/// buf.extend_from_slice(b"\x02\x80");
+/// assert_eq!(codec.decode(&mut buf).unwrap(), Some(vec![1, 2]));
+/// assert_eq!(codec.decode(&mut buf).unwrap(), Some(vec![]));
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct Codec<T = Value> {
+    scanner: Scanner,
+    max_frame_len: usize,
+    options: SerializerBuilder,
+    output: PhantomData<fn() -> T>,
+}
+
+impl<T> Codec<T> {
+    /// Creates a codec with the default maximum frame length and serializer options.
+    pub fn new() -> Codec<T> {
+        Codec {
+            scanner: Scanner::new(),
+            max_frame_len: DEFAULT_MAX_FRAME_LEN,
+            options: SerializerBuilder::new(),
+            output: PhantomData,
+        }
+    }
+
+    /// Sets the maximum length of an encoded item.
+    pub fn max_frame_len(mut self, max_frame_len: usize) -> Codec<T> {
+        self.max_frame_len = max_frame_len;
+        self
+    }
+
+    /// Sets the options outgoing items are serialized with.
+    pub fn serializer_options(mut self, options: SerializerBuilder) -> Codec<T> {
+        self.options = options;
+        self
+    }
+
+    // The limit is crossed at the byte after the longest frame allowed.
+    fn frame_too_large(&self) -> Error {
+        Error::syntax(
+            ErrorCode::FrameTooLarge(self.max_frame_len),
+            self.max_frame_len as u64,
+        )
+    }
+}
+
+impl<T> Default for Codec<T> {
+    fn default() -> Codec<T> {
+        Codec::new()
+    }
+}
+
+impl<T> Decoder for Codec<T>
+where
+    T: DeserializeOwned,
+{
+    type Item = T;
+    type Error = Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<T>> {
+        match self.scanner.scan(src)? {
+            Scan::Complete(len) => {
+                if len > self.max_frame_len {
+                    return Err(self.frame_too_large());
+                }
+                let frame = src.split_to(len);
+                de::from_slice(&frame).map(Some)
+            }
+            Scan::Incomplete(missing) => {
+                if src.len().saturating_add(missing) > self.max_frame_len {
+                    self.scanner.reset();
+                    return Err(self.frame_too_large());
+                }
+                src.reserve(missing);
+                Ok(None)
+            }
+        }
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<T>> {
+        match self.decode(src)? {
+            Some(item) => Ok(Some(item)),
+            None if src.is_empty() => Ok(None),
+            None => Err(Error::syntax(
+                ErrorCode::EofWhileParsingValue,
+                src.len() as u64,
+            )),
+        }
+    }
+}
+
+impl<T, U> Encoder<U> for Codec<T>
+where
+    U: Serialize,
+{
+    type Error = Error;
+
+    fn encode(&mut self, item: U, dst: &mut BytesMut) -> Result<()> {
+        let start = dst.len();
+        let result = self
+            .options
+            .to_writer(IoWrite::new((&mut *dst).writer()), &item);
+        let len = dst.len() - start;
+        if result.is_err() || len > self.max_frame_len {
+            dst.truncate(start);
+        }
+        result?;
+        if len > self.max_frame_len {
+            return Err(self.frame_too_large());
+        }
+        Ok(())
+    }
+}
@@ -2,16 +2,35 @@
 
 use byteorder::{ByteOrder, BigEndian};
 use half::f16;
-use serde::de;
+use serde::de::{self, IntoDeserializer};
+use core::cmp;
+use core::str;
+use core::f32;
+use core::result;
+use core::marker::PhantomData;
+#[cfg(feature = "alloc")]
+use core::mem;
+use core::ops::Range;
+#[cfg(feature = "std")]
 use std::io;
-use std::str;
-use std::f32;
-use std::result;
-use std::marker::PhantomData;
+
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+use alloc::borrow::ToOwned;
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+use alloc::string::String;
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+use alloc::vec::Vec;
 
 use error::{Error, Result, ErrorCode};
+#[cfg(feature = "alloc")]
+use error::PathSegment;
 use read::Reference;
-pub use read::{Read, IoRead, SliceRead};
+use ser::EnumRepresentation;
+use value::{F16_TOKEN, RAW_VALUE_TOKEN, VALUE_TOKEN};
+pub use decoder::{Decoder, Event};
+pub use read::{Read, SliceRead, SliceReadFixed};
+#[cfg(feature = "std")]
+pub use read::{BufIoRead, IoRead};
 
 /// Decodes a value from CBOR data in a slice.
 ///
@@ -44,6 +63,35 @@ where
     Ok(value)
 }
 
+/// Decodes a value from CBOR data in a slice without allocating.
+///
+/// Strings and byte strings of definite length are borrowed from the input. Indefinite length
+/// strings are assembled in `scratch`, so they can only be visited as transient data. If one does
+/// not fit, a "buffer too small" error reports the size it needs.
+///
+/// ```
+/// # use serde_cbor::de;
+/// let mut scratch = [0; 8];
+/// let value: &str = de::from_slice_with_scratch(b"\x66foobar", &mut scratch).unwrap();
+/// assert_eq!(value, "foobar");
+///
+/// let v = b"\x7f\x63foo\x63bar\xff";
+/// let value: String = de::from_slice_with_scratch(&v[..], &mut scratch).unwrap();
+/// assert_eq!(value, "foobar");
+///
+/// let error = de::from_slice_with_scratch::<String>(&v[..], &mut scratch[..4]).unwrap_err();
+/// assert_eq!(error.required_size(), Some(6));
+/// ```
+pub fn from_slice_with_scratch<'a, 'b, T>(slice: &'a [u8], scratch: &'b mut [u8]) -> Result<T>
+where
+    T: de::Deserialize<'a>,
+{
+    let mut deserializer = Deserializer::from_slice_with_scratch(slice, scratch);
+    let value = de::Deserialize::deserialize(&mut deserializer)?;
+    deserializer.end()?;
+    Ok(value)
+}
+
 /// Decodes a value from CBOR data in a reader.
 ///
 /// # Examples
@@ -65,6 +113,7 @@ where
 /// let value: &str = de::from_reader(&v[..]).unwrap();
 /// assert_eq!(value, "foobar");
 /// ```
+#[cfg(feature = "std")]
 pub fn from_reader<T, R>(reader: R) -> Result<T>
 where
     T: de::DeserializeOwned,
@@ -76,13 +125,84 @@ where
     Ok(value)
 }
 
+/// Decodes a value from CBOR data in a buffered reader.
+///
+/// This is much faster than `from_reader`, which reads every head separately. Strings within
+/// the buffer of the reader are visited without copying them into the scratch buffer, but are
+/// only lent for the call to the visitor, since the buffer is refilled while decoding. Types
+/// that borrow strings, like `&str`, fail to decode, so use `String` instead.
+///
+/// ```
+/// # use serde_cbor::de;
+/// use std::io::BufReader;
+///
+/// let v: Vec<u8> = vec![0x66, 0x66, 0x6f, 0x6f, 0x62, 0x61, 0x72];
+/// let value: String = de::from_buf_reader(BufReader::new(&v[..])).unwrap();
+/// assert_eq!(value, "foobar");
+/// ```
+#[cfg(feature = "std")]
+pub fn from_buf_reader<T, R>(reader: R) -> Result<T>
+where
+    T: de::DeserializeOwned,
+    R: io::BufRead,
+{
+    let mut deserializer = Deserializer::from_buf_reader(reader);
+    let value = de::Deserialize::deserialize(&mut deserializer)?;
+    deserializer.end()?;
+    Ok(value)
+}
+
+/// Decodes the item at `path` within the CBOR data in a slice, skipping everything else.
+///
+/// Returns `None` if there is no item at `path`. The data following the item is not read, so
+/// it is not checked to be well-formed.
+///
+/// ```
+/// use serde_cbor::de::{self, Selector};
+///
+/// // {"meta": [0, 1, 2, {7: "x"}], "other": true}
+/// let v = b"\xa2\x64meta\x84\x00\x01\x02\xa1\x07\x61x\x65other\xf5";
+/// let path = [Selector::Key("meta"), Selector::Index(3), Selector::Int(7)];
+/// let value: Option<&str> = de::from_slice_at(v, &path).unwrap();
+/// assert_eq!(value, Some("x"));
+/// assert_eq!(de::range_at(v, &path).unwrap(), Some(12..14));
+/// let missing: Option<&str> = de::from_slice_at(v, &[Selector::Key("data")]).unwrap();
+/// assert_eq!(missing, None);
+/// ```
+pub fn from_slice_at<'a, T>(slice: &'a [u8], path: &[Selector]) -> Result<Option<T>>
+where
+    T: de::Deserialize<'a>,
+{
+    let mut deserializer = Deserializer::from_slice(slice);
+    if !deserializer.find_path(path)? {
+        return Ok(None);
+    }
+    de::Deserialize::deserialize(&mut deserializer).map(Some)
+}
+
+/// Returns the byte range of the item at `path` within the CBOR data in a slice, or `None` if
+/// there is no such item.
+///
+/// The item itself is checked to be well-formed, like a `RawValue`.
+pub fn range_at(slice: &[u8], path: &[Selector]) -> Result<Option<Range<usize>>> {
+    let mut deserializer = Deserializer::from_slice(slice);
+    if !deserializer.find_path(path)? {
+        return Ok(None);
+    }
+    let start = deserializer.read.offset() as usize;
+    deserializer.skip_value(false)?;
+    Ok(Some(start..deserializer.read.offset() as usize))
+}
+
 /// A Serde `Deserialize`r of CBOR data.
 pub struct Deserializer<R> {
     read: R,
-    buf: Vec<u8>,
     remaining_depth: u8,
+    enum_representation: Option<EnumRepresentation>,
+    keys: Keys,
 }
 
+#[cfg(feature = "std")]
 impl<R> Deserializer<IoRead<R>>
 where
     R: io::Read,
@@ -93,6 +213,17 @@ where
     }
 }
 
+#[cfg(feature = "std")]
+impl<R> Deserializer<BufIoRead<R>>
+where
+    R: io::BufRead,
+{
+    /// Constructs a `Deserializer` which reads from a `BufRead`er.
+    pub fn from_buf_reader(reader: R) -> Deserializer<BufIoRead<R>> {
+        Deserializer::new(BufIoRead::new(reader))
+    }
+}
+
 impl<'a> Deserializer<SliceRead<'a>> {
     /// Constructs a `Deserializer` which reads from a slice.
     ///
@@ -102,6 +233,18 @@ impl<'a> Deserializer<SliceRead<'a>> {
     }
 }
 
+impl<'a, 'b> Deserializer<SliceReadFixed<'a, 'b>> {
+    /// Constructs a `Deserializer` which reads from a slice and never allocates.
+    ///
+    /// Indefinite length strings are assembled in `scratch`.
+    pub fn from_slice_with_scratch(
+        bytes: &'a [u8],
+        scratch: &'b mut [u8],
+    ) -> Deserializer<SliceReadFixed<'a, 'b>> {
+        Deserializer::new(SliceReadFixed::new(bytes, scratch))
+    }
+}
+
 impl<'de, R> Deserializer<R>
 where
     R: Read<'de>,
@@ -112,11 +255,22 @@ where
     pub fn new(read: R) -> Self {
         Deserializer {
             read,
-            buf: Vec::new(),
             remaining_depth: 128,
+            enum_representation: None,
+            keys: Keys::default(),
         }
     }
 
+    /// Accepts enum variants with content only in the given representation.
+    ///
+    /// By default both the array form `[variant, content...]` and the single entry map form
+    /// `{variant: content}` are accepted. Unit variants written as just the variant identifier
+    /// are always accepted.
+    pub fn enum_representation(mut self, representation: EnumRepresentation) -> Self {
+        self.enum_representation = Some(representation);
+        self
+    }
+
     /// This method should be called after a value has been deserialized to ensure there is no
     /// trailing data in the input source.
     pub fn end(&mut self) -> Result<()> {
@@ -138,19 +292,186 @@ where
         }
     }
 
+    /// Streams the contents of the next byte string, definite or indefinite length, in chunks.
+    ///
+    /// Tags before the byte string are skipped. Chunks are borrowed from the input if possible
+    /// and are at most 16 KiB long otherwise, so a string of any size is decoded without
+    /// holding it in memory. The string has been consumed once the chunks return `None`.
+    ///
+    /// ```rust
+    /// use serde_cbor::Deserializer;
+    ///
+    /// let mut de = Deserializer::from_slice(b"\x5f\x42\x01\x02\x41\x03\xff");
+    /// let mut chunks = de.byte_string_chunks().unwrap();
+    /// assert_eq!(chunks.next_chunk().unwrap(), Some(&b"\x01\x02"[..]));
+    /// assert_eq!(chunks.next_chunk().unwrap(), Some(&b"\x03"[..]));
+    /// assert_eq!(chunks.next_chunk().unwrap(), None);
+    /// ```
+    pub fn byte_string_chunks<'a>(&'a mut self) -> Result<StringChunks<'a, R>> {
+        StringChunks::new(self, 2)
+    }
+
+    /// Streams the contents of the next text string, definite or indefinite length, in chunks.
+    ///
+    /// Like `byte_string_chunks`, but the chunks are checked to be UTF-8. A chunk may end in the
+    /// middle of a character if the string is longer than a chunk.
+    pub fn text_string_chunks<'a>(&'a mut self) -> Result<StringChunks<'a, R>> {
+        StringChunks::new(self, 3)
+    }
+
+    /// Copies the contents of the next byte string into a writer and returns their length.
+    #[cfg(feature = "std")]
+    pub fn copy_byte_string<W>(&mut self, writer: W) -> Result<u64>
+    where
+        W: io::Write,
+    {
+        self.byte_string_chunks()?.copy_to(writer)
+    }
+
+    /// Copies the contents of the next text string into a writer and returns their length.
+    #[cfg(feature = "std")]
+    pub fn copy_text_string<W>(&mut self, writer: W) -> Result<u64>
+    where
+        W: io::Write,
+    {
+        self.text_string_chunks()?.copy_to(writer)
+    }
+
+    /// Moves to the item at `path` within the next item, skipping everything before it.
+    ///
+    /// Tags are skipped along the way. Returns `false` if there is no item at `path`, because a
+    /// key is missing, an index is out of range or an item on the way is not a container. The
+    /// rest of the enclosing items is left unread either way, so the deserializer cannot be used
+    /// for following items afterwards.
+    pub fn find_path(&mut self, path: &[Selector]) -> Result<bool> {
+        for &selector in path {
+            let head = self.skip_head(false)?;
+            let (major, info, argument) = self.skip_tags(head, false)?;
+            let found = match (selector, major, info) {
+                (_, 7, 31) => return Err(self.error(ErrorCode::UnexpectedCode)),
+                (Selector::Index(index), 4, _) => self.find_index(index, info == 31, argument)?,
+                (Selector::Key(_), 5, _) | (Selector::Int(_), 5, _) => {
+                    self.find_key(selector, info == 31, argument)?
+                }
+                _ => false,
+            };
+            if !found {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    fn find_index(&mut self, index: usize, indefinite: bool, len: u64) -> Result<bool> {
+        if !indefinite && index as u64 >= len {
+            return Ok(false);
+        }
+        for _ in 0..index {
+            if !self.skip_item(false)? {
+                return Ok(false);
+            }
+        }
+        Ok(!indefinite || self.peek()? != Some(0xff))
+    }
+
+    fn find_key(&mut self, selector: Selector, indefinite: bool, len: u64) -> Result<bool> {
+        let mut remaining = len;
+        loop {
+            if !indefinite {
+                if remaining == 0 {
+                    return Ok(false);
+                }
+                remaining -= 1;
+            }
+            let head = self.skip_head(false)?;
+            if indefinite && head.0 == 7 && head.1 == 31 {
+                return Ok(false);
+            }
+            if self.key_matches(selector, head)? {
+                return Ok(true);
+            }
+            self.skip_value(false)?;
+        }
+    }
+
+    /// Reads the rest of a map key whose head has been read and compares it to `selector`.
+    fn key_matches(&mut self, selector: Selector, head: (u8, u8, u64)) -> Result<bool> {
+        let (major, info, argument) = self.skip_tags(head, false)?;
+        match (selector, major) {
+            (Selector::Key(key), 3) if info != 31 => {
+                let len = self.skip_len(argument)?;
+                return Ok(match self.read.read(len)? {
+                    Reference::Borrowed(buf) => buf == key.as_bytes(),
+                    Reference::Copied(buf) => buf == key.as_bytes(),
+                });
+            }
+            // Keys of indefinite length are compared chunk by chunk.
+            (Selector::Key(key), 3) => {
+                let key = key.as_bytes();
+                let mut matched = 0;
+                let mut matches = true;
+                loop {
+                    let (major, info, argument) = self.skip_head(false)?;
+                    if major == 7 && info == 31 {
+                        return Ok(matches && matched == key.len());
+                    }
+                    if major != 3 || info == 31 {
+                        return Err(self.error(ErrorCode::UnexpectedCode));
+                    }
+                    let len = self.skip_len(argument)?;
+                    let chunk = match self.read.read(len)? {
+                        Reference::Borrowed(buf) => buf,
+                        Reference::Copied(buf) => buf,
+                    };
+                    matches = matches && key.get(matched..).map_or(false, |rest| rest.starts_with(chunk));
+                    matched += len;
+                }
+            }
+            (Selector::Int(key), 0) => return Ok(key >= 0 && argument == key as u64),
+            (Selector::Int(key), 1) => return Ok(key < 0 && argument == (-1 - key) as u64),
+            _ => {}
+        }
+        if !self.skip_rest((major, info, argument), false)? {
+            return Err(self.error(ErrorCode::UnexpectedCode));
+        }
+        Ok(false)
+    }
+
     fn next(&mut self) -> Result<Option<u8>> {
-        self.read.next().map_err(Error::io)
+        self.read.next()
     }
 
     fn peek(&mut self) -> Result<Option<u8>> {
-        self.read.peek().map_err(Error::io)
+        self.read.peek()
     }
 
     fn consume(&mut self) {
         self.read.discard();
     }
 
-    fn error(&self, reason: ErrorCode) -> Error {
+    #[cfg(feature = "alloc")]
+    pub(crate) fn offset(&self) -> u64 {
+        self.read.offset()
+    }
+
+    // Adds the key of a map entry to the path of an error in its value.
+    fn within_key(&self, error: Error, key_start: usize, key: Key) -> Error {
+        self.keys.within_key(error, key_start, key, &self.read)
+    }
+
+    #[cfg(feature = "alloc")]
+    /// Reads the contents of a definite length string whose head has been read.
+    pub(crate) fn read_slice<'s>(&'s mut self, len: usize) -> Result<&'s [u8]>
+    where
+        'de: 's,
+    {
+        Ok(match self.read.read(len)? {
+            Reference::Borrowed(buf) => buf,
+            Reference::Copied(buf) => buf,
+        })
+    }
+
+    pub(crate) fn error(&self, reason: ErrorCode) -> Error {
         let offset = self.read.offset();
         Error::syntax(reason, offset)
     }
@@ -184,16 +505,14 @@ where
     where
         V: de::Visitor<'de>,
     {
-        self.buf.clear();
-        match self.read.read(len, &mut self.buf, 0)? {
+        match self.read.read(len)? {
             Reference::Borrowed(buf) => visitor.visit_borrowed_bytes(buf),
-            Reference::Copied => visitor.visit_bytes(&self.buf),
+            Reference::Copied(buf) => visitor.visit_bytes(buf),
         }
     }
 
     fn parse_indefinite_bytes(&mut self) -> Result<&[u8]> {
-        let mut offset = 0;
-        self.buf.clear();
+        self.read.clear_buffer();
         loop {
             let byte = self.parse_u8()?;
             let len = match byte {
@@ -212,54 +531,33 @@ where
                 _ => return Err(self.error(ErrorCode::UnexpectedCode)),
             };
 
-            match self.read.read(len, &mut self.buf, offset)? {
-                Reference::Borrowed(buf) => {
-                    let new_len = offset + len;
-                    if new_len > self.buf.len() {
-                        self.buf.resize(new_len, 0);
-                    }
-                    self.buf[offset..].copy_from_slice(buf);
-                }
-                Reference::Copied => {}
-            }
-
-            offset += len;
+            self.read.read_to_buffer(len)?;
         }
 
-        Ok(&self.buf[..offset])
-    }
-
-    fn convert_str<'a>(&self, buf: &'a [u8]) -> Result<&'a str> {
-        match str::from_utf8(buf) {
-            Ok(s) => Ok(s),
-            Err(e) => {
-                let shift = buf.len() - e.valid_up_to();
-                let offset = self.read.offset() - shift as u64;
-                Err(Error::syntax(ErrorCode::InvalidUtf8, offset))
-            }
-        }
+        Ok(self.read.take_buffer())
     }
 
     fn parse_str<V>(&mut self, len: usize, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        self.buf.clear();
-        match self.read.read(len, &mut self.buf, 0)? {
+        let offset = self.read.offset();
+        match self.read.read(len)? {
             Reference::Borrowed(buf) => {
-                let s = self.convert_str(buf)?;
+                let s = convert_str(buf, offset + len as u64)?;
+                self.keys.capture_span(offset, offset + len as u64);
                 visitor.visit_borrowed_str(s)
             }
-            Reference::Copied => {
-                let s = self.convert_str(&self.buf)?;
+            Reference::Copied(buf) => {
+                let s = convert_str(buf, offset + len as u64)?;
+                self.keys.capture_text(s);
                 visitor.visit_str(s)
             }
         }
     }
 
     fn parse_indefinite_str(&mut self) -> Result<&str> {
-        let mut offset = 0;
-        self.buf.clear();
+        self.read.clear_buffer();
         loop {
             let byte = self.parse_u8()?;
             let len = match byte {
@@ -278,24 +576,16 @@ where
                 _ => return Err(self.error(ErrorCode::UnexpectedCode)),
             };
 
-            match self.read.read(len, &mut self.buf, offset)? {
-                Reference::Borrowed(buf) => {
-                    let new_len = offset + len;
-                    if new_len > self.buf.len() {
-                        self.buf.resize(new_len, 0);
-                    }
-                    self.buf[offset..].copy_from_slice(buf);
-                }
-                Reference::Copied => {}
-            }
-
-            offset += len;
+            self.read.read_to_buffer(len)?;
         }
 
-        self.convert_str(&self.buf[..offset])
+        let offset = self.read.offset();
+        let s = convert_str(self.read.take_buffer(), offset)?;
+        self.keys.capture_text(s);
+        Ok(s)
     }
 
-    fn recursion_checked<F, T>(&mut self, f: F) -> Result<T>
+    pub(crate) fn recursion_checked<F, T>(&mut self, f: F) -> Result<T>
     where
         F: FnOnce(&mut Deserializer<R>) -> Result<T>,
     {
@@ -303,6 +593,8 @@ where
         if self.remaining_depth == 0 {
             return Err(self.error(ErrorCode::RecursionLimitExceeded));
         }
+        // Only keys that are single strings or integers are recorded.
+        self.keys.disarm();
         let r = f(self);
         self.remaining_depth += 1;
         r
@@ -313,7 +605,11 @@ where
         V: de::Visitor<'de>,
     {
         self.recursion_checked(|de| {
-            let value = visitor.visit_seq(SeqAccess { de, len: &mut len })?;
+            let value = visitor.visit_seq(SeqAccess {
+                de,
+                len: &mut len,
+                index: 0,
+            })?;
 
             if len != 0 {
                 Err(de.error(ErrorCode::TrailingData))
@@ -328,7 +624,7 @@ where
         V: de::Visitor<'de>,
     {
         self.recursion_checked(|de| {
-            let value = visitor.visit_seq(IndefiniteSeqAccess { de })?;
+            let value = visitor.visit_seq(IndefiniteSeqAccess { de, index: 0 })?;
             match de.next()? {
                 Some(0xff) => Ok(value),
                 Some(_) => Err(de.error(ErrorCode::TrailingData)),
@@ -342,7 +638,15 @@ where
         V: de::Visitor<'de>,
     {
         self.recursion_checked(|de| {
-            let value = visitor.visit_map(MapAccess { de, len: &mut len })?;
+            let key_start = de.keys.len();
+            let value = visitor.visit_map(MapAccess {
+                de: &mut *de,
+                len: &mut len,
+                key_start,
+                key: Key::Other,
+            });
+            de.keys.truncate(key_start);
+            let value = value?;
 
             if len != 0 {
                 Err(de.error(ErrorCode::TrailingData))
@@ -357,7 +661,14 @@ where
         V: de::Visitor<'de>,
     {
         self.recursion_checked(|de| {
-            let value = visitor.visit_map(IndefiniteMapAccess { de })?;
+            let key_start = de.keys.len();
+            let value = visitor.visit_map(IndefiniteMapAccess {
+                de: &mut *de,
+                key_start,
+                key: Key::Other,
+            });
+            de.keys.truncate(key_start);
+            let value = value?;
             match de.next()? {
                 Some(0xff) => Ok(value),
                 Some(_) => Err(de.error(ErrorCode::TrailingData)),
@@ -372,7 +683,11 @@ where
     {
         self.recursion_checked(|de| {
             let value = visitor.visit_enum(VariantAccess {
-                seq: SeqAccess { de, len: &mut len },
+                seq: SeqAccess {
+                    de,
+                    len: &mut len,
+                    index: 0,
+                },
             })?;
 
             if len != 0 {
@@ -389,7 +704,7 @@ where
     {
         self.recursion_checked(|de| {
             let value = visitor.visit_enum(
-                VariantAccess { seq: IndefiniteSeqAccess { de } },
+                VariantAccess { seq: IndefiniteSeqAccess { de, index: 0 } },
             )?;
             match de.next()? {
                 Some(0xff) => Ok(value),
@@ -399,6 +714,113 @@ where
         })
     }
 
+    fn parse_map_enum<V>(&mut self, len: Option<usize>, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        if let Some(len) = len {
+            if len != 1 {
+                return Err(de::Error::invalid_length(len, &"a map with a single entry"));
+            }
+        }
+        self.recursion_checked(|de| {
+            let value = visitor.visit_enum(MapVariantAccess { de })?;
+            if len.is_none() {
+                match de.next()? {
+                    Some(0xff) => {}
+                    Some(_) => return Err(de.error(ErrorCode::TrailingData)),
+                    None => return Err(de.error(ErrorCode::EofWhileParsingMap)),
+                }
+            }
+            Ok(value)
+        })
+    }
+
+    fn parse_enum_value<V>(&mut self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.peek()? {
+            Some(byte @ 0xa0...0xbf) => {
+                self.check_enum_representation(EnumRepresentation::Map)?;
+                self.consume();
+                match byte {
+                    0xa0...0xb7 => self.parse_map_enum(Some(byte as usize - 0xa0), visitor),
+                    0xb8 => {
+                        let len = self.parse_u8()?;
+                        self.parse_map_enum(Some(len as usize), visitor)
+                    }
+                    0xb9 => {
+                        let len = self.parse_u16()?;
+                        self.parse_map_enum(Some(len as usize), visitor)
+                    }
+                    0xba => {
+                        let len = self.parse_u32()?;
+                        self.parse_map_enum(Some(len as usize), visitor)
+                    }
+                    0xbb => {
+                        let len = self.parse_u64()?;
+                        if len > usize::max_value() as u64 {
+                            return Err(self.error(ErrorCode::LengthOutOfRange));
+                        }
+                        self.parse_map_enum(Some(len as usize), visitor)
+                    }
+                    0xbc...0xbe => Err(self.error(ErrorCode::UnassignedCode)),
+                    0xbf => self.parse_map_enum(None, visitor),
+
+                    _ => unreachable!(),
+                }
+            }
+            Some(byte @ 0x80...0x9f) => {
+                self.check_enum_representation(EnumRepresentation::Array)?;
+                self.consume();
+                match byte {
+                    0x80...0x97 => self.parse_enum(byte as usize - 0x80, visitor),
+                    0x98 => {
+                        let len = self.parse_u8()?;
+                        self.parse_enum(len as usize, visitor)
+                    }
+                    0x99 => {
+                        let len = self.parse_u16()?;
+                        self.parse_enum(len as usize, visitor)
+                    }
+                    0x9a => {
+                        let len = self.parse_u32()?;
+                        self.parse_enum(len as usize, visitor)
+                    }
+                    0x9b => {
+                        let len = self.parse_u64()?;
+                        if len > usize::max_value() as u64 {
+                            return Err(self.error(ErrorCode::LengthOutOfRange));
+                        }
+                        self.parse_enum(len as usize, visitor)
+                    }
+                    0x9c...0x9e => Err(self.error(ErrorCode::UnassignedCode)),
+                    0x9f => self.parse_indefinite_enum(visitor),
+
+                    _ => unreachable!(),
+                }
+            }
+            None => Err(self.error(ErrorCode::EofWhileParsingValue)),
+            _ => visitor.visit_enum(UnitVariantAccess { de: self }),
+        }
+    }
+
+    fn check_enum_representation(&self, representation: EnumRepresentation) -> Result<()> {
+        match self.enum_representation {
+            Some(expected) if expected != representation => {
+                let (unexpected, expected) = match expected {
+                    EnumRepresentation::Array => {
+                        (de::Unexpected::Map, "an enum encoded as an array")
+                    }
+                    EnumRepresentation::Map => (de::Unexpected::Seq, "an enum encoded as a map"),
+                };
+                Err(de::Error::invalid_type(unexpected, &expected))
+            }
+            _ => Ok(()),
+        }
+    }
+
     fn parse_f16(&mut self) -> Result<f32> {
         Ok(f32::from(f16::from_bits(self.parse_u16()?)))
     }
@@ -415,52 +837,280 @@ where
         Ok(BigEndian::read_f64(&buf))
     }
 
-    fn parse_value<V>(&mut self, visitor: V) -> Result<V::Value>
-    where
-        V: de::Visitor<'de>,
-    {
-        let byte = self.parse_u8()?;
-        match byte {
-            // Major type 0: an unsigned integer
-            0x00...0x17 => visitor.visit_u8(byte),
-            0x18 => {
-                let value = self.parse_u8()?;
-                visitor.visit_u8(value)
-            }
-            0x19 => {
-                let value = self.parse_u16()?;
-                visitor.visit_u16(value)
-            }
-            0x1a => {
-                let value = self.parse_u32()?;
-                visitor.visit_u32(value)
-            }
-            0x1b => {
-                let value = self.parse_u64()?;
-                visitor.visit_u64(value)
+    /// Skips the next item without decoding it, checking only that it is well-formed. Text
+    /// strings are not checked to be UTF-8.
+    ///
+    /// With `capture` the skipped input is appended to the scratch buffer.
+    pub(crate) fn skip_value(&mut self, capture: bool) -> Result<()> {
+        if self.skip_item(capture)? {
+            Ok(())
+        } else {
+            Err(self.error(ErrorCode::UnexpectedCode))
+        }
+    }
+
+    // Like `skip_value`, but returns `false` if the next byte is a break.
+    fn skip_item(&mut self, capture: bool) -> Result<bool> {
+        let head = self.skip_head(capture)?;
+        self.skip_rest(head, capture)
+    }
+
+    /// Skips the rest of an item whose head has been read, returning `false` if the head is a
+    /// break.
+    pub(crate) fn skip_rest(&mut self, head: (u8, u8, u64), capture: bool) -> Result<bool> {
+        let (major, info, argument) = self.skip_tags(head, capture)?;
+        match (major, info) {
+            (7, 31) => return Ok(false),
+            (2, 31) | (3, 31) => self.skip_chunks(major, capture)?,
+            (2, _) | (3, _) => {
+                let len = self.skip_len(argument)?;
+                self.skip_bytes(len, capture)?;
             }
-            0x1c...0x1f => Err(self.error(ErrorCode::UnassignedCode)),
+            (4, 31) => self.recursion_checked(|de| {
+                while de.skip_item(capture)? {}
+                Ok(())
+            })?,
+            (4, _) => self.recursion_checked(|de| {
+                for _ in 0..argument {
+                    de.skip_value(capture)?;
+                }
+                Ok(())
+            })?,
+            (5, 31) => self.recursion_checked(|de| {
+                while de.skip_item(capture)? {
+                    de.skip_value(capture)?;
+                }
+                Ok(())
+            })?,
+            (5, _) => self.recursion_checked(|de| {
+                for _ in 0..argument {
+                    de.skip_value(capture)?;
+                    de.skip_value(capture)?;
+                }
+                Ok(())
+            })?,
+            _ => {}
+        }
+        Ok(true)
+    }
 
-            // Major type 1: a negative integer
-            0x20...0x37 => visitor.visit_i8(-1 - (byte - 0x20) as i8),
-            0x38 => {
-                let value = self.parse_u8()?;
-                visitor.visit_i16(-1 - i16::from(value))
+    /// Skips the tags starting with `head` and returns the head of the item they apply to.
+    pub(crate) fn skip_tags(&mut self, mut head: (u8, u8, u64), capture: bool) -> Result<(u8, u8, u64)> {
+        if head.0 != 6 {
+            return Ok(head);
+        }
+        while head.0 == 6 {
+            head = self.skip_head(capture)?;
+        }
+        // A tag has to be followed by an item.
+        if head.0 == 7 && head.1 == 31 {
+            return Err(self.error(ErrorCode::UnexpectedCode));
+        }
+        Ok(head)
+    }
+
+    /// Reads a head and returns its major type, additional information and argument.
+    pub(crate) fn skip_head(&mut self, capture: bool) -> Result<(u8, u8, u64)> {
+        let byte = if capture {
+            let mut buf = [0; 1];
+            self.skip_read(&mut buf, true)?;
+            buf[0]
+        } else {
+            self.parse_u8()?
+        };
+        let major = byte >> 5;
+        let info = byte & 0x1f;
+        let argument = match info {
+            0...23 => u64::from(info),
+            24 => {
+                let mut buf = [0; 1];
+                self.skip_read(&mut buf, capture)?;
+                // Simple values below 32 have to be written in a single byte.
+                if major == 7 && buf[0] < 32 {
+                    return Err(self.error(ErrorCode::UnassignedCode));
+                }
+                u64::from(buf[0])
             }
-            0x39 => {
-                let value = self.parse_u16()?;
-                visitor.visit_i32(-1 - i32::from(value))
+            25 => {
+                let mut buf = [0; 2];
+                self.skip_read(&mut buf, capture)?;
+                u64::from(BigEndian::read_u16(&buf))
             }
-            0x3a => {
-                let value = self.parse_u32()?;
-                visitor.visit_i64(-1 - i64::from(value))
+            26 => {
+                let mut buf = [0; 4];
+                self.skip_read(&mut buf, capture)?;
+                u64::from(BigEndian::read_u32(&buf))
             }
-            0x3b => {
-                let value = self.parse_u64()?;
-                if value > i64::max_value() as u64 {
-                    return Err(self.error(ErrorCode::NumberOutOfRange));
-                }
-                visitor.visit_i64(-1 - value as i64)
+            27 => {
+                let mut buf = [0; 8];
+                self.skip_read(&mut buf, capture)?;
+                BigEndian::read_u64(&buf)
+            }
+            31 if major != 0 && major != 1 && major != 6 => 0,
+            _ => return Err(self.error(ErrorCode::UnassignedCode)),
+        };
+        Ok((major, info, argument))
+    }
+
+    /// Skips the chunks of an indefinite length string and the break that ends them.
+    fn skip_chunks(&mut self, major: u8, capture: bool) -> Result<()> {
+        loop {
+            match self.skip_head(capture)? {
+                (7, 31, _) => return Ok(()),
+                (chunk_major, info, argument) if chunk_major == major && info != 31 => {
+                    let len = self.skip_len(argument)?;
+                    self.skip_bytes(len, capture)?;
+                }
+                _ => return Err(self.error(ErrorCode::UnexpectedCode)),
+            }
+        }
+    }
+
+    pub(crate) fn skip_len(&self, argument: u64) -> Result<usize> {
+        if argument > usize::max_value() as u64 {
+            return Err(self.error(ErrorCode::LengthOutOfRange));
+        }
+        Ok(argument as usize)
+    }
+
+    fn skip_bytes(&mut self, len: usize, capture: bool) -> Result<()> {
+        if capture {
+            self.read.read_to_buffer(len)
+        } else {
+            self.read.skip(len)
+        }
+    }
+
+    /// Reads bytes of a head. With `capture` they are read through the scratch buffer and left
+    /// appended to it.
+    #[inline]
+    fn skip_read(&mut self, buf: &mut [u8], capture: bool) -> Result<()> {
+        if !capture {
+            return self.read.read_into(buf);
+        }
+        self.read.read_to_buffer(buf.len())?;
+        let scratch = self.read.take_buffer();
+        buf.copy_from_slice(&scratch[scratch.len() - buf.len()..]);
+        Ok(())
+    }
+
+    // Passes the bytes of the next item to the visitor, borrowed from slices and copied from
+    // readers.
+    fn parse_raw_value<V>(&mut self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        let start = self.read.offset();
+        let capture = self.read.slice_from(start).is_none();
+        if capture {
+            self.read.clear_buffer();
+        }
+        self.skip_value(capture)?;
+        match self.read.slice_from(start) {
+            Some(raw) => visitor.visit_borrowed_bytes(raw),
+            None => visitor.visit_bytes(self.read.take_buffer()),
+        }
+    }
+
+    // Like `parse_value`, but reports half precision floats as the `F16_TOKEN` enum variant so
+    // `Value` can remember their width.
+    fn parse_value_with_width<V>(&mut self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.peek()? {
+            Some(0xf9) => {
+                self.consume();
+                let value = self.parse_f16()?;
+                visitor.visit_enum(HalfFloatAccess { value })
+            }
+            Some(byte @ 0xc0...0xdb) => {
+                self.consume();
+                match byte {
+                    0xd8 => {
+                        self.parse_u8()?;
+                    }
+                    0xd9 => {
+                        self.parse_u16()?;
+                    }
+                    0xda => {
+                        self.parse_u32()?;
+                    }
+                    0xdb => {
+                        self.parse_u64()?;
+                    }
+                    _ => {}
+                }
+                self.parse_value_with_width(visitor)
+            }
+            _ => self.parse_value(visitor),
+        }
+    }
+
+    fn parse_value<V>(&mut self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        let byte = self.parse_u8()?;
+        match byte {
+            // Major type 0: an unsigned integer
+            0x00...0x17 => {
+                self.keys.capture_int(i64::from(byte));
+                visitor.visit_u8(byte)
+            }
+            0x18 => {
+                let value = self.parse_u8()?;
+                self.keys.capture_int(i64::from(value));
+                visitor.visit_u8(value)
+            }
+            0x19 => {
+                let value = self.parse_u16()?;
+                self.keys.capture_int(i64::from(value));
+                visitor.visit_u16(value)
+            }
+            0x1a => {
+                let value = self.parse_u32()?;
+                self.keys.capture_int(i64::from(value));
+                visitor.visit_u32(value)
+            }
+            0x1b => {
+                let value = self.parse_u64()?;
+                if value <= i64::max_value() as u64 {
+                    self.keys.capture_int(value as i64);
+                }
+                visitor.visit_u64(value)
+            }
+            0x1c...0x1f => Err(self.error(ErrorCode::UnassignedCode)),
+
+            // Major type 1: a negative integer
+            0x20...0x37 => {
+                let value = -1 - (byte - 0x20) as i8;
+                self.keys.capture_int(i64::from(value));
+                visitor.visit_i8(value)
+            }
+            0x38 => {
+                let value = -1 - i16::from(self.parse_u8()?);
+                self.keys.capture_int(i64::from(value));
+                visitor.visit_i16(value)
+            }
+            0x39 => {
+                let value = -1 - i32::from(self.parse_u16()?);
+                self.keys.capture_int(i64::from(value));
+                visitor.visit_i32(value)
+            }
+            0x3a => {
+                let value = -1 - i64::from(self.parse_u32()?);
+                self.keys.capture_int(value);
+                visitor.visit_i64(value)
+            }
+            0x3b => {
+                let value = self.parse_u64()?;
+                if value > i64::max_value() as u64 {
+                    return Err(self.error(ErrorCode::NumberOutOfRange));
+                }
+                let value = -1 - value as i64;
+                self.keys.capture_int(value);
+                visitor.visit_i64(value)
             }
             0x3c...0x3f => Err(self.error(ErrorCode::UnassignedCode)),
 
@@ -614,18 +1264,31 @@ where
     }
 }
 
+/// Checks that `buf` is UTF-8. `end` is the offset of the input just past `buf`.
+pub(crate) fn convert_str(buf: &[u8], end: u64) -> Result<&str> {
+    match str::from_utf8(buf) {
+        Ok(s) => Ok(s),
+        Err(e) => {
+            let shift = buf.len() - e.valid_up_to();
+            Err(Error::syntax(ErrorCode::InvalidUtf8, end - shift as u64))
+        }
+    }
+}
+
 impl<'de, 'a, R> de::Deserializer<'de> for &'a mut Deserializer<R>
 where
     R: Read<'de>,
 {
     type Error = Error;
 
+    // Errors raised by visitors have no offset yet, they are placed at the start of the item.
     #[inline]
     fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        self.parse_value(visitor)
+        let offset = self.read.offset();
+        self.parse_value(visitor).map_err(|e| e.at_offset(offset))
     }
 
     #[inline]
@@ -633,26 +1296,38 @@ where
     where
         V: de::Visitor<'de>,
     {
-        match self.peek()? {
+        let offset = self.read.offset();
+        let value = match self.peek()? {
             Some(0xf6) => {
                 self.consume();
                 visitor.visit_none()
             }
-            _ => visitor.visit_some(self),
-        }
+            _ => visitor.visit_some(&mut *self),
+        };
+        value.map_err(|e| e.at_offset(offset))
     }
 
     #[inline]
-    fn deserialize_newtype_struct<V>(self, _name: &str, visitor: V) -> Result<V::Value>
+    fn deserialize_newtype_struct<V>(self, name: &str, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        visitor.visit_newtype_struct(self)
+        let offset = self.read.offset();
+        let value = if name == VALUE_TOKEN {
+            self.parse_value_with_width(visitor)
+        } else if name == RAW_VALUE_TOKEN {
+            self.parse_raw_value(visitor)
+        } else {
+            visitor.visit_newtype_struct(&mut *self)
+        };
+        value.map_err(|e| e.at_offset(offset))
     }
 
     // Unit variants are encoded as just the variant identifier.
     // Tuple variants are encoded as an array of the variant identifier followed by the fields.
     // Struct variants are encoded as an array of the variant identifier followed by the struct.
+    // Alternatively variants are encoded as a map from the variant identifier to the content,
+    // which is an array of the fields for tuple variants.
     #[inline]
     fn deserialize_enum<V>(
         self,
@@ -663,39 +1338,19 @@ where
     where
         V: de::Visitor<'de>,
     {
-        match self.peek()? {
-            Some(byte @ 0x80...0x9f) => {
-                self.consume();
-                match byte {
-                    0x80...0x97 => self.parse_enum(byte as usize - 0x80, visitor),
-                    0x98 => {
-                        let len = self.parse_u8()?;
-                        self.parse_enum(len as usize, visitor)
-                    }
-                    0x99 => {
-                        let len = self.parse_u16()?;
-                        self.parse_enum(len as usize, visitor)
-                    }
-                    0x9a => {
-                        let len = self.parse_u32()?;
-                        self.parse_enum(len as usize, visitor)
-                    }
-                    0x9b => {
-                        let len = self.parse_u64()?;
-                        if len > usize::max_value() as u64 {
-                            return Err(self.error(ErrorCode::LengthOutOfRange));
-                        }
-                        self.parse_enum(len as usize, visitor)
-                    }
-                    0x9c...0x9e => Err(self.error(ErrorCode::UnassignedCode)),
-                    0x9f => self.parse_indefinite_enum(visitor),
+        let offset = self.read.offset();
+        self.parse_enum_value(visitor).map_err(|e| e.at_offset(offset))
+    }
 
-                    _ => unreachable!(),
-                }
-            }
-            None => Err(self.error(ErrorCode::EofWhileParsingValue)),
-            _ => visitor.visit_enum(UnitVariantAccess { de: self }),
-        }
+    // Ignored values are only checked to be well-formed, strings are neither copied nor checked
+    // to be UTF-8.
+    #[inline]
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.skip_value(false)?;
+        visitor.visit_unit()
     }
 
     #[inline]
@@ -705,7 +1360,7 @@ where
 
     forward_to_deserialize_any! {
         bool i8 i16 i32 i64 u8 u16 u32 u64 f32 f64 char str string unit
-        unit_struct seq tuple tuple_struct map struct identifier ignored_any
+        unit_struct seq tuple tuple_struct map struct identifier
         bytes byte_buf
     }
 }
@@ -714,9 +1369,157 @@ trait MakeError {
     fn error(&self, code: ErrorCode) -> Error;
 }
 
+/// The key of a map entry, as far as it is recorded.
+#[derive(Clone, Copy)]
+#[cfg_attr(not(feature = "alloc"), allow(dead_code))]
+enum Key {
+    // Not a single string or integer within `i64`, or not recorded without `alloc`.
+    Other,
+    // The next key is to be recorded.
+    Armed,
+    // The text is recorded from the map's `key_start` on.
+    Text,
+    // The text is borrowed from the input between these offsets, and only looked up there when
+    // an error needs it.
+    Span(u64, u64),
+    Int(i64),
+}
+
+impl Default for Key {
+    fn default() -> Key {
+        Key::Other
+    }
+}
+
+/// Records the keys of the map entries being decoded, so that errors in their values can name
+/// them in their path.
+///
+/// Keys borrowed from the input are recorded by their offsets. The texts of other keys, which
+/// are gone from a stream or scratch buffer by the time their value is decoded, are kept one
+/// after another in a single buffer, which each map cuts back to where its own key starts.
+#[derive(Default)]
+struct Keys {
+    #[cfg(feature = "alloc")]
+    text: String,
+    #[cfg(feature = "alloc")]
+    key: Key,
+}
+
+#[cfg(feature = "alloc")]
+impl Keys {
+    fn len(&self) -> usize {
+        self.text.len()
+    }
+
+    fn truncate(&mut self, len: usize) {
+        self.text.truncate(len);
+    }
+
+    fn arm(&mut self, key_start: usize) {
+        self.text.truncate(key_start);
+        self.key = Key::Armed;
+    }
+
+    fn disarm(&mut self) {
+        if let Key::Armed = self.key {
+            self.key = Key::Other;
+        }
+    }
+
+    fn take(&mut self) -> Key {
+        match mem::replace(&mut self.key, Key::Other) {
+            Key::Armed => Key::Other,
+            key => key,
+        }
+    }
+
+    #[inline]
+    fn capture_text(&mut self, text: &str) {
+        if let Key::Armed = self.key {
+            self.text.push_str(text);
+            self.key = Key::Text;
+        }
+    }
+
+    #[inline]
+    fn capture_span(&mut self, start: u64, end: u64) {
+        if let Key::Armed = self.key {
+            self.key = Key::Span(start, end);
+        }
+    }
+
+    #[inline]
+    fn capture_int(&mut self, value: i64) {
+        if let Key::Armed = self.key {
+            self.key = Key::Int(value);
+        }
+    }
+
+    fn within_key<'de, R>(&self, error: Error, key_start: usize, key: Key, read: &R) -> Error
+    where
+        R: Read<'de>,
+    {
+        let segment = match key {
+            Key::Text => PathSegment::Key(self.text[key_start..].to_owned()),
+            Key::Span(start, end) => match read.slice(start, end) {
+                Some(text) => PathSegment::Key(String::from_utf8_lossy(text).into_owned()),
+                None => PathSegment::Other,
+            },
+            Key::Int(value) => PathSegment::Int(value),
+            Key::Other | Key::Armed => PathSegment::Other,
+        };
+        error.within(segment)
+    }
+}
+
+// Without `alloc` errors have no path, and nothing is recorded.
+#[cfg(not(feature = "alloc"))]
+impl Keys {
+    fn len(&self) -> usize {
+        0
+    }
+
+    fn truncate(&mut self, _len: usize) {}
+
+    fn arm(&mut self, _key_start: usize) {}
+
+    fn disarm(&mut self) {}
+
+    fn take(&mut self) -> Key {
+        Key::Other
+    }
+
+    #[inline]
+    fn capture_text(&mut self, _text: &str) {}
+
+    #[inline]
+    fn capture_span(&mut self, _start: u64, _end: u64) {}
+
+    #[inline]
+    fn capture_int(&mut self, _value: i64) {}
+
+    fn within_key<'de, R>(&self, error: Error, _key_start: usize, _key: Key, _read: &R) -> Error
+    where
+        R: Read<'de>,
+    {
+        error
+    }
+}
+
+#[cfg(feature = "alloc")]
+fn within_index(error: Error, index: usize) -> Error {
+    error.within(PathSegment::Index(index))
+}
+
+#[cfg(not(feature = "alloc"))]
+fn within_index(error: Error, _index: usize) -> Error {
+    error
+}
+
 struct SeqAccess<'a, R: 'a> {
     de: &'a mut Deserializer<R>,
     len: &'a mut usize,
+    index: usize,
 }
 
 impl<'de, 'a, R> de::SeqAccess<'de> for SeqAccess<'a, R>
@@ -734,7 +1537,11 @@ where
         }
         *self.len -= 1;
 
-        let value = seed.deserialize(&mut *self.de)?;
+        let index = self.index;
+        self.index += 1;
+        let value = seed
+            .deserialize(&mut *self.de)
+            .map_err(|e| within_index(e, index))?;
         Ok(Some(value))
     }
 
@@ -754,6 +1561,7 @@ where
 
 struct IndefiniteSeqAccess<'a, R: 'a> {
     de: &'a mut Deserializer<R>,
+    index: usize,
 }
 
 impl<'de, 'a, R> de::SeqAccess<'de> for IndefiniteSeqAccess<'a, R>
@@ -772,7 +1580,11 @@ where
             None => return Err(self.de.error(ErrorCode::EofWhileParsingArray)),
         }
 
-        let value = seed.deserialize(&mut *self.de)?;
+        let index = self.index;
+        self.index += 1;
+        let value = seed
+            .deserialize(&mut *self.de)
+            .map_err(|e| within_index(e, index))?;
         Ok(Some(value))
     }
 }
@@ -789,6 +1601,9 @@ where
 struct MapAccess<'a, R: 'a> {
     de: &'a mut Deserializer<R>,
     len: &'a mut usize,
+    // Where the text of this map's current key starts in the recorded keys.
+    key_start: usize,
+    key: Key,
 }
 
 impl<'de, 'a, R> de::MapAccess<'de> for MapAccess<'a, R>
@@ -806,7 +1621,9 @@ where
         }
         *self.len -= 1;
 
+        self.de.keys.arm(self.key_start);
         let value = seed.deserialize(&mut *self.de)?;
+        self.key = self.de.keys.take();
         Ok(Some(value))
     }
 
@@ -814,7 +1631,10 @@ where
     where
         V: de::DeserializeSeed<'de>,
     {
-        seed.deserialize(&mut *self.de)
+        match seed.deserialize(&mut *self.de) {
+            Ok(value) => Ok(value),
+            Err(e) => Err(self.de.within_key(e, self.key_start, self.key)),
+        }
     }
 
     fn size_hint(&self) -> Option<usize> {
@@ -824,6 +1644,8 @@ where
 
 struct IndefiniteMapAccess<'a, R: 'a> {
     de: &'a mut Deserializer<R>,
+    key_start: usize,
+    key: Key,
 }
 
 impl<'de, 'a, R> de::MapAccess<'de> for IndefiniteMapAccess<'a, R>
@@ -842,7 +1664,9 @@ where
             None => return Err(self.de.error(ErrorCode::EofWhileParsingMap)),
         }
 
+        self.de.keys.arm(self.key_start);
         let value = seed.deserialize(&mut *self.de)?;
+        self.key = self.de.keys.take();
         Ok(Some(value))
     }
 
@@ -850,7 +1674,10 @@ where
     where
         V: de::DeserializeSeed<'de>,
     {
-        seed.deserialize(&mut *self.de)
+        match seed.deserialize(&mut *self.de) {
+            Ok(value) => Ok(value),
+            Err(e) => Err(self.de.within_key(e, self.key_start, self.key)),
+        }
     }
 }
 
@@ -915,6 +1742,63 @@ where
     }
 }
 
+struct MapVariantAccess<'a, R: 'a> {
+    de: &'a mut Deserializer<R>,
+}
+
+impl<'de, 'a, R> de::EnumAccess<'de> for MapVariantAccess<'a, R>
+where
+    R: Read<'de>,
+{
+    type Error = Error;
+    type Variant = MapVariantAccess<'a, R>;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, MapVariantAccess<'a, R>)>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        match self.de.peek()? {
+            Some(0xff) => return Err(de::Error::invalid_length(0, &"a map with a single entry")),
+            Some(_) => {}
+            None => return Err(self.de.error(ErrorCode::EofWhileParsingMap)),
+        }
+        let variant = seed.deserialize(&mut *self.de)?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de, 'a, R> de::VariantAccess<'de> for MapVariantAccess<'a, R>
+where
+    R: Read<'de>,
+{
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        de::Deserialize::deserialize(self.de)
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        seed.deserialize(self.de)
+    }
+
+    fn tuple_variant<V>(self, _len: usize, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        de::Deserializer::deserialize_any(self.de, visitor)
+    }
+
+    fn struct_variant<V>(self, _fields: &'static [&'static str], visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        de::Deserializer::deserialize_any(self.de, visitor)
+    }
+}
+
 struct VariantAccess<T> {
     seq: T,
 }
@@ -1003,6 +1887,274 @@ where
     }
 }
 
+struct HalfFloatAccess {
+    value: f32,
+}
+
+impl<'de> de::EnumAccess<'de> for HalfFloatAccess {
+    type Error = Error;
+    type Variant = HalfFloatAccess;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, HalfFloatAccess)>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        let deserializer: de::value::StrDeserializer<Error> = F16_TOKEN.into_deserializer();
+        let variant = seed.deserialize(deserializer)?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for HalfFloatAccess {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        Err(de::Error::invalid_type(
+            de::Unexpected::NewtypeVariant,
+            &"unit variant",
+        ))
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        seed.deserialize(self.value.into_deserializer())
+    }
+
+    fn tuple_variant<V>(self, _len: usize, _visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        Err(de::Error::invalid_type(
+            de::Unexpected::NewtypeVariant,
+            &"tuple variant",
+        ))
+    }
+
+    fn struct_variant<V>(self, _fields: &'static [&'static str], _visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        Err(de::Error::invalid_type(
+            de::Unexpected::NewtypeVariant,
+            &"struct variant",
+        ))
+    }
+}
+
+// Longer segments of a string are split so reading them from a stream buffers a bounded amount.
+pub(crate) const MAX_CHUNK_LEN: usize = 16 * 1024;
+
+/// The contents of a byte or text string, read in chunks.
+///
+/// Created by `Deserializer::byte_string_chunks` and `Deserializer::text_string_chunks`. With
+/// `alloc` it is also an iterator over owned chunks.
+pub struct StringChunks<'a, R: 'a> {
+    de: &'a mut Deserializer<R>,
+    major: u8,
+    indefinite: bool,
+    // Bytes left in the current segment, the whole string or one chunk of an indefinite string.
+    remaining: usize,
+    done: bool,
+    // The start of a character split between two chunks of a text string.
+    partial: [u8; 4],
+    partial_len: usize,
+}
+
+impl<'de, 'a, R> StringChunks<'a, R>
+where
+    R: Read<'de>,
+{
+    fn new(de: &'a mut Deserializer<R>, major: u8) -> Result<StringChunks<'a, R>> {
+        let byte = loop {
+            let byte = de.parse_u8()?;
+            match byte {
+                0xc0...0xd7 => {}
+                0xd8 => {
+                    de.parse_u8()?;
+                }
+                0xd9 => {
+                    de.parse_u16()?;
+                }
+                0xda => {
+                    de.parse_u32()?;
+                }
+                0xdb => {
+                    de.parse_u64()?;
+                }
+                _ => break byte,
+            }
+        };
+        if byte >> 5 != major {
+            return Err(de::Error::invalid_type(
+                de::Unexpected::Other("non-string value"),
+                &if major == 2 { "a byte string" } else { "a text string" },
+            ));
+        }
+        let mut chunks = StringChunks {
+            de,
+            major,
+            indefinite: byte & 0x1f == 31,
+            remaining: 0,
+            done: false,
+            partial: [0; 4],
+            partial_len: 0,
+        };
+        if !chunks.indefinite {
+            chunks.remaining = chunks.parse_len(byte)?;
+            chunks.done = chunks.remaining == 0;
+        }
+        Ok(chunks)
+    }
+
+    fn parse_len(&mut self, byte: u8) -> Result<usize> {
+        match byte & 0x1f {
+            len @ 0...23 => Ok(len as usize),
+            24 => Ok(self.de.parse_u8()? as usize),
+            25 => Ok(self.de.parse_u16()? as usize),
+            26 => Ok(self.de.parse_u32()? as usize),
+            27 => {
+                let len = self.de.parse_u64()?;
+                if len > usize::max_value() as u64 {
+                    return Err(self.de.error(ErrorCode::LengthOutOfRange));
+                }
+                Ok(len as usize)
+            }
+            _ => Err(self.de.error(ErrorCode::UnexpectedCode)),
+        }
+    }
+
+    /// Returns the next chunk of the string, or `None` once all of it has been read.
+    pub fn next_chunk<'s>(&'s mut self) -> Result<Option<&'s [u8]>>
+    where
+        'de: 's,
+    {
+        if self.done {
+            return Ok(None);
+        }
+        // Stays set if reading fails, so a broken string is not read any further.
+        self.done = true;
+        while self.remaining == 0 {
+            // Only chunks of indefinite strings get here, definite strings end with a segment.
+            let byte = self.de.parse_u8()?;
+            if byte == 0xff {
+                return Ok(None);
+            }
+            if byte >> 5 != self.major || byte & 0x1f == 31 {
+                return Err(self.de.error(ErrorCode::UnexpectedCode));
+            }
+            self.remaining = self.parse_len(byte)?;
+        }
+
+        let len = cmp::min(self.remaining, MAX_CHUNK_LEN);
+        self.remaining -= len;
+        let segment_end = self.remaining == 0;
+
+        let offset = self.de.read.offset();
+        let chunk = match self.de.read.read(len)? {
+            Reference::Borrowed(buf) => buf,
+            Reference::Copied(buf) => buf,
+        };
+        if self.major == 3 {
+            check_utf8(
+                chunk,
+                segment_end,
+                &mut self.partial,
+                &mut self.partial_len,
+                offset,
+            )?;
+        }
+        self.done = segment_end && !self.indefinite;
+        Ok(Some(chunk))
+    }
+
+    /// Copies the remaining chunks into a writer and returns their length.
+    #[cfg(feature = "std")]
+    pub fn copy_to<W>(mut self, mut writer: W) -> Result<u64>
+    where
+        W: io::Write,
+    {
+        let mut written = 0;
+        while let Some(chunk) = self.next_chunk()? {
+            writer.write_all(chunk).map_err(Error::io)?;
+            written += chunk.len() as u64;
+        }
+        Ok(written)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<'de, 'a, R> Iterator for StringChunks<'a, R>
+where
+    R: Read<'de>,
+{
+    type Item = Result<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Result<Vec<u8>>> {
+        match self.next_chunk() {
+            Ok(Some(chunk)) => Some(Ok(chunk.to_vec())),
+            Ok(None) => None,
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+/// Checks that a piece of a text string is UTF-8, given the start of a character left over from
+/// the previous piece. Unless the piece ends a chunk, an incomplete character at its end is left
+/// over for the next. `offset` is the offset of the input at the start of the piece.
+pub(crate) fn check_utf8(
+    mut piece: &[u8],
+    segment_end: bool,
+    partial: &mut [u8; 4],
+    partial_len: &mut usize,
+    offset: u64,
+) -> Result<()> {
+    let invalid = |at: usize| Error::syntax(ErrorCode::InvalidUtf8, offset + at as u64);
+    let mut start = 0;
+    if *partial_len > 0 {
+        let width = match partial[0] {
+            0xc0...0xdf => 2,
+            0xe0...0xef => 3,
+            _ => 4,
+        };
+        let take = cmp::min(width - *partial_len, piece.len());
+        partial[*partial_len..*partial_len + take].copy_from_slice(&piece[..take]);
+        *partial_len += take;
+        piece = &piece[take..];
+        start = take;
+        if *partial_len < width {
+            return if segment_end { Err(invalid(start)) } else { Ok(()) };
+        }
+        if str::from_utf8(&partial[..width]).is_err() {
+            return Err(invalid(0));
+        }
+        *partial_len = 0;
+    }
+    match str::from_utf8(piece) {
+        Ok(_) => Ok(()),
+        Err(e) if e.error_len().is_none() && !segment_end => {
+            let rest = &piece[e.valid_up_to()..];
+            partial[..rest.len()].copy_from_slice(rest);
+            *partial_len = rest.len();
+            Ok(())
+        }
+        Err(e) => Err(invalid(start + e.valid_up_to())),
+    }
+}
+
+/// A step from a container to one of its items, in the path given to `from_slice_at`,
+/// `range_at` or `Deserializer::find_path`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Selector<'a> {
+    /// The value stored under a text string key of a map.
+    Key(&'a str),
+    /// The value stored under an integer key of a map.
+    Int(i64),
+    /// The item at the given position of an array.
+    Index(usize),
+}
+
 /// Iterator that deserializes a stream into multiple CBOR values.
 ///
 /// A stream deserializer can be created from any CBOR deserializer using the
@@ -0,0 +1,407 @@
+use byteorder::{BigEndian, ByteOrder};
+use core::cmp;
+use half::f16;
+#[cfg(feature = "std")]
+use std::io;
+
+use de::{check_utf8, convert_str, MAX_CHUNK_LEN};
+use error::{Error, ErrorCode, Result};
+#[cfg(feature = "std")]
+use read::IoRead;
+use read::{Read, Reference, SliceRead};
+use value::FloatWidth;
+
+/// A single CBOR head, or the contents of a string, as reported by a `Decoder`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Event<'a> {
+    /// An unsigned integer.
+    UInt(u64),
+    /// The negative integer `-1 - n`, for `NInt(n)`.
+    NInt(u64),
+    /// A byte string of definite length, or a chunk of one of indefinite length.
+    Bytes(&'a [u8]),
+    /// A text string of definite length, or a chunk of one of indefinite length.
+    Text(&'a str),
+    /// The start of a byte string whose contents follow in pieces.
+    ///
+    /// The chunks of a string of indefinite length, `None`, follow until a `Break`. The contents
+    /// of a string or chunk of definite length longer than 16 KiB follow as `Chunk`s.
+    BytesStart(Option<usize>),
+    /// The start of a text string whose contents follow in pieces, like `BytesStart`.
+    TextStart(Option<usize>),
+    /// A piece of a string of definite length started by `BytesStart` or `TextStart`.
+    ///
+    /// The pieces of a text string may split characters. Together they are checked to be UTF-8.
+    Chunk(&'a [u8]),
+    /// The start of an array with the given number of items, or of indefinite length.
+    ArrayStart(Option<usize>),
+    /// The start of a map with the given number of entries, or of indefinite length.
+    MapStart(Option<usize>),
+    /// A tag, which applies to the next item.
+    Tag(u64),
+    /// A simple value. `false`, `true`, `null` and `undefined` are the simple values 20 to 23.
+    Simple(u8),
+    /// A float and the precision it is encoded with.
+    Float(f64, FloatWidth),
+    /// The end of an array, map or string of indefinite length.
+    Break,
+}
+
+// The same nesting limit the deserializer enforces.
+const MAX_DEPTH: usize = 128;
+
+#[derive(Clone, Copy, Debug)]
+enum Level {
+    /// A container with this many items left, counting the keys and values of maps apart.
+    Definite(u64),
+    /// An array of indefinite length.
+    Array,
+    /// A map of indefinite length, and whether the value of an entry is next.
+    Map(bool),
+}
+
+/// A pull-based decoder that reports CBOR data as a flat sequence of events.
+///
+/// Unlike `Deserializer` it does not go through serde, so tags, the lengths of containers and
+/// the widths of floats are reported as they are encoded. Items are not nested: an `ArrayStart`
+/// with a length is followed by the events of that many items, one of indefinite length by the
+/// events of its items and a `Break`. The nesting is still checked: a `Break` has to end a
+/// container or string of indefinite length, and the input may only end between top-level
+/// items.
+///
+/// ```rust
+/// use serde_cbor::de::{Decoder, Event};
+///
+/// let mut decoder = Decoder::from_slice(b"\xd8\x20\x82\x01\x63foo");
+/// assert_eq!(decoder.next_event().unwrap(), Some((0, Event::Tag(32))));
+/// assert_eq!(decoder.next_event().unwrap(), Some((2, Event::ArrayStart(Some(2)))));
+/// assert_eq!(decoder.next_event().unwrap(), Some((3, Event::UInt(1))));
+/// assert_eq!(decoder.next_event().unwrap(), Some((4, Event::Text("foo"))));
+/// assert_eq!(decoder.next_event().unwrap(), None);
+/// ```
+pub struct Decoder<R> {
+    read: R,
+    // The containers the next item is in, innermost last.
+    stack: [Level; MAX_DEPTH],
+    depth: usize,
+    // Whether the last head was a tag, which has to be followed by an item.
+    tagged: bool,
+    // The major type of the indefinite length string being read, if any.
+    string: Option<u8>,
+    // Bytes left of a long string that is returned in pieces, and whether it is text.
+    remaining: usize,
+    text: bool,
+    // The start of a character split between two pieces of a text string.
+    partial: [u8; 4],
+    partial_len: usize,
+}
+
+impl<'a> Decoder<SliceRead<'a>> {
+    /// Creates a decoder that reads from a slice.
+    pub fn from_slice(bytes: &'a [u8]) -> Decoder<SliceRead<'a>> {
+        Decoder::new(SliceRead::new(bytes))
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R> Decoder<IoRead<R>>
+where
+    R: io::Read,
+{
+    /// Creates a decoder that reads from a `Read`er.
+    pub fn from_reader(reader: R) -> Decoder<IoRead<R>> {
+        Decoder::new(IoRead::new(reader))
+    }
+}
+
+impl<'de, R> Decoder<R>
+where
+    R: Read<'de>,
+{
+    /// Creates a decoder from one of the serde_cbor input sources.
+    pub fn new(read: R) -> Decoder<R> {
+        Decoder {
+            read,
+            stack: [Level::Array; MAX_DEPTH],
+            depth: 0,
+            tagged: false,
+            string: None,
+            remaining: 0,
+            text: false,
+            partial: [0; 4],
+            partial_len: 0,
+        }
+    }
+
+    /// Returns the offset of the next byte to be read.
+    pub fn offset(&self) -> u64 {
+        self.read.offset()
+    }
+
+    /// Unwraps the input source.
+    pub fn into_inner(self) -> R {
+        self.read
+    }
+
+    /// Returns the next event and the offset of its first byte, or `None` at the end of the
+    /// input.
+    ///
+    /// Strings longer than 16 KiB are returned in pieces, so reading them from a stream buffers
+    /// a bounded amount.
+    pub fn next_event<'s>(&'s mut self) -> Result<Option<(u64, Event<'s>)>>
+    where
+        'de: 's,
+    {
+        let offset = self.read.offset();
+        if self.remaining > 0 {
+            return self.next_piece(offset).map(Some);
+        }
+        let byte = match self.read.next()? {
+            Some(byte) => byte,
+            None if self.depth > 0 || self.tagged || self.string.is_some() => {
+                return Err(self.error(ErrorCode::EofWhileParsingValue));
+            }
+            None => return Ok(None),
+        };
+        let major = byte >> 5;
+        let info = byte & 0x1f;
+
+        if let Some(string) = self.string {
+            if byte == 0xff {
+                self.string = None;
+                self.complete_item();
+                return Ok(Some((offset, Event::Break)));
+            }
+            if major != string || info == 31 {
+                return Err(self.error(ErrorCode::UnexpectedCode));
+            }
+        }
+
+        let mut complete = true;
+        let event = match (major, info) {
+            (2, 31) | (3, 31) => {
+                self.string = Some(major);
+                complete = false;
+                if major == 2 {
+                    Event::BytesStart(None)
+                } else {
+                    Event::TextStart(None)
+                }
+            }
+            (4, 31) => {
+                self.push(Level::Array)?;
+                complete = false;
+                Event::ArrayStart(None)
+            }
+            (5, 31) => {
+                self.push(Level::Map(false))?;
+                complete = false;
+                Event::MapStart(None)
+            }
+            (7, 25) => {
+                let bits = self.parse_u16()?;
+                Event::Float(f64::from(f16::from_bits(bits)), FloatWidth::Half)
+            }
+            (7, 26) => {
+                let mut buf = [0; 4];
+                self.read.read_into(&mut buf)?;
+                Event::Float(f64::from(BigEndian::read_f32(&buf)), FloatWidth::Single)
+            }
+            (7, 27) => {
+                let mut buf = [0; 8];
+                self.read.read_into(&mut buf)?;
+                Event::Float(BigEndian::read_f64(&buf), FloatWidth::Double)
+            }
+            (7, 31) => {
+                // A tag has to be followed by an item, not by a break.
+                match self.stack[..self.depth].last() {
+                    Some(&Level::Array) | Some(&Level::Map(false)) if !self.tagged => {}
+                    _ => return Err(self.error(ErrorCode::UnexpectedCode)),
+                }
+                self.depth -= 1;
+                Event::Break
+            }
+            (_, 28...31) => return Err(self.error(ErrorCode::UnassignedCode)),
+            (7, 24) => {
+                let value = self.parse_u8()?;
+                // Simple values below 32 have to be written in a single byte.
+                if value < 32 {
+                    return Err(self.error(ErrorCode::UnassignedCode));
+                }
+                Event::Simple(value)
+            }
+            (7, _) => Event::Simple(info),
+            _ => {
+                let argument = self.parse_argument(info)?;
+                match major {
+                    0 => Event::UInt(argument),
+                    1 => Event::NInt(argument),
+                    4 | 5 => {
+                        let len = self.length(argument)?;
+                        let items = if major == 4 {
+                            Some(argument)
+                        } else {
+                            argument.checked_mul(2)
+                        };
+                        match items {
+                            Some(0) => {}
+                            Some(items) => {
+                                self.push(Level::Definite(items))?;
+                                complete = false;
+                            }
+                            None => return Err(self.error(ErrorCode::LengthOutOfRange)),
+                        }
+                        if major == 4 {
+                            Event::ArrayStart(Some(len))
+                        } else {
+                            Event::MapStart(Some(len))
+                        }
+                    }
+                    6 => {
+                        self.tagged = true;
+                        return Ok(Some((offset, Event::Tag(argument))));
+                    }
+                    _ => return self.string_event(offset, major, argument).map(Some),
+                }
+            }
+        };
+        self.tagged = false;
+        if complete {
+            self.complete_item();
+        }
+        Ok(Some((offset, event)))
+    }
+
+    /// Returns a string of definite length whose head has been read, or the start of its
+    /// pieces if it is long.
+    fn string_event<'s>(
+        &'s mut self,
+        offset: u64,
+        major: u8,
+        argument: u64,
+    ) -> Result<(u64, Event<'s>)>
+    where
+        'de: 's,
+    {
+        let len = self.length(argument)?;
+        self.tagged = false;
+        if len > MAX_CHUNK_LEN {
+            self.remaining = len;
+            self.text = major == 3;
+            let event = if major == 2 {
+                Event::BytesStart(Some(len))
+            } else {
+                Event::TextStart(Some(len))
+            };
+            return Ok((offset, event));
+        }
+        // Chunks of indefinite strings are not items.
+        if self.string.is_none() {
+            self.complete_item();
+        }
+        let start = self.read.offset();
+        let buf = match self.read.read(len)? {
+            Reference::Borrowed(buf) => buf,
+            Reference::Copied(buf) => buf,
+        };
+        if major == 2 {
+            Ok((offset, Event::Bytes(buf)))
+        } else {
+            Ok((offset, Event::Text(convert_str(buf, start + len as u64)?)))
+        }
+    }
+
+    /// Returns the next piece of a long string.
+    fn next_piece<'s>(&'s mut self, offset: u64) -> Result<(u64, Event<'s>)>
+    where
+        'de: 's,
+    {
+        let len = cmp::min(self.remaining, MAX_CHUNK_LEN);
+        self.remaining -= len;
+        let end = self.remaining == 0;
+        if end && self.string.is_none() {
+            self.complete_item();
+        }
+        let piece = match self.read.read(len)? {
+            Reference::Borrowed(buf) => buf,
+            Reference::Copied(buf) => buf,
+        };
+        if self.text {
+            check_utf8(piece, end, &mut self.partial, &mut self.partial_len, offset)?;
+        }
+        Ok((offset, Event::Chunk(piece)))
+    }
+
+    fn push(&mut self, level: Level) -> Result<()> {
+        if self.depth == MAX_DEPTH {
+            return Err(self.error(ErrorCode::RecursionLimitExceeded));
+        }
+        self.stack[self.depth] = level;
+        self.depth += 1;
+        Ok(())
+    }
+
+    /// Counts a complete item towards the containers it is in.
+    fn complete_item(&mut self) {
+        while self.depth > 0 {
+            match self.stack[self.depth - 1] {
+                Level::Definite(ref mut remaining) => {
+                    *remaining -= 1;
+                    if *remaining > 0 {
+                        return;
+                    }
+                }
+                Level::Array => return,
+                Level::Map(ref mut value) => {
+                    *value = !*value;
+                    return;
+                }
+            }
+            self.depth -= 1;
+        }
+    }
+
+    fn error(&self, code: ErrorCode) -> Error {
+        Error::syntax(code, self.read.offset())
+    }
+
+    fn parse_u8(&mut self) -> Result<u8> {
+        match self.read.next()? {
+            Some(byte) => Ok(byte),
+            None => Err(self.error(ErrorCode::EofWhileParsingValue)),
+        }
+    }
+
+    fn parse_u16(&mut self) -> Result<u16> {
+        let mut buf = [0; 2];
+        self.read.read_into(&mut buf)?;
+        Ok(BigEndian::read_u16(&buf))
+    }
+
+    /// Reads the argument of a head with additional information below 28.
+    fn parse_argument(&mut self, info: u8) -> Result<u64> {
+        match info {
+            24 => Ok(u64::from(self.parse_u8()?)),
+            25 => Ok(u64::from(self.parse_u16()?)),
+            26 => {
+                let mut buf = [0; 4];
+                self.read.read_into(&mut buf)?;
+                Ok(u64::from(BigEndian::read_u32(&buf)))
+            }
+            27 => {
+                let mut buf = [0; 8];
+                self.read.read_into(&mut buf)?;
+                Ok(BigEndian::read_u64(&buf))
+            }
+            _ => Ok(u64::from(info)),
+        }
+    }
+
+    fn length(&self, argument: u64) -> Result<usize> {
+        if argument > usize::max_value() as u64 {
+            Err(self.error(ErrorCode::LengthOutOfRange))
+        } else {
+            Ok(argument as usize)
+        }
+    }
+}
@@ -0,0 +1,233 @@
+use half::f16;
+use serde::ser::{self, Serialize};
+
+use error::Result;
+use ser::Serializer;
+use write::Write;
+
+/// A low-level CBOR encoder that writes heads, tags and containers directly.
+///
+/// It produces messages whose shape serde's data model cannot express, like tagged arrays with
+/// mixed content or containers of unknown length. The caller is responsible for writing as many
+/// items as a container announces. Subtrees can be handed to serde with `serialize`, using the
+/// options of the underlying `Serializer`.
+///
+/// ```rust
+/// use serde_cbor::ser::Encoder;
+///
+/// // A COSE_Sign1 structure: tag 18 and an array of four items.
+/// let mut encoder = Encoder::new(Vec::new());
+/// encoder.write_tag(18).unwrap();
+/// encoder.begin_array(4).unwrap();
+/// encoder.write_bytes(b"\xa1\x01\x26").unwrap();
+/// encoder.begin_map(0).unwrap();
+/// encoder.serialize(&"payload").unwrap();
+/// encoder.write_bytes(&[0; 4]).unwrap();
+/// assert_eq!(
+///     encoder.into_inner(),
+///     b"\xd2\x84\x43\xa1\x01\x26\xa0\x67payload\x44\x00\x00\x00\x00"
+/// );
+/// ```
+pub struct Encoder<W> {
+    ser: Serializer<W>,
+}
+
+impl<W> Encoder<W>
+where
+    W: Write,
+{
+    /// Creates a new encoder that hands subtrees to a serializer with the default options.
+    pub fn new(writer: W) -> Encoder<W> {
+        Encoder {
+            ser: Serializer::new(writer),
+        }
+    }
+
+    /// Writes an unsigned integer.
+    #[inline]
+    pub fn write_uint(&mut self, value: u64) -> Result<()> {
+        self.ser.write_u64(0, value)
+    }
+
+    /// Writes the negative integer `-1 - value`.
+    ///
+    /// This covers negative integers down to -2^64, which do not fit into an `i64`.
+    #[inline]
+    pub fn write_nint(&mut self, value: u64) -> Result<()> {
+        self.ser.write_u64(1, value)
+    }
+
+    /// Writes a signed integer.
+    #[inline]
+    pub fn write_int(&mut self, value: i64) -> Result<()> {
+        if value < 0 {
+            self.write_nint(-(value + 1) as u64)
+        } else {
+            self.write_uint(value as u64)
+        }
+    }
+
+    /// Writes a byte string of definite length.
+    #[inline]
+    pub fn write_bytes(&mut self, value: &[u8]) -> Result<()> {
+        self.ser.write_u64(2, value.len() as u64)?;
+        self.ser.write_all(value)
+    }
+
+    /// Writes a text string of definite length.
+    #[inline]
+    pub fn write_str(&mut self, value: &str) -> Result<()> {
+        self.ser.write_u64(3, value.len() as u64)?;
+        self.ser.write_all(value.as_bytes())
+    }
+
+    /// Starts a byte string of indefinite length.
+    ///
+    /// It has to be followed by byte strings of definite length and a break.
+    #[inline]
+    pub fn begin_indefinite_bytes(&mut self) -> Result<()> {
+        self.ser.write_all(&[2 << 5 | 31])
+    }
+
+    /// Starts a text string of indefinite length.
+    ///
+    /// It has to be followed by text strings of definite length and a break.
+    #[inline]
+    pub fn begin_indefinite_str(&mut self) -> Result<()> {
+        self.ser.write_all(&[3 << 5 | 31])
+    }
+
+    /// Starts an array of `len` items.
+    #[inline]
+    pub fn begin_array(&mut self, len: usize) -> Result<()> {
+        self.ser.write_u64(4, len as u64)
+    }
+
+    /// Starts an array of indefinite length, which is ended with `write_break`.
+    #[inline]
+    pub fn begin_indefinite_array(&mut self) -> Result<()> {
+        self.ser.write_all(&[4 << 5 | 31])
+    }
+
+    /// Starts a map of `len` entries, each a key followed by a value.
+    #[inline]
+    pub fn begin_map(&mut self, len: usize) -> Result<()> {
+        self.ser.write_u64(5, len as u64)
+    }
+
+    /// Starts a map of indefinite length, which is ended with `write_break`.
+    #[inline]
+    pub fn begin_indefinite_map(&mut self) -> Result<()> {
+        self.ser.write_all(&[5 << 5 | 31])
+    }
+
+    /// Writes a tag, which applies to the next item.
+    #[inline]
+    pub fn write_tag(&mut self, tag: u64) -> Result<()> {
+        self.ser.write_u64(6, tag)
+    }
+
+    /// Ends the innermost array, map or string of indefinite length.
+    #[inline]
+    pub fn write_break(&mut self) -> Result<()> {
+        self.ser.write_all(&[0xff])
+    }
+
+    /// Writes a simple value.
+    ///
+    /// The values 24 to 31 are reserved and cannot be written.
+    #[inline]
+    pub fn write_simple(&mut self, value: u8) -> Result<()> {
+        match value {
+            0...23 => self.ser.write_all(&[7 << 5 | value]),
+            24...31 => Err(ser::Error::custom(format_args!(
+                "simple value {} is reserved",
+                value
+            ))),
+            _ => self.ser.write_all(&[7 << 5 | 24, value]),
+        }
+    }
+
+    /// Writes a boolean.
+    #[inline]
+    pub fn write_bool(&mut self, value: bool) -> Result<()> {
+        self.write_simple(if value { 21 } else { 20 })
+    }
+
+    /// Writes null.
+    #[inline]
+    pub fn write_null(&mut self) -> Result<()> {
+        self.write_simple(22)
+    }
+
+    /// Writes undefined.
+    #[inline]
+    pub fn write_undefined(&mut self) -> Result<()> {
+        self.write_simple(23)
+    }
+
+    /// Writes a half precision float.
+    ///
+    /// Fails if the value cannot be represented exactly with half precision. NaNs lose the
+    /// bits of their payload that do not fit.
+    #[inline]
+    pub fn write_f16(&mut self, value: f32) -> Result<()> {
+        let half = f16::from_f32(value);
+        if !value.is_nan() && f32::from(half) != value {
+            return Err(ser::Error::custom(format_args!(
+                "{} cannot be represented as a half precision float",
+                value
+            )));
+        }
+        self.ser.write_half(half.to_bits())
+    }
+
+    /// Writes a single precision float, regardless of the float policy.
+    #[inline]
+    pub fn write_f32(&mut self, value: f32) -> Result<()> {
+        self.ser.write_single(value.to_bits())
+    }
+
+    /// Writes a double precision float, regardless of the float policy.
+    #[inline]
+    pub fn write_f64(&mut self, value: f64) -> Result<()> {
+        self.ser.write_double(value.to_bits())
+    }
+
+    /// Writes the encoded bytes of one or more items as they are.
+    #[inline]
+    pub fn write_raw(&mut self, bytes: &[u8]) -> Result<()> {
+        self.ser.write_all(bytes)
+    }
+
+    /// Serializes a value as the next item with serde.
+    #[inline]
+    pub fn serialize<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(&mut self.ser)
+    }
+
+    /// Returns the serializer that `serialize` uses.
+    #[inline]
+    pub fn serializer(&mut self) -> &mut Serializer<W> {
+        &mut self.ser
+    }
+
+    /// Unwraps the writer.
+    #[inline]
+    pub fn into_inner(self) -> W {
+        self.ser.into_inner()
+    }
+}
+
+impl<W> From<Serializer<W>> for Encoder<W>
+where
+    W: Write,
+{
+    /// Creates an encoder that hands subtrees to `ser`, keeping its options.
+    fn from(ser: Serializer<W>) -> Encoder<W> {
+        Encoder { ser }
+    }
+}
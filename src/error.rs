@@ -1,14 +1,30 @@
 //! When serializing or deserializing CBOR goes wrong.
 use serde::de;
 use serde::ser;
+use core::convert::Infallible;
+use core::fmt;
+use core::result;
+#[cfg(feature = "std")]
 use std::error;
-use std::fmt;
+#[cfg(feature = "std")]
 use std::io;
-use std::result;
+
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+use alloc::boxed::Box;
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+use alloc::string::{String, ToString};
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+use alloc::vec::Vec;
 
 /// This type represents all possible errors that can occur when serializing or deserializing CBOR
 /// data.
-pub struct Error(Box<ErrorImpl>);
+pub struct Error(ErrorBox);
+
+// Errors are boxed to keep results small, except without `alloc` where nothing may allocate.
+#[cfg(feature = "alloc")]
+type ErrorBox = Box<ErrorImpl>;
+#[cfg(not(feature = "alloc"))]
+type ErrorBox = ErrorImpl;
 
 /// Alias for a `Result` with the error type `serde_cbor::Error`.
 pub type Result<T> = result::Result<T, Error>;
@@ -23,30 +39,237 @@ pub enum Category {
     Data,
     /// The error was causeed by prematurely reaching the end of the input data.
     Eof,
+    /// The error was caused by an output buffer that is too small for the encoded data, or a
+    /// scratch buffer that is too small for an indefinite length string.
+    BufferTooSmall,
+}
+
+/// The specific cause of a `serde_cbor::Error`.
+///
+/// More kinds may be added in future versions.
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// A message from a `Serialize` or `Deserialize` implementation, such as a type mismatch.
+    Message,
+    /// Reading or writing an IO stream failed.
+    Io,
+    /// An output or scratch buffer is too small, this many bytes are needed.
+    BufferTooSmall(usize),
+    /// An item is longer than the maximum frame length of a codec.
+    #[cfg(feature = "codec")]
+    FrameTooLarge(usize),
+    /// The input ended within an item.
+    EofWhileParsingValue,
+    /// The input ended within an array.
+    EofWhileParsingArray,
+    /// The input ended within a map.
+    EofWhileParsingMap,
+    /// A number does not fit the type it is decoded into.
+    NumberOutOfRange,
+    /// A length does not fit into a `usize`.
+    LengthOutOfRange,
+    /// A text string is not valid UTF-8.
+    InvalidUtf8,
+    /// The input uses a reserved or unassigned initial byte.
+    UnassignedCode,
+    /// An initial byte is not allowed where it appears, such as a misplaced break.
+    UnexpectedCode,
+    /// The input continues after the item, or an array or map has more items than expected.
+    TrailingData,
+    /// An array is shorter than expected.
+    ArrayTooShort,
+    /// An array is longer than expected.
+    ArrayTooLong,
+    /// Items are nested too deeply.
+    RecursionLimitExceeded,
+}
+
+/// A step on the way from the top-level item to the item an error occurred in.
+///
+/// Requires the `alloc` feature.
+#[cfg(feature = "alloc")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PathSegment {
+    /// The value of the map entry with this text key.
+    Key(String),
+    /// The value of the map entry with this integer key.
+    Int(i64),
+    /// The item at this index of an array.
+    Index(usize),
+    /// The value of a map entry whose key is neither text nor an integer within `i64`.
+    Other,
+}
+
+#[cfg(feature = "alloc")]
+impl fmt::Display for PathSegment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PathSegment::Key(ref key) => f.write_str(key),
+            PathSegment::Int(key) => write!(f, "{}", key),
+            PathSegment::Index(index) => write!(f, "[{}]", index),
+            PathSegment::Other => f.write_str("?"),
+        }
+    }
 }
 
 impl Error {
     /// The byte offset at which the error occurred.
+    ///
+    /// Errors found in the encoding point to the offending byte, errors raised while decoding an
+    /// item, such as type mismatches, to the start of the item. Errors that are not tied to a
+    /// position in the input, for example those from serializing, return 0.
     pub fn offset(&self) -> u64 {
-        self.0.offset
+        self.0.offset.unwrap_or(0)
+    }
+
+    /// The map keys and array indices leading from the top-level item to the item the error
+    /// occurred in, outermost first.
+    ///
+    /// The path is empty for errors in the top-level item and for errors that are not tied to a
+    /// position in the input.
+    ///
+    /// ```rust
+    /// # #[macro_use]
+    /// # extern crate serde_derive;
+    /// # extern crate serde_cbor;
+    /// use serde_cbor::error::PathSegment;
+    ///
+    /// #[derive(Debug, Deserialize)]
+    /// struct User {
+    ///     age: u32,
+    /// }
+    ///
+    /// # fn main() {
+    /// // {"users": [{"age": 1}, {"age": "2"}]}
+    /// let input = b"\xa1\x65users\x82\xa1\x63age\x01\xa1\x63age\x61\x32";
+    /// let error = serde_cbor::from_slice::<std::collections::BTreeMap<String, Vec<User>>>(input)
+    ///     .unwrap_err();
+    /// assert_eq!(
+    ///     error.path(),
+    ///     &[
+    ///         PathSegment::Key("users".to_string()),
+    ///         PathSegment::Index(1),
+    ///         PathSegment::Key("age".to_string()),
+    ///     ]
+    /// );
+    /// assert_eq!(error.offset(), 19);
+    /// assert_eq!(
+    ///     error.to_string(),
+    ///     "invalid type: string \"2\", expected u32 at offset 19 in users[1].age"
+    /// );
+    /// # }
+    /// ```
+    #[cfg(feature = "alloc")]
+    pub fn path(&self) -> &[PathSegment] {
+        &self.0.path
+    }
+
+    /// The specific cause of this error.
+    pub fn kind(&self) -> ErrorKind {
+        match self.0.code {
+            #[cfg(feature = "alloc")]
+            ErrorCode::Message(_) => ErrorKind::Message,
+            #[cfg(not(feature = "alloc"))]
+            ErrorCode::Message => ErrorKind::Message,
+            #[cfg(feature = "std")]
+            ErrorCode::Io(_) => ErrorKind::Io,
+            ErrorCode::BufferTooSmall(required) => ErrorKind::BufferTooSmall(required),
+            #[cfg(feature = "codec")]
+            ErrorCode::FrameTooLarge(max) => ErrorKind::FrameTooLarge(max),
+            ErrorCode::EofWhileParsingValue => ErrorKind::EofWhileParsingValue,
+            ErrorCode::EofWhileParsingArray => ErrorKind::EofWhileParsingArray,
+            ErrorCode::EofWhileParsingMap => ErrorKind::EofWhileParsingMap,
+            ErrorCode::NumberOutOfRange => ErrorKind::NumberOutOfRange,
+            ErrorCode::LengthOutOfRange => ErrorKind::LengthOutOfRange,
+            ErrorCode::InvalidUtf8 => ErrorKind::InvalidUtf8,
+            ErrorCode::UnassignedCode => ErrorKind::UnassignedCode,
+            ErrorCode::UnexpectedCode => ErrorKind::UnexpectedCode,
+            ErrorCode::TrailingData => ErrorKind::TrailingData,
+            ErrorCode::ArrayTooShort => ErrorKind::ArrayTooShort,
+            ErrorCode::ArrayTooLong => ErrorKind::ArrayTooLong,
+            ErrorCode::RecursionLimitExceeded => ErrorKind::RecursionLimitExceeded,
+        }
     }
 
     pub(crate) fn syntax(code: ErrorCode, offset: u64) -> Error {
-        Error(Box::new(ErrorImpl { code, offset }))
+        Error::new(code, Some(offset))
+    }
+
+    // Without `alloc` the conversion into `ErrorBox` does nothing.
+    #[allow(clippy::useless_conversion)]
+    fn new(code: ErrorCode, offset: Option<u64>) -> Error {
+        Error(
+            ErrorImpl {
+                code,
+                offset,
+                #[cfg(feature = "alloc")]
+                path: Vec::new(),
+            }
+            .into(),
+        )
+    }
+
+    pub(crate) fn buffer_too_small(required: usize) -> Error {
+        Error::new(ErrorCode::BufferTooSmall(required), None)
     }
 
+    #[cfg(feature = "alloc")]
+    fn message<T>(msg: T) -> Error
+    where
+        T: fmt::Display,
+    {
+        Error::new(ErrorCode::Message(msg.to_string()), None)
+    }
+
+    #[cfg(not(feature = "alloc"))]
+    fn message<T>(_msg: T) -> Error
+    where
+        T: fmt::Display,
+    {
+        Error::new(ErrorCode::Message, None)
+    }
+
+    /// Places an error that has no offset yet at `offset`.
+    pub(crate) fn at_offset(mut self, offset: u64) -> Error {
+        if self.0.offset.is_none() {
+            self.0.offset = Some(offset);
+        }
+        self
+    }
+
+    /// Adds the step into the item the error occurred in to the front of the path.
+    #[cfg(feature = "alloc")]
+    pub(crate) fn within(mut self, segment: PathSegment) -> Error {
+        self.0.path.insert(0, segment);
+        self
+    }
+
+    /// If the output or scratch buffer was too small, the number of bytes that are needed.
+    pub fn required_size(&self) -> Option<usize> {
+        match self.0.code {
+            ErrorCode::BufferTooSmall(required) => Some(required),
+            _ => None,
+        }
+    }
+
+    #[cfg(feature = "std")]
     pub(crate) fn io(error: io::Error) -> Error {
-        Error(Box::new(ErrorImpl {
-            code: ErrorCode::Io(error),
-            offset: 0,
-        }))
+        Error::new(ErrorCode::Io(error), None)
     }
 
     /// Categorizes the cause of this error.
     pub fn classify(&self) -> Category {
         match self.0.code {
+            #[cfg(feature = "alloc")]
             ErrorCode::Message(_) => Category::Data,
+            #[cfg(not(feature = "alloc"))]
+            ErrorCode::Message => Category::Data,
+            #[cfg(feature = "std")]
             ErrorCode::Io(_) => Category::Io,
+            ErrorCode::BufferTooSmall(_) => Category::BufferTooSmall,
+            #[cfg(feature = "codec")]
+            ErrorCode::FrameTooLarge(_) => Category::Data,
             ErrorCode::EofWhileParsingValue |
             ErrorCode::EofWhileParsingArray |
             ErrorCode::EofWhileParsingMap => Category::Eof,
@@ -86,6 +309,14 @@ impl Error {
         }
     }
 
+    /// Returns true if this error was caused by an output or scratch buffer that is too small.
+    pub fn is_buffer_too_small(&self) -> bool {
+        match self.classify() {
+            Category::BufferTooSmall => true,
+            _ => false,
+        }
+    }
+
     /// Returns true if this error was caused by prematurely reaching the end of the input data.
     pub fn is_eof(&self) -> bool {
         match self.classify() {
@@ -95,6 +326,7 @@ impl Error {
     }
 }
 
+#[cfg(feature = "std")]
 impl error::Error for Error {
     fn description(&self) -> &str {
         match self.0.code {
@@ -111,19 +343,45 @@ impl error::Error for Error {
     }
 }
 
+// Serde's error traits require `core::error::Error` when `std` is not available.
+#[cfg(not(feature = "std"))]
+impl ::core::error::Error for Error {}
+
+#[cfg(feature = "std")]
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Error {
+        Error::io(error)
+    }
+}
+
+impl From<Infallible> for Error {
+    fn from(error: Infallible) -> Error {
+        match error {}
+    }
+}
+
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        if self.0.offset == 0 {
-            fmt::Display::fmt(&self.0.code, f)
-        } else {
-            write!(f, "{} at offset {}", self.0.code, self.0.offset)
+        fmt::Display::fmt(&self.0.code, f)?;
+        if let Some(offset) = self.0.offset {
+            write!(f, " at offset {}", offset)?;
+        }
+        #[cfg(feature = "alloc")]
+        for (i, segment) in self.0.path.iter().enumerate() {
+            let separator = match (i, segment) {
+                (0, _) => " in ",
+                (_, &PathSegment::Index(_)) => "",
+                _ => ".",
+            };
+            write!(f, "{}{}", separator, segment)?;
         }
+        Ok(())
     }
 }
 
 impl fmt::Debug for Error {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-        fmt::Debug::fmt(&*self.0, fmt)
+        fmt::Debug::fmt(&self.0, fmt)
     }
 }
 
@@ -132,10 +390,7 @@ impl de::Error for Error {
     where
         T: fmt::Display,
     {
-        Error(Box::new(ErrorImpl {
-            code: ErrorCode::Message(msg.to_string()),
-            offset: 0,
-        }))
+        Error::message(msg)
     }
 
     fn invalid_type(unexp: de::Unexpected, exp: &de::Expected) -> Error {
@@ -152,23 +407,30 @@ impl ser::Error for Error {
     where
         T: fmt::Display,
     {
-        Error(Box::new(ErrorImpl {
-            code: ErrorCode::Message(msg.to_string()),
-            offset: 0,
-        }))
+        Error::message(msg)
     }
 }
 
 #[derive(Debug)]
 struct ErrorImpl {
     code: ErrorCode,
-    offset: u64,
+    offset: Option<u64>,
+    #[cfg(feature = "alloc")]
+    path: Vec<PathSegment>,
 }
 
 #[derive(Debug)]
 pub(crate) enum ErrorCode {
+    #[cfg(feature = "alloc")]
     Message(String),
+    // Without `alloc` the message cannot be stored.
+    #[cfg(not(feature = "alloc"))]
+    Message,
+    #[cfg(feature = "std")]
     Io(io::Error),
+    BufferTooSmall(usize),
+    #[cfg(feature = "codec")]
+    FrameTooLarge(usize),
     EofWhileParsingValue,
     EofWhileParsingArray,
     EofWhileParsingMap,
@@ -186,8 +448,19 @@ pub(crate) enum ErrorCode {
 impl fmt::Display for ErrorCode {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
+            #[cfg(feature = "alloc")]
             ErrorCode::Message(ref msg) => f.write_str(msg),
+            #[cfg(not(feature = "alloc"))]
+            ErrorCode::Message => f.write_str("custom error"),
+            #[cfg(feature = "std")]
             ErrorCode::Io(ref err) => fmt::Display::fmt(err, f),
+            ErrorCode::BufferTooSmall(required) => {
+                write!(f, "buffer too small, {} bytes required", required)
+            }
+            #[cfg(feature = "codec")]
+            ErrorCode::FrameTooLarge(max) => {
+                write!(f, "frame larger than the maximum of {} bytes", max)
+            }
             ErrorCode::EofWhileParsingValue => f.write_str("EOF while parsing a value"),
             ErrorCode::EofWhileParsingArray => f.write_str("EOF while parsing an array"),
             ErrorCode::EofWhileParsingMap => f.write_str("EOF while parsing a map"),
@@ -0,0 +1,556 @@
+//! An index of the byte offsets of the items in a CBOR document.
+//!
+//! Building an index walks a document once, recording where the elements of arrays and the
+//! values of maps start and end, down to a chosen depth. Nothing is decoded apart from the keys
+//! of maps. The index can be serialized itself, so a large archive only has to be walked once:
+//! later the index is loaded and single items are decoded by seeking straight to them.
+//!
+//! ```rust
+//! use serde_cbor::de::Selector;
+//! use serde_cbor::index::Index;
+//!
+//! // {"users": [{"name": "a"}, {"name": "b"}]}
+//! let archive = b"\xa1\x65users\x82\xa1\x64name\x61a\xa1\x64name\x61b";
+//! let index = Index::from_slice(archive, 3).unwrap();
+//!
+//! let stored = serde_cbor::to_vec(&index).unwrap();
+//! let index: Index = serde_cbor::from_slice(&stored).unwrap();
+//!
+//! let path = [Selector::Key("users"), Selector::Index(1), Selector::Key("name")];
+//! let name: Option<&str> = index.decode_slice(archive, &path).unwrap();
+//! assert_eq!(name, Some("b"));
+//! ```
+
+#[cfg(not(feature = "std"))]
+use alloc::borrow::ToOwned;
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use core::fmt;
+use core::ops::Range;
+use core::result;
+use serde::de::{self, Deserialize};
+use serde::ser::{self, Serialize, SerializeTuple};
+#[cfg(feature = "std")]
+use std::io;
+
+use de::{convert_str, Deserializer, Selector};
+use error::{Error, ErrorCode, Result};
+#[cfg(feature = "std")]
+use read::BufIoRead;
+use read::{Read, SliceRead};
+use value::ObjectKey;
+
+/// The byte offsets of the items in a CBOR document, down to a maximum depth.
+///
+/// Serialized, an index is the array `[depth, root]`. Every entry is an array
+/// `[start, end, kind, items]`, where `kind` is 0 for items that are not indexed further and
+/// `items` is null, 1 for arrays and 2 for maps. The items of an array are a list of entries,
+/// those of a map a list of `[entry, key]` pairs, with the key left out if it is not a valid
+/// `ObjectKey`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Index {
+    depth: usize,
+    root: IndexEntry,
+}
+
+impl Index {
+    /// Indexes the next item of a deserializer down to `depth` levels of nesting.
+    ///
+    /// With a depth of 0 only the range of the item itself is recorded, with a depth of 1 also
+    /// the ranges of its elements or values, and so on. Offsets are those of the deserializer,
+    /// so they are counted from the start of its input. Readers should be buffered and read
+    /// with `Deserializer::from_buf_reader`.
+    pub fn new<'de, R>(de: &mut Deserializer<R>, depth: usize) -> Result<Index>
+    where
+        R: Read<'de>,
+    {
+        let start = de.offset();
+        let head = de.skip_head(false)?;
+        let root = index_item(de, start, head, depth)?;
+        Ok(Index { depth, root })
+    }
+
+    /// Indexes the item at the start of a slice down to `depth` levels of nesting.
+    pub fn from_slice(slice: &[u8], depth: usize) -> Result<Index> {
+        Index::new(&mut Deserializer::from_slice(slice), depth)
+    }
+
+    /// The depth the index was built with.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// The entry of the indexed item.
+    pub fn root(&self) -> &IndexEntry {
+        &self.root
+    }
+
+    /// Returns the entry at `path`, or `None` if it does not exist or is not indexed.
+    pub fn get(&self, path: &[Selector]) -> Option<&IndexEntry> {
+        let mut entry = &self.root;
+        for &selector in path {
+            entry = entry.get(selector)?;
+        }
+        Some(entry)
+    }
+
+    /// Decodes the item at `path` from the indexed slice.
+    ///
+    /// Returns `None` if the item is not indexed.
+    pub fn decode_slice<'a, T>(&self, slice: &'a [u8], path: &[Selector]) -> Result<Option<T>>
+    where
+        T: Deserialize<'a>,
+    {
+        let entry = match self.get(path) {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+        if entry.end > slice.len() as u64 {
+            return Err(Error::syntax(
+                ErrorCode::EofWhileParsingValue,
+                slice.len() as u64,
+            ));
+        }
+        let mut read = SliceRead::new(&slice[..entry.end as usize]);
+        read.skip(entry.start as usize)?;
+        let mut de = Deserializer::new(read);
+        let value = T::deserialize(&mut de)?;
+        de.end()?;
+        Ok(Some(value))
+    }
+
+    /// Seeks to the item at `path` in the indexed stream and decodes it.
+    ///
+    /// Returns `None` if the item is not indexed. Offsets in errors are counted from the start
+    /// of the stream.
+    #[cfg(feature = "std")]
+    pub fn decode_reader<T, R>(&self, mut reader: R, path: &[Selector]) -> Result<Option<T>>
+    where
+        T: de::DeserializeOwned,
+        R: io::Read + io::Seek,
+    {
+        let entry = match self.get(path) {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+        reader.seek(io::SeekFrom::Start(entry.start))?;
+        let reader = io::BufReader::new(reader.take(entry.end - entry.start));
+        let read = BufIoRead::with_offset(reader, entry.start);
+        let mut de = Deserializer::new(read);
+        let value = T::deserialize(&mut de)?;
+        de.end()?;
+        Ok(Some(value))
+    }
+}
+
+/// The position of an item in an indexed document.
+#[derive(Clone, Debug, PartialEq)]
+pub struct IndexEntry {
+    start: u64,
+    end: u64,
+    items: IndexItems,
+    // The positions of the map entries with keys, sorted by key and then by position.
+    by_key: Vec<usize>,
+}
+
+impl IndexEntry {
+    fn new(start: u64, end: u64, items: IndexItems) -> IndexEntry {
+        let mut by_key = Vec::new();
+        if let IndexItems::Map(ref entries) = items {
+            by_key.extend((0..entries.len()).filter(|&i| entries[i].0.is_some()));
+            // The sort is stable, so the first of duplicate keys stays first.
+            by_key.sort_by(|&a, &b| entries[a].0.cmp(&entries[b].0));
+        }
+        IndexEntry {
+            start,
+            end,
+            items,
+            by_key,
+        }
+    }
+
+    /// The offset of the first byte of the item, including its tags.
+    pub fn start(&self) -> u64 {
+        self.start
+    }
+
+    /// The range of bytes the item occupies.
+    pub fn range(&self) -> Range<u64> {
+        self.start..self.end
+    }
+
+    /// The indexed elements or values of the item.
+    pub fn items(&self) -> &IndexItems {
+        &self.items
+    }
+
+    /// Returns the entry of an element or value of this item.
+    ///
+    /// Maps are searched for the first value with the given key, with a binary search over
+    /// their keys.
+    pub fn get(&self, selector: Selector) -> Option<&IndexEntry> {
+        match self.items {
+            IndexItems::Array(ref entries) => match selector {
+                Selector::Index(index) => entries.get(index),
+                _ => None,
+            },
+            IndexItems::Map(ref entries) => {
+                let key = |i: usize| entries[i].0.as_ref().expect("indexed keys are present");
+                let pos = self
+                    .by_key
+                    .partition_point(|&i| compare_key(key(i), selector) == Ordering::Less);
+                match self.by_key.get(pos) {
+                    Some(&i) if compare_key(key(i), selector) == Ordering::Equal => {
+                        Some(&entries[i].1)
+                    }
+                    _ => None,
+                }
+            }
+            IndexItems::None => None,
+        }
+    }
+}
+
+/// The indexed contents of an item.
+#[derive(Clone, Debug, PartialEq)]
+pub enum IndexItems {
+    /// The item is not an array or map, or lies at the maximum depth.
+    None,
+    /// The entries of the elements of an array.
+    Array(Vec<IndexEntry>),
+    /// The keys and the entries of the values of a map. Keys that are not a valid `ObjectKey`,
+    /// like arrays or integers outside the range of `i64`, are `None`.
+    Map(Vec<(Option<ObjectKey>, IndexEntry)>),
+}
+
+/// The major type, additional information and argument of a head.
+type Head = (u8, u8, u64);
+
+/// Indexes an item whose head has been read and which starts at `start`.
+fn index_item<'de, R>(
+    de: &mut Deserializer<R>,
+    start: u64,
+    head: Head,
+    depth: usize,
+) -> Result<IndexEntry>
+where
+    R: Read<'de>,
+{
+    let (major, info, argument) = de.skip_tags(head, false)?;
+    let items = match major {
+        4 | 5 if depth > 0 => de.recursion_checked(|de| {
+            if major == 4 {
+                index_array(de, info == 31, argument, depth - 1).map(IndexItems::Array)
+            } else {
+                index_map(de, info == 31, argument, depth - 1).map(IndexItems::Map)
+            }
+        })?,
+        _ => {
+            if !de.skip_rest((major, info, argument), false)? {
+                return Err(de.error(ErrorCode::UnexpectedCode));
+            }
+            IndexItems::None
+        }
+    };
+    Ok(IndexEntry::new(start, de.offset(), items))
+}
+
+/// Compares a key to a selector in the order of `ObjectKey`, where integers come first, then
+/// byte strings and then text strings.
+fn compare_key(key: &ObjectKey, selector: Selector) -> Ordering {
+    match (key, selector) {
+        (ObjectKey::Integer(key), Selector::Int(selector)) => key.cmp(&selector),
+        (ObjectKey::String(key), Selector::Key(selector)) => key.as_str().cmp(selector),
+        (ObjectKey::Integer(_), _) | (ObjectKey::Bytes(_), Selector::Key(_)) => Ordering::Less,
+        _ => Ordering::Greater,
+    }
+}
+
+/// Reads the head of the next item of a container and returns its offset, or `None` at the
+/// end of the container.
+fn next_head<'de, R>(
+    de: &mut Deserializer<R>,
+    indefinite: bool,
+    remaining: &mut u64,
+) -> Result<Option<(u64, Head)>>
+where
+    R: Read<'de>,
+{
+    if !indefinite {
+        if *remaining == 0 {
+            return Ok(None);
+        }
+        *remaining -= 1;
+    }
+    let start = de.offset();
+    let head = de.skip_head(false)?;
+    match head {
+        (7, 31, _) if indefinite => Ok(None),
+        (7, 31, _) => Err(de.error(ErrorCode::UnexpectedCode)),
+        _ => Ok(Some((start, head))),
+    }
+}
+
+fn index_array<'de, R>(
+    de: &mut Deserializer<R>,
+    indefinite: bool,
+    mut len: u64,
+    depth: usize,
+) -> Result<Vec<IndexEntry>>
+where
+    R: Read<'de>,
+{
+    let mut entries = Vec::new();
+    while let Some((start, head)) = next_head(de, indefinite, &mut len)? {
+        entries.push(index_item(de, start, head, depth)?);
+    }
+    Ok(entries)
+}
+
+fn index_map<'de, R>(
+    de: &mut Deserializer<R>,
+    indefinite: bool,
+    mut len: u64,
+    depth: usize,
+) -> Result<Vec<(Option<ObjectKey>, IndexEntry)>>
+where
+    R: Read<'de>,
+{
+    let mut entries = Vec::new();
+    while let Some((_, head)) = next_head(de, indefinite, &mut len)? {
+        let key = read_key(de, head)?;
+        let start = de.offset();
+        let head = de.skip_head(false)?;
+        if let (7, 31, _) = head {
+            return Err(de.error(ErrorCode::UnexpectedCode));
+        }
+        entries.push((key, index_item(de, start, head, depth)?));
+    }
+    Ok(entries)
+}
+
+/// Reads the rest of a map key whose head has been read.
+fn read_key<'de, R>(de: &mut Deserializer<R>, head: Head) -> Result<Option<ObjectKey>>
+where
+    R: Read<'de>,
+{
+    let (major, info, argument) = de.skip_tags(head, false)?;
+    let key = match (major, info) {
+        (0, _) if argument <= i64::max_value() as u64 => ObjectKey::Integer(argument as i64),
+        (1, _) if argument <= i64::max_value() as u64 => ObjectKey::Integer(-1 - argument as i64),
+        (2, _) | (3, _) if info != 31 => {
+            let len = de.skip_len(argument)?;
+            let end = de.offset() + len as u64;
+            let buf = de.read_slice(len)?;
+            if major == 2 {
+                ObjectKey::Bytes(buf.to_vec())
+            } else {
+                ObjectKey::String(convert_str(buf, end)?.to_owned())
+            }
+        }
+        (7, 20) => ObjectKey::Bool(false),
+        (7, 21) => ObjectKey::Bool(true),
+        (7, 22) => ObjectKey::Null,
+        _ => {
+            de.skip_rest((major, info, argument), false)?;
+            return Ok(None);
+        }
+    };
+    Ok(Some(key))
+}
+
+impl Serialize for Index {
+    fn serialize<S>(&self, serializer: S) -> result::Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        let mut tuple = serializer.serialize_tuple(2)?;
+        tuple.serialize_element(&(self.depth as u64))?;
+        tuple.serialize_element(&self.root)?;
+        tuple.end()
+    }
+}
+
+impl Serialize for IndexEntry {
+    fn serialize<S>(&self, serializer: S) -> result::Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        let mut tuple = serializer.serialize_tuple(4)?;
+        tuple.serialize_element(&self.start)?;
+        tuple.serialize_element(&self.end)?;
+        match self.items {
+            IndexItems::None => {
+                tuple.serialize_element(&0u8)?;
+                tuple.serialize_element(&())?;
+            }
+            IndexItems::Array(ref entries) => {
+                tuple.serialize_element(&1u8)?;
+                tuple.serialize_element(entries)?;
+            }
+            IndexItems::Map(ref entries) => {
+                tuple.serialize_element(&2u8)?;
+                tuple.serialize_element(&MapEntries(entries))?;
+            }
+        }
+        tuple.end()
+    }
+}
+
+struct MapEntries<'a>(&'a [(Option<ObjectKey>, IndexEntry)]);
+
+impl<'a> Serialize for MapEntries<'a> {
+    fn serialize<S>(&self, serializer: S) -> result::Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        serializer.collect_seq(self.0.iter().map(MapEntry))
+    }
+}
+
+/// Serializes a key and the entry of its value as `[entry, key]` or `[entry]`.
+struct MapEntry<'a>(&'a (Option<ObjectKey>, IndexEntry));
+
+impl<'a> Serialize for MapEntry<'a> {
+    fn serialize<S>(&self, serializer: S) -> result::Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        let (ref key, ref entry) = *self.0;
+        let mut tuple = serializer.serialize_tuple(if key.is_some() { 2 } else { 1 })?;
+        tuple.serialize_element(entry)?;
+        if let Some(ref key) = *key {
+            tuple.serialize_element(key)?;
+        }
+        tuple.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Index {
+    fn deserialize<D>(deserializer: D) -> result::Result<Index, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        struct IndexVisitor;
+
+        impl<'de> de::Visitor<'de> for IndexVisitor {
+            type Value = Index;
+
+            fn expecting(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+                fmt.write_str("a CBOR index")
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> result::Result<Index, A::Error>
+            where
+                A: de::SeqAccess<'de>,
+            {
+                let depth: u64 = next_element(&mut seq, 0, &self)?;
+                let root = next_element(&mut seq, 1, &self)?;
+                Ok(Index {
+                    depth: depth as usize,
+                    root,
+                })
+            }
+        }
+
+        deserializer.deserialize_tuple(2, IndexVisitor)
+    }
+}
+
+impl<'de> Deserialize<'de> for IndexEntry {
+    fn deserialize<D>(deserializer: D) -> result::Result<IndexEntry, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        struct IndexEntryVisitor;
+
+        impl<'de> de::Visitor<'de> for IndexEntryVisitor {
+            type Value = IndexEntry;
+
+            fn expecting(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+                fmt.write_str("an entry of a CBOR index")
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> result::Result<IndexEntry, A::Error>
+            where
+                A: de::SeqAccess<'de>,
+            {
+                let start = next_element(&mut seq, 0, &self)?;
+                let end = next_element(&mut seq, 1, &self)?;
+                if end < start {
+                    return Err(de::Error::invalid_value(
+                        de::Unexpected::Unsigned(end),
+                        &"an end not before the start",
+                    ));
+                }
+                let items = match next_element(&mut seq, 2, &self)? {
+                    0u8 => {
+                        next_element::<(), _>(&mut seq, 3, &self)?;
+                        IndexItems::None
+                    }
+                    1 => IndexItems::Array(next_element(&mut seq, 3, &self)?),
+                    2 => {
+                        let pairs: Vec<MapEntryBuf> = next_element(&mut seq, 3, &self)?;
+                        IndexItems::Map(pairs.into_iter().map(|pair| pair.0).collect())
+                    }
+                    kind => {
+                        return Err(de::Error::invalid_value(
+                            de::Unexpected::Unsigned(u64::from(kind)),
+                            &"0, 1 or 2",
+                        ))
+                    }
+                };
+                Ok(IndexEntry::new(start, end, items))
+            }
+        }
+
+        deserializer.deserialize_tuple(4, IndexEntryVisitor)
+    }
+}
+
+/// Deserializes a key and the entry of its value from `[entry, key]` or `[entry]`.
+struct MapEntryBuf((Option<ObjectKey>, IndexEntry));
+
+impl<'de> Deserialize<'de> for MapEntryBuf {
+    fn deserialize<D>(deserializer: D) -> result::Result<MapEntryBuf, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        struct MapEntryVisitor;
+
+        impl<'de> de::Visitor<'de> for MapEntryVisitor {
+            type Value = MapEntryBuf;
+
+            fn expecting(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+                fmt.write_str("a map entry of a CBOR index")
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> result::Result<MapEntryBuf, A::Error>
+            where
+                A: de::SeqAccess<'de>,
+            {
+                let entry = next_element(&mut seq, 0, &self)?;
+                let key = seq.next_element()?;
+                Ok(MapEntryBuf((key, entry)))
+            }
+        }
+
+        deserializer.deserialize_seq(MapEntryVisitor)
+    }
+}
+
+fn next_element<'de, T, A>(
+    seq: &mut A,
+    index: usize,
+    expected: &dyn de::Expected,
+) -> result::Result<T, A::Error>
+where
+    T: Deserialize<'de>,
+    A: de::SeqAccess<'de>,
+{
+    match seq.next_element()? {
+        Some(value) => Ok(value),
+        None => Err(de::Error::invalid_length(index, expected)),
+    }
+}
@@ -144,24 +144,91 @@
 //! assert!(serialized_byte_string.len() < serialized_array.len());
 //! # }
 //! ```
+//!
+//! # `no_std` support
+//!
+//! The `std` feature is enabled by default. Without it the crate is `no_std`:
+//! input is read from slices and output is written to a
+//! [`SliceWrite`](ser/struct.SliceWrite.html) or any other implementation of
+//! [`ser::Write`](ser/trait.Write.html). Reading from and writing to `std::io`
+//! streams, the `cddl` module and the `std::error::Error` implementation
+//! require `std`.
+//!
+//! The `alloc` feature adds `Value`, the `syntax` module, writing to a
+//! `Vec<u8>` and deterministic encoding. Without it nothing is ever allocated:
+//! error messages from `Deserialize` implementations are dropped, errors have
+//! no path, and indefinite length strings can only be decoded with
+//! [`from_slice_with_scratch`](de/fn.from_slice_with_scratch.html).
+//!
+//! # Async support
+//!
+//! The `async` feature adds the [`async_io`](async_io/index.html) module, which decodes items
+//! from tokio's `AsyncRead` and encodes them to its `AsyncWrite`. The `codec` feature adds a
+//! [`Codec`](codec/struct.Codec.html) for tokio-util's `FramedRead` and `FramedWrite`.
+//!
+//! # Minimum supported Rust version
+//!
+//! This crate requires Rust 1.60 or newer. The `async` and `codec` features require Rust 1.85,
+//! the oldest version tokio-util builds with, and the `regex` feature requires Rust 1.65.
 
 #![deny(missing_docs)]
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate core;
 
 extern crate byteorder;
 extern crate half;
+#[cfg(feature = "regex")]
+extern crate regex;
+#[cfg(feature = "async")]
+extern crate futures_core;
+#[cfg(feature = "async")]
+extern crate tokio;
+#[cfg(feature = "codec")]
+extern crate bytes;
+#[cfg(feature = "codec")]
+extern crate tokio_util;
 
 #[macro_use]
 extern crate serde;
 
+#[cfg(feature = "async")]
+pub mod async_io;
+mod decoder;
+mod encoder;
 mod read;
+#[cfg(any(feature = "async", feature = "codec"))]
+mod scan;
+#[cfg(feature = "std")]
+pub mod cddl;
+#[cfg(feature = "codec")]
+pub mod codec;
 pub mod de;
 pub mod error;
+#[cfg(feature = "alloc")]
+pub mod index;
 pub mod ser;
+#[cfg(feature = "alloc")]
+pub mod syntax;
 pub mod value;
+mod write;
 
 #[doc(inline)]
-pub use de::{from_slice, from_reader, Deserializer, StreamDeserializer};
+pub use de::{from_slice, Deserializer, StreamDeserializer};
+#[cfg(feature = "std")]
+#[doc(inline)]
+pub use de::{from_buf_reader, from_reader};
+#[doc(inline)]
+pub use ser::Serializer;
+#[cfg(feature = "std")]
+#[doc(inline)]
+pub use ser::to_writer;
+#[cfg(feature = "alloc")]
 #[doc(inline)]
-pub use ser::{to_writer, to_vec, Serializer};
+pub use ser::to_vec;
+#[cfg(feature = "alloc")]
 #[doc(inline)]
 pub use value::{Value, ObjectKey, to_value, from_value};
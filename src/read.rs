@@ -1,6 +1,11 @@
-use std::cmp;
+#[cfg(feature = "std")]
+use core::cmp;
+#[cfg(feature = "std")]
 use std::io::{self, Read as StdRead};
 
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+use alloc::vec::Vec;
+
 use error::{Result, Error, ErrorCode};
 
 /// Trait used by the deserializer for iterating over input.
@@ -8,21 +13,50 @@ use error::{Result, Error, ErrorCode};
 /// This trait is sealed and cannot be implemented for types outside of `serde_cbor`.
 pub trait Read<'de>: private::Sealed {
     #[doc(hidden)]
-    fn next(&mut self) -> io::Result<Option<u8>>;
+    fn next(&mut self) -> Result<Option<u8>>;
+    #[doc(hidden)]
+    fn peek(&mut self) -> Result<Option<u8>>;
+
+    /// Empties the scratch buffer that indefinite length strings are assembled in.
+    #[doc(hidden)]
+    fn clear_buffer(&mut self);
+
+    /// Appends the next `n` bytes of input to the scratch buffer.
+    #[doc(hidden)]
+    fn read_to_buffer(&mut self, n: usize) -> Result<()>;
+
+    /// Returns the contents of the scratch buffer.
     #[doc(hidden)]
-    fn peek(&mut self) -> io::Result<Option<u8>>;
+    fn take_buffer(&mut self) -> &[u8];
 
+    /// Reads the next `n` bytes, borrowing them from the input if possible.
     #[doc(hidden)]
-    fn read(
-        &mut self,
-        n: usize,
-        scratch: &mut Vec<u8>,
-        scratch_offset: usize,
-    ) -> Result<Reference<'de>>;
+    fn read<'a>(&'a mut self, n: usize) -> Result<Reference<'de, 'a>> {
+        self.clear_buffer();
+        self.read_to_buffer(n)?;
+        Ok(Reference::Copied(self.take_buffer()))
+    }
 
     #[doc(hidden)]
     fn read_into(&mut self, buf: &mut [u8]) -> Result<()>;
 
+    /// Skips the next `n` bytes.
+    #[doc(hidden)]
+    fn skip(&mut self, n: usize) -> Result<()>;
+
+    /// Returns the input from offset `start` up to the current position, if it is borrowed from
+    /// a slice.
+    #[doc(hidden)]
+    fn slice_from(&self, _start: u64) -> Option<&'de [u8]> {
+        None
+    }
+
+    /// Returns the input between the offsets `start` and `end`, if it is borrowed from a slice.
+    #[doc(hidden)]
+    fn slice(&self, _start: u64, _end: u64) -> Option<&'de [u8]> {
+        None
+    }
+
     #[doc(hidden)]
     fn discard(&mut self);
 
@@ -30,9 +64,10 @@ pub trait Read<'de>: private::Sealed {
     fn offset(&self) -> u64;
 }
 
-pub enum Reference<'b> {
+/// Bytes borrowed either from the input, for `'b`, or from a scratch buffer, for `'c`.
+pub enum Reference<'b, 'c> {
     Borrowed(&'b [u8]),
-    Copied,
+    Copied(&'c [u8]),
 }
 
 mod private {
@@ -40,14 +75,20 @@ mod private {
 }
 
 /// CBOR input source that reads from a std::io input stream.
+///
+/// Input is read exactly up to the end of a value, which takes a call to `read` for every head.
+/// If the stream can be buffered, `BufIoRead` is much faster.
+#[cfg(feature = "std")]
 pub struct IoRead<R>
 where
     R: io::Read,
 {
     reader: OffsetReader<R>,
     ch: Option<u8>,
+    scratch: Vec<u8>,
 }
 
+#[cfg(feature = "std")]
 impl<R> IoRead<R>
 where
     R: io::Read,
@@ -60,6 +101,7 @@ where
                 offset: 0,
             },
             ch: None,
+            scratch: Vec::new(),
         }
     }
 
@@ -77,69 +119,121 @@ where
     }
 }
 
+#[cfg(feature = "std")]
 impl<R> private::Sealed for IoRead<R>
 where
     R: io::Read,
 {
 }
 
+#[cfg(feature = "std")]
 impl<'de, R> Read<'de> for IoRead<R>
 where
     R: io::Read,
 {
     #[inline]
-    fn next(&mut self) -> io::Result<Option<u8>> {
+    fn next(&mut self) -> Result<Option<u8>> {
         match self.ch.take() {
             Some(ch) => Ok(Some(ch)),
-            None => self.next_inner(),
+            None => {
+                let offset = self.reader.offset;
+                self.next_inner().map_err(|e| Error::io(e).at_offset(offset))
+            }
         }
     }
 
     #[inline]
-    fn peek(&mut self) -> io::Result<Option<u8>> {
+    fn peek(&mut self) -> Result<Option<u8>> {
         match self.ch {
             Some(ch) => Ok(Some(ch)),
             None => {
-                self.ch = self.next_inner()?;
+                let offset = self.reader.offset;
+                self.ch = self
+                    .next_inner()
+                    .map_err(|e| Error::io(e).at_offset(offset))?;
                 Ok(self.ch)
             }
         }
     }
 
-    fn read(
-        &mut self,
-        mut n: usize,
-        scratch: &mut Vec<u8>,
-        mut scratch_offset: usize,
-    ) -> Result<Reference<'de>> {
+    #[inline]
+    fn clear_buffer(&mut self) {
+        self.scratch.clear();
+    }
+
+    fn read_to_buffer(&mut self, mut n: usize) -> Result<()> {
         while n > 0 {
             // defend against malicious input pretending to be huge strings by limiting growth
             let to_read = cmp::min(n, 16 * 1024);
             n -= to_read;
 
-            if to_read > scratch.len() - scratch_offset {
-                scratch.resize(scratch_offset + to_read, 0);
-            }
+            let mut offset = self.scratch.len();
+            self.scratch.resize(offset + to_read, 0);
 
             if let Some(ch) = self.ch.take() {
-                scratch[scratch_offset] = ch;
-                scratch_offset += 1;
+                self.scratch[offset] = ch;
+                offset += 1;
             }
 
-            self.read_into(&mut scratch[scratch_offset..])?;
-            scratch_offset = scratch.len();
+            self.reader.fill(&mut self.scratch[offset..])?;
         }
 
-        Ok(Reference::Copied)
+        Ok(())
     }
 
-    fn read_into(&mut self, mut buf: &mut [u8]) -> Result<()> {
+    #[inline]
+    fn take_buffer(&mut self) -> &[u8] {
+        &self.scratch
+    }
+
+    fn read_into(&mut self, buf: &mut [u8]) -> Result<()> {
+        self.reader.fill(buf)
+    }
+
+    fn skip(&mut self, mut n: usize) -> Result<()> {
+        if n > 0 && self.ch.take().is_some() {
+            n -= 1;
+        }
+        let skipped = io::copy(&mut (&mut self.reader).take(n as u64), &mut io::sink())
+            .map_err(|e| Error::io(e).at_offset(self.reader.offset))?;
+        if skipped < n as u64 {
+            return Err(Error::syntax(
+                ErrorCode::EofWhileParsingValue,
+                self.reader.offset,
+            ));
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn discard(&mut self) {
+        self.ch = None;
+    }
+
+    fn offset(&self) -> u64 {
+        // A peeked byte has been read but not consumed.
+        self.reader.offset - self.ch.is_some() as u64
+    }
+}
+
+#[cfg(feature = "std")]
+struct OffsetReader<R> {
+    reader: R,
+    offset: u64,
+}
+
+#[cfg(feature = "std")]
+impl<R> OffsetReader<R>
+where
+    R: io::Read,
+{
+    fn fill(&mut self, mut buf: &mut [u8]) -> Result<()> {
         while !buf.is_empty() {
-            match self.reader.read(buf) {
+            match self.read(buf) {
                 Ok(0) => {
                     return Err(Error::syntax(
                         ErrorCode::EofWhileParsingValue,
-                        self.offset(),
+                        self.offset,
                     ))
                 }
                 Ok(count) => {
@@ -148,28 +242,15 @@ where
                     }[count..]
                 }
                 Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
-                Err(e) => return Err(Error::io(e)),
+                Err(e) => return Err(Error::io(e).at_offset(self.offset)),
             }
         }
 
         Ok(())
     }
-
-    #[inline]
-    fn discard(&mut self) {
-        self.ch = None;
-    }
-
-    fn offset(&self) -> u64 {
-        self.reader.offset
-    }
-}
-
-struct OffsetReader<R> {
-    reader: R,
-    offset: u64,
 }
 
+#[cfg(feature = "std")]
 impl<R> io::Read for OffsetReader<R>
 where
     R: io::Read,
@@ -184,10 +265,226 @@ where
     }
 }
 
+/// CBOR input source that reads from a buffered std::io input stream.
+///
+/// Strings that lie within the buffer of the reader are visited straight from the buffer instead
+/// of the scratch buffer, and the reader is asked for more input only when its buffer runs
+/// empty. These strings are still only lent for the call to the visitor, since the buffer is
+/// refilled while decoding: `&str` and `&[u8]` fields cannot be borrowed from a reader and need
+/// `String` and `Vec<u8>`, or a `Cow`. Input past the end of the values read stays in the buffer
+/// of the reader, so a borrowed reader can be used for other data afterwards.
+#[cfg(feature = "std")]
+pub struct BufIoRead<R>
+where
+    R: io::BufRead,
+{
+    reader: R,
+    // Bytes that have been passed on but not yet consumed from the reader.
+    pending: usize,
+    offset: u64,
+    scratch: Vec<u8>,
+}
+
+#[cfg(feature = "std")]
+impl<R> BufIoRead<R>
+where
+    R: io::BufRead,
+{
+    /// Creates a new CBOR input source to read from a buffered std::io input stream.
+    pub fn new(reader: R) -> BufIoRead<R> {
+        BufIoRead {
+            reader,
+            pending: 0,
+            offset: 0,
+            scratch: Vec::new(),
+        }
+    }
+
+    /// Creates a CBOR input source to read from a buffered std::io input stream that has
+    /// already been read up to `offset`, for example one that was seeked there.
+    ///
+    /// Offsets in errors are counted from the start of the stream.
+    pub fn with_offset(reader: R, offset: u64) -> BufIoRead<R> {
+        let mut read = BufIoRead::new(reader);
+        read.offset = offset;
+        read
+    }
+
+    #[inline]
+    fn consume_pending(&mut self) {
+        if self.pending > 0 {
+            self.reader.consume(self.pending);
+            self.pending = 0;
+        }
+    }
+
+    #[inline]
+    fn consume(&mut self, n: usize) {
+        self.reader.consume(n);
+        self.offset += n as u64;
+    }
+
+    fn eof(&self) -> Error {
+        Error::syntax(ErrorCode::EofWhileParsingValue, self.offset)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R> Drop for BufIoRead<R>
+where
+    R: io::BufRead,
+{
+    fn drop(&mut self) {
+        self.consume_pending();
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R> private::Sealed for BufIoRead<R>
+where
+    R: io::BufRead,
+{
+}
+
+#[cfg(feature = "std")]
+impl<'de, R> Read<'de> for BufIoRead<R>
+where
+    R: io::BufRead,
+{
+    #[inline]
+    fn next(&mut self) -> Result<Option<u8>> {
+        let ch = self.peek()?;
+        if ch.is_some() {
+            self.consume(1);
+        }
+        Ok(ch)
+    }
+
+    #[inline]
+    fn peek(&mut self) -> Result<Option<u8>> {
+        self.consume_pending();
+        with_buf(&mut self.reader, self.offset, |buf| buf.first().cloned())
+    }
+
+    #[inline]
+    fn clear_buffer(&mut self) {
+        self.scratch.clear();
+    }
+
+    fn read_to_buffer(&mut self, mut n: usize) -> Result<()> {
+        while n > 0 {
+            self.consume_pending();
+            let scratch = &mut self.scratch;
+            let len = with_buf(&mut self.reader, self.offset, |buf| {
+                let len = cmp::min(n, buf.len());
+                scratch.extend_from_slice(&buf[..len]);
+                len
+            })?;
+            if len == 0 {
+                return Err(self.eof());
+            }
+            self.consume(len);
+            n -= len;
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn take_buffer(&mut self) -> &[u8] {
+        &self.scratch
+    }
+
+    fn read<'a>(&'a mut self, n: usize) -> Result<Reference<'de, 'a>> {
+        if n == 0 {
+            return Ok(Reference::Copied(&[]));
+        }
+        self.consume_pending();
+        if with_buf(&mut self.reader, self.offset, |buf| buf.len())? < n {
+            self.clear_buffer();
+            self.read_to_buffer(n)?;
+            return Ok(Reference::Copied(&self.scratch));
+        }
+        // The bytes are consumed before the next read, once they are no longer borrowed. Filling
+        // a buffer that is not empty again returns it unchanged.
+        let offset = self.offset;
+        let buf = self
+            .reader
+            .fill_buf()
+            .map_err(|e| Error::io(e).at_offset(offset))?;
+        self.pending = n;
+        self.offset += n as u64;
+        Ok(Reference::Copied(&buf[..n]))
+    }
+
+    fn read_into(&mut self, mut buf: &mut [u8]) -> Result<()> {
+        while !buf.is_empty() {
+            self.consume_pending();
+            let len = with_buf(&mut self.reader, self.offset, |input| {
+                let len = cmp::min(buf.len(), input.len());
+                buf[..len].copy_from_slice(&input[..len]);
+                len
+            })?;
+            if len == 0 {
+                return Err(self.eof());
+            }
+            self.consume(len);
+            buf = &mut { buf }[len..];
+        }
+        Ok(())
+    }
+
+    fn skip(&mut self, mut n: usize) -> Result<()> {
+        while n > 0 {
+            self.consume_pending();
+            let len = with_buf(&mut self.reader, self.offset, |buf| cmp::min(n, buf.len()))?;
+            if len == 0 {
+                return Err(self.eof());
+            }
+            self.consume(len);
+            n -= len;
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn discard(&mut self) {
+        self.consume_pending();
+        self.consume(1);
+    }
+
+    fn offset(&self) -> u64 {
+        self.offset
+    }
+}
+
+/// Passes the buffered input, which is empty at the end of the stream, to `f`.
+///
+/// Errors are placed at `offset`, the position of the reader in the stream.
+#[cfg(feature = "std")]
+#[inline]
+fn with_buf<R, F, T>(reader: &mut R, offset: u64, mut f: F) -> Result<T>
+where
+    R: io::BufRead,
+    F: FnMut(&[u8]) -> T,
+{
+    loop {
+        match reader.fill_buf() {
+            Ok(buf) => return Ok(f(buf)),
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(Error::io(e).at_offset(offset)),
+        }
+    }
+}
+
 /// A CBOR input source that reads from a slice of bytes.
 pub struct SliceRead<'a> {
     slice: &'a [u8],
     index: usize,
+    #[cfg(feature = "alloc")]
+    scratch: Vec<u8>,
+    // Without a scratch buffer only the length of indefinite strings is tracked, to report it.
+    #[cfg(not(feature = "alloc"))]
+    scratch_len: usize,
 }
 
 impl<'a> SliceRead<'a> {
@@ -196,6 +493,10 @@ impl<'a> SliceRead<'a> {
         SliceRead {
             slice,
             index: 0,
+            #[cfg(feature = "alloc")]
+            scratch: Vec::new(),
+            #[cfg(not(feature = "alloc"))]
+            scratch_len: 0,
         }
     }
 
@@ -216,7 +517,7 @@ impl<'a> private::Sealed for SliceRead<'a> {}
 
 impl<'a> Read<'a> for SliceRead<'a> {
     #[inline]
-    fn next(&mut self) -> io::Result<Option<u8>> {
+    fn next(&mut self) -> Result<Option<u8>> {
         Ok(if self.index < self.slice.len() {
             let ch = self.slice[self.index];
             self.index += 1;
@@ -227,7 +528,7 @@ impl<'a> Read<'a> for SliceRead<'a> {
     }
 
     #[inline]
-    fn peek(&mut self) -> io::Result<Option<u8>> {
+    fn peek(&mut self) -> Result<Option<u8>> {
         Ok(if self.index < self.slice.len() {
             Some(self.slice[self.index])
         } else {
@@ -235,8 +536,49 @@ impl<'a> Read<'a> for SliceRead<'a> {
         })
     }
 
+    #[cfg(feature = "alloc")]
+    #[inline]
+    fn clear_buffer(&mut self) {
+        self.scratch.clear();
+    }
+
+    #[cfg(not(feature = "alloc"))]
+    #[inline]
+    fn clear_buffer(&mut self) {
+        self.scratch_len = 0;
+    }
+
+    #[cfg(feature = "alloc")]
+    fn read_to_buffer(&mut self, n: usize) -> Result<()> {
+        let end = self.end(n)?;
+        self.scratch.extend_from_slice(&self.slice[self.index..end]);
+        self.index = end;
+        Ok(())
+    }
+
+    #[cfg(not(feature = "alloc"))]
+    fn read_to_buffer(&mut self, n: usize) -> Result<()> {
+        self.scratch_len = self.scratch_len.saturating_add(n);
+        Err(Error::syntax(
+            ErrorCode::BufferTooSmall(self.scratch_len),
+            self.offset(),
+        ))
+    }
+
+    #[cfg(feature = "alloc")]
+    #[inline]
+    fn take_buffer(&mut self) -> &[u8] {
+        &self.scratch
+    }
+
+    #[cfg(not(feature = "alloc"))]
+    #[inline]
+    fn take_buffer(&mut self) -> &[u8] {
+        &[]
+    }
+
     #[inline]
-    fn read(&mut self, n: usize, _: &mut Vec<u8>, _: usize) -> Result<Reference<'a>> {
+    fn read<'b>(&'b mut self, n: usize) -> Result<Reference<'a, 'b>> {
         let end = self.end(n)?;
         let slice = &self.slice[self.index..end];
         self.index = end;
@@ -251,6 +593,21 @@ impl<'a> Read<'a> for SliceRead<'a> {
         Ok(())
     }
 
+    #[inline]
+    fn skip(&mut self, n: usize) -> Result<()> {
+        self.index = self.end(n)?;
+        Ok(())
+    }
+
+    #[inline]
+    fn slice_from(&self, start: u64) -> Option<&'a [u8]> {
+        Some(&self.slice[start as usize..self.index])
+    }
+
+    fn slice(&self, start: u64, end: u64) -> Option<&'a [u8]> {
+        self.slice.get(start as usize..end as usize)
+    }
+
     #[inline]
     fn discard(&mut self) {
         self.index += 1;
@@ -260,3 +617,93 @@ impl<'a> Read<'a> for SliceRead<'a> {
         self.index as u64
     }
 }
+
+/// A CBOR input source that reads from a slice of bytes and assembles indefinite length strings
+/// in a caller-supplied scratch slice.
+///
+/// Nothing is ever allocated. If an indefinite length string does not fit into the scratch slice
+/// decoding fails with a "buffer too small" error.
+pub struct SliceReadFixed<'a, 'b> {
+    read: SliceRead<'a>,
+    scratch: &'b mut [u8],
+    scratch_index: usize,
+}
+
+impl<'a, 'b> SliceReadFixed<'a, 'b> {
+    /// Creates a CBOR input source to read from a slice of bytes, using `scratch` for
+    /// indefinite length strings.
+    pub fn new(slice: &'a [u8], scratch: &'b mut [u8]) -> SliceReadFixed<'a, 'b> {
+        SliceReadFixed {
+            read: SliceRead::new(slice),
+            scratch,
+            scratch_index: 0,
+        }
+    }
+}
+
+impl<'a, 'b> private::Sealed for SliceReadFixed<'a, 'b> {}
+
+impl<'a, 'b> Read<'a> for SliceReadFixed<'a, 'b> {
+    #[inline]
+    fn next(&mut self) -> Result<Option<u8>> {
+        self.read.next()
+    }
+
+    #[inline]
+    fn peek(&mut self) -> Result<Option<u8>> {
+        self.read.peek()
+    }
+
+    #[inline]
+    fn clear_buffer(&mut self) {
+        self.scratch_index = 0;
+    }
+
+    fn read_to_buffer(&mut self, n: usize) -> Result<()> {
+        let end = self.scratch_index.saturating_add(n);
+        if end > self.scratch.len() {
+            return Err(Error::syntax(ErrorCode::BufferTooSmall(end), self.offset()));
+        }
+        self.read.read_into(&mut self.scratch[self.scratch_index..end])?;
+        self.scratch_index = end;
+        Ok(())
+    }
+
+    #[inline]
+    fn take_buffer(&mut self) -> &[u8] {
+        &self.scratch[..self.scratch_index]
+    }
+
+    #[inline]
+    fn read<'c>(&'c mut self, n: usize) -> Result<Reference<'a, 'c>> {
+        self.read.read(n)
+    }
+
+    #[inline]
+    fn read_into(&mut self, buf: &mut [u8]) -> Result<()> {
+        self.read.read_into(buf)
+    }
+
+    #[inline]
+    fn skip(&mut self, n: usize) -> Result<()> {
+        self.read.skip(n)
+    }
+
+    #[inline]
+    fn slice_from(&self, start: u64) -> Option<&'a [u8]> {
+        self.read.slice_from(start)
+    }
+
+    fn slice(&self, start: u64, end: u64) -> Option<&'a [u8]> {
+        self.read.slice(start, end)
+    }
+
+    #[inline]
+    fn discard(&mut self) {
+        self.read.discard();
+    }
+
+    fn offset(&self) -> u64 {
+        self.read.offset()
+    }
+}
@@ -0,0 +1,235 @@
+//! Finds the end of a CBOR item in incomplete input without decoding it.
+
+use byteorder::{BigEndian, ByteOrder};
+
+use error::{Error, ErrorCode, Result};
+
+// The same nesting limit the deserializer enforces.
+const MAX_DEPTH: usize = 128;
+
+/// The outcome of scanning a buffer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Scan {
+    /// The first item in the buffer ends after this many bytes.
+    Complete(usize),
+    /// The buffer holds no complete item. At least this many more bytes are needed.
+    Incomplete(usize),
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Level {
+    /// A container with this many items left.
+    Definite(u64),
+    /// A container of indefinite length.
+    Indefinite,
+    /// A string of indefinite length with chunks of the given major type.
+    Chunks(u8),
+}
+
+/// A resumable scanner for the end of the first CBOR item in a growing buffer.
+///
+/// Scanning never reads beyond the end of the item, and the number of missing bytes it
+/// reports never exceeds the rest of the item, so a reader can be read exactly up to the end
+/// of an item.
+#[derive(Debug, Default)]
+pub(crate) struct Scanner {
+    // Bytes of completely scanned heads and string contents.
+    pos: usize,
+    stack: Vec<Level>,
+    // Whether the last head was a tag, which has to be followed by an item.
+    tagged: bool,
+}
+
+impl Scanner {
+    pub fn new() -> Scanner {
+        Scanner::default()
+    }
+
+    /// Continues scanning the buffer, which has to start with the bytes scanned before.
+    ///
+    /// After an item is complete or an error is found the scanner starts over for the next one.
+    pub fn scan(&mut self, buf: &[u8]) -> Result<Scan> {
+        let result = self.scan_item(buf);
+        if result.is_err() {
+            self.reset();
+        }
+        result
+    }
+
+    /// Forgets the item scanned so far.
+    pub fn reset(&mut self) {
+        self.pos = 0;
+        self.stack.clear();
+        self.tagged = false;
+    }
+
+    fn scan_item(&mut self, buf: &[u8]) -> Result<Scan> {
+        loop {
+            let (head_len, major, info, argument) = match self.head(buf)? {
+                Some(head) => head,
+                None => {
+                    let missing = match buf.get(self.pos) {
+                        Some(&byte) => 1 + argument_len(byte & 0x1f) - (buf.len() - self.pos),
+                        None => 1,
+                    };
+                    return Ok(Scan::Incomplete(missing));
+                }
+            };
+
+            if let Some(&Level::Chunks(chunks)) = self.stack.last() {
+                if buf[self.pos] == 0xff {
+                    self.pos += 1;
+                    self.stack.pop();
+                    if let Some(scan) = self.complete_item() {
+                        return Ok(scan);
+                    }
+                    continue;
+                }
+                if major != chunks || info == 31 {
+                    return Err(self.error(ErrorCode::UnexpectedCode));
+                }
+            }
+
+            let mut advance = head_len;
+            let complete = match (major, info) {
+                (7, 31) => {
+                    match self.stack.last() {
+                        Some(&Level::Indefinite) if !self.tagged => {}
+                        _ => return Err(self.error(ErrorCode::UnexpectedCode)),
+                    }
+                    self.stack.pop();
+                    true
+                }
+                (_, 31) if (2..=5).contains(&major) => {
+                    let level = if major <= 3 {
+                        Level::Chunks(major)
+                    } else {
+                        Level::Indefinite
+                    };
+                    self.push(level)?;
+                    false
+                }
+                (2, _) | (3, _) => {
+                    let end = (self.pos as u64)
+                        .saturating_add(head_len as u64)
+                        .saturating_add(argument);
+                    if end > buf.len() as u64 {
+                        let missing = end - buf.len() as u64;
+                        if missing > usize::max_value() as u64 {
+                            return Err(self.error(ErrorCode::LengthOutOfRange));
+                        }
+                        return Ok(Scan::Incomplete(missing as usize));
+                    }
+                    // Chunks of indefinite strings are not items.
+                    advance = end as usize - self.pos;
+                    !self.in_chunks()
+                }
+                (4, _) | (5, _) => {
+                    let len = if major == 4 {
+                        Some(argument)
+                    } else {
+                        argument.checked_mul(2)
+                    };
+                    match len {
+                        Some(0) => true,
+                        Some(len) => {
+                            self.push(Level::Definite(len))?;
+                            false
+                        }
+                        None => return Err(self.error(ErrorCode::LengthOutOfRange)),
+                    }
+                }
+                // A tag is followed by the item it applies to.
+                (6, _) => false,
+                _ => true,
+            };
+            self.tagged = major == 6;
+            self.pos += advance;
+
+            if complete {
+                if let Some(scan) = self.complete_item() {
+                    return Ok(scan);
+                }
+            }
+        }
+    }
+
+    /// Parses the head at the current position, returning its length, major type, additional
+    /// information and argument, or `None` if it is incomplete.
+    fn head(&self, buf: &[u8]) -> Result<Option<(usize, u8, u8, u64)>> {
+        let byte = match buf.get(self.pos) {
+            Some(&byte) => byte,
+            None => return Ok(None),
+        };
+        let major = byte >> 5;
+        let info = byte & 0x1f;
+        let len = 1 + argument_len(info);
+        let rest = &buf[self.pos + 1..];
+        if rest.len() < len - 1 {
+            return Ok(None);
+        }
+        let argument = match info {
+            0...23 => u64::from(info),
+            24 => u64::from(rest[0]),
+            25 => u64::from(BigEndian::read_u16(rest)),
+            26 => u64::from(BigEndian::read_u32(rest)),
+            27 => BigEndian::read_u64(rest),
+            31 if major != 0 && major != 1 && major != 6 => 0,
+            _ => return Err(self.error(ErrorCode::UnassignedCode)),
+        };
+        // Simple values below 32 have to be written in a single byte.
+        if major == 7 && info == 24 && argument < 32 {
+            return Err(self.error(ErrorCode::UnassignedCode));
+        }
+        Ok(Some((len, major, info, argument)))
+    }
+
+    fn push(&mut self, level: Level) -> Result<()> {
+        if self.stack.len() >= MAX_DEPTH {
+            return Err(self.error(ErrorCode::RecursionLimitExceeded));
+        }
+        self.stack.push(level);
+        Ok(())
+    }
+
+    fn in_chunks(&self) -> bool {
+        matches!(self.stack.last(), Some(&Level::Chunks(_)))
+    }
+
+    /// Counts a complete item towards its enclosing containers, returning the scan result if
+    /// it completes the top-level item.
+    fn complete_item(&mut self) -> Option<Scan> {
+        loop {
+            match self.stack.last_mut() {
+                None => {
+                    let len = self.pos;
+                    self.reset();
+                    return Some(Scan::Complete(len));
+                }
+                Some(&mut Level::Definite(ref mut remaining)) => {
+                    *remaining -= 1;
+                    if *remaining > 0 {
+                        return None;
+                    }
+                }
+                Some(_) => return None,
+            }
+            self.stack.pop();
+        }
+    }
+
+    fn error(&self, code: ErrorCode) -> Error {
+        Error::syntax(code, self.pos as u64)
+    }
+}
+
+/// The number of bytes following the initial byte of a head.
+fn argument_len(info: u8) -> usize {
+    match info {
+        24 => 1,
+        25 => 2,
+        26 => 4,
+        27 => 8,
+        _ => 0,
+    }
+}
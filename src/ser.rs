@@ -2,117 +2,384 @@
 use byteorder::{ByteOrder, BigEndian};
 use half::f16;
 use serde::ser::{self, Serialize};
+#[cfg(feature = "std")]
+use core::{cmp, str};
+#[cfg(feature = "std")]
 use std::io;
 
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+use alloc::vec::Vec;
+
+#[cfg(feature = "std")]
+use error::ErrorCode;
 use error::{Error, Result};
+use value::{F16_TOKEN, RAW_VALUE_TOKEN};
+pub use encoder::Encoder;
+pub use write::{CountWrite, SliceWrite, Write};
+#[cfg(feature = "std")]
+pub use write::IoWrite;
 
 /// Serializes a value to a writer.
-pub fn to_writer<W, T>(mut writer: &mut W, value: &T) -> Result<()>
+#[cfg(feature = "std")]
+pub fn to_writer<W, T>(writer: &mut W, value: &T) -> Result<()>
 where
     W: io::Write,
     T: ser::Serialize,
 {
-    value.serialize(&mut Serializer::new(&mut writer))
+    SerializerBuilder::new().to_writer(IoWrite::new(writer), value)
 }
 
 /// Serializes a value to a writer and adds a CBOR self-describe tag.
-pub fn to_writer_sd<W, T>(mut writer: &mut W, value: &T) -> Result<()>
+#[cfg(feature = "std")]
+pub fn to_writer_sd<W, T>(writer: &mut W, value: &T) -> Result<()>
 where
     W: io::Write,
     T: ser::Serialize,
 {
-    let mut ser = Serializer::new(&mut writer);
-    ser.self_describe()?;
-    value.serialize(&mut ser)
+    SerializerBuilder::new()
+        .self_describe(true)
+        .to_writer(IoWrite::new(writer), value)
 }
 
 /// Serializes a value without names to a writer.
 ///
 /// Struct fields and enum variants are identified by their numeric indices rather than names to
 /// save space.
-pub fn to_writer_packed<W, T>(mut writer: &mut W, value: &T) -> Result<()>
+#[cfg(feature = "std")]
+pub fn to_writer_packed<W, T>(writer: &mut W, value: &T) -> Result<()>
 where
     W: io::Write,
     T: ser::Serialize,
 {
-    value.serialize(&mut Serializer::packed(&mut writer))
+    SerializerBuilder::new()
+        .packed(true)
+        .to_writer(IoWrite::new(writer), value)
 }
 
 /// Serializes a value without names to a writer and adds a CBOR self-describe tag.
 ///
 /// Struct fields and enum variants are identified by their numeric indices rather than names to
 /// save space.
-pub fn to_writer_packed_sd<W, T>(mut writer: &mut W, value: &T) -> Result<()>
+#[cfg(feature = "std")]
+pub fn to_writer_packed_sd<W, T>(writer: &mut W, value: &T) -> Result<()>
 where
     W: io::Write,
     T: ser::Serialize,
 {
-    let mut ser = Serializer::packed(&mut writer);
-    ser.self_describe()?;
-    value.serialize(&mut ser)
+    SerializerBuilder::new()
+        .packed(true)
+        .self_describe(true)
+        .to_writer(IoWrite::new(writer), value)
+}
+
+/// Serializes a value to a writer using the deterministic encoding of RFC 8949.
+///
+/// See `Serializer::deterministic` for the rules this applies.
+#[cfg(feature = "std")]
+pub fn to_writer_deterministic<W, T>(writer: &mut W, value: &T) -> Result<()>
+where
+    W: io::Write,
+    T: ser::Serialize,
+{
+    SerializerBuilder::new()
+        .deterministic(true)
+        .to_writer(IoWrite::new(writer), value)
+}
+
+/// Serializes a value into a slice and returns the number of bytes written.
+///
+/// If the slice is too small the error reports the number of bytes needed, see
+/// `Error::required_size`. Nothing is allocated.
+///
+/// ```rust
+/// let mut buf = [0; 8];
+/// let len = serde_cbor::ser::to_slice(&mut buf, &[1, 2, 3]).unwrap();
+/// assert_eq!(&buf[..len], b"\x83\x01\x02\x03");
+///
+/// let error = serde_cbor::ser::to_slice(&mut buf[..2], &[1, 2, 3]).unwrap_err();
+/// assert_eq!(error.required_size(), Some(4));
+/// ```
+pub fn to_slice<T>(slice: &mut [u8], value: &T) -> Result<usize>
+where
+    T: ser::Serialize,
+{
+    SerializerBuilder::new().to_slice(slice, value)
 }
 
 /// Serializes a value to a vector.
+#[cfg(feature = "alloc")]
 pub fn to_vec<T>(value: &T) -> Result<Vec<u8>>
 where
     T: ser::Serialize,
 {
-    let mut vec = Vec::new();
-    to_writer(&mut vec, value)?;
-    Ok(vec)
+    SerializerBuilder::new().to_vec(value)
 }
 
 /// Serializes a value to a vector and adds a CBOR self-describe tag.
+#[cfg(feature = "alloc")]
 pub fn to_vec_sd<T>(value: &T) -> Result<Vec<u8>>
 where
     T: ser::Serialize,
 {
-    let mut vec = Vec::new();
-    to_writer_sd(&mut vec, value)?;
-    Ok(vec)
+    SerializerBuilder::new().self_describe(true).to_vec(value)
+}
+
+/// Serializes a value to a vector using the deterministic encoding of RFC 8949.
+///
+/// See `Serializer::deterministic` for the rules this applies.
+#[cfg(feature = "alloc")]
+pub fn to_vec_deterministic<T>(value: &T) -> Result<Vec<u8>>
+where
+    T: ser::Serialize,
+{
+    SerializerBuilder::new().deterministic(true).to_vec(value)
 }
 
 /// Serializes a value without names to a vector.
 ///
 /// Struct fields and enum variants are identified by their numeric indices rather than names to
 /// save space.
+#[cfg(feature = "alloc")]
 pub fn to_vec_packed<T>(value: &T) -> Result<Vec<u8>>
 where
     T: ser::Serialize,
 {
-    let mut vec = Vec::new();
-    to_writer_packed(&mut vec, value)?;
-    Ok(vec)
+    SerializerBuilder::new().packed(true).to_vec(value)
 }
+
 /// Serializes a value without names to a vector and adds a CBOR self-describe tag.
 ///
 /// Struct fields and enum variants are identified by their numeric indices rather than names to
 /// save space.
+#[cfg(feature = "alloc")]
 pub fn to_vec_packed_sd<T>(value: &T) -> Result<Vec<u8>>
 where
     T: ser::Serialize,
 {
-    let mut vec = Vec::new();
-    to_writer_packed_sd(&mut vec, value)?;
-    Ok(vec)
+    SerializerBuilder::new()
+        .packed(true)
+        .self_describe(true)
+        .to_vec(value)
+}
+
+/// How a `Serializer` chooses the width of floats.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FloatPolicy {
+    /// Uses the smallest width that represents the value exactly and writes every NaN as the
+    /// half precision quiet NaN `0x7e00`. This is the default policy.
+    Shortest,
+    /// Writes `f32` values with single and `f64` values with double precision, NaN payloads
+    /// included. A `Value` is written with the width it was decoded from.
+    Declared,
+    /// Like `Shortest`, but never uses half precision. NaN is written as `0x7fc00000`.
+    NoHalf,
+    /// Like `Shortest`, but keeps the payload and sign of NaNs, shrinking them only if that
+    /// does not lose any bits.
+    PreserveNan,
+}
+
+/// How enum variants with content are encoded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum EnumRepresentation {
+    /// An array of the variant and its content, like `["Variant", 1]`. This is the default.
+    Array,
+    /// A map with a single entry from the variant to its content, like `{"Variant": 1}`.
+    ///
+    /// Tuple variants have an array of their fields as content. This is the form JSON-shaped
+    /// data uses.
+    Map,
+}
+
+/// Options for serializing Rust values to CBOR.
+///
+/// ```rust
+/// use serde_cbor::ser::SerializerBuilder;
+///
+/// let bytes = SerializerBuilder::new()
+///     .packed(true)
+///     .deterministic(true)
+///     .to_vec(&vec![1, 2, 3])
+///     .unwrap();
+/// assert_eq!(bytes, b"\x83\x01\x02\x03");
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct SerializerBuilder {
+    packed: bool,
+    self_describe: bool,
+    deterministic: bool,
+    float_policy: FloatPolicy,
+    reduce_integral_floats: bool,
+    enum_representation: EnumRepresentation,
+    struct_as_array: bool,
+    human_readable: bool,
+}
+
+impl Default for SerializerBuilder {
+    fn default() -> SerializerBuilder {
+        SerializerBuilder::new()
+    }
+}
+
+impl SerializerBuilder {
+    /// Creates options with the defaults of `Serializer::new`.
+    pub fn new() -> SerializerBuilder {
+        SerializerBuilder {
+            packed: false,
+            self_describe: false,
+            deterministic: false,
+            float_policy: FloatPolicy::Shortest,
+            reduce_integral_floats: false,
+            enum_representation: EnumRepresentation::Array,
+            struct_as_array: false,
+            human_readable: false,
+        }
+    }
+
+    /// Identifies struct fields and enum variants by their numeric indices rather than names to
+    /// save space.
+    pub fn packed(mut self, packed: bool) -> SerializerBuilder {
+        self.packed = packed;
+        self
+    }
+
+    /// Starts the output with a CBOR self-describe tag.
+    pub fn self_describe(mut self, self_describe: bool) -> SerializerBuilder {
+        self.self_describe = self_describe;
+        self
+    }
+
+    /// Uses the core deterministic encoding of RFC 8949, see `Serializer::deterministic`.
+    #[cfg(feature = "alloc")]
+    pub fn deterministic(mut self, deterministic: bool) -> SerializerBuilder {
+        self.deterministic = deterministic;
+        self
+    }
+
+    /// Sets how the width of floats is chosen.
+    pub fn float_policy(mut self, policy: FloatPolicy) -> SerializerBuilder {
+        self.float_policy = policy;
+        self
+    }
+
+    /// Writes floats without a fractional part as integers, see
+    /// `Serializer::reduce_integral_floats`.
+    pub fn reduce_integral_floats(mut self, reduce: bool) -> SerializerBuilder {
+        self.reduce_integral_floats = reduce;
+        self
+    }
+
+    /// Sets how enum variants with content are written.
+    pub fn enum_representation(mut self, representation: EnumRepresentation) -> SerializerBuilder {
+        self.enum_representation = representation;
+        self
+    }
+
+    /// Writes structs as arrays of their fields in declaration order, like tuple structs.
+    ///
+    /// This saves the space of the field keys, and the deserializer accepts structs in this
+    /// form. Fields are written as they come, without buffering the struct. Since the following
+    /// fields would move to the wrong positions, serializing a struct that skips a field, for
+    /// example with `#[serde(skip_serializing_if = "...")]`, is an error. It combines with
+    /// `packed` for the variants of enums.
+    pub fn struct_as_array(mut self, struct_as_array: bool) -> SerializerBuilder {
+        self.struct_as_array = struct_as_array;
+        self
+    }
+
+    /// Sets what the serializer reports from `is_human_readable`.
+    ///
+    /// Some types, like `IpAddr`, serialize themselves more compactly for formats that are not
+    /// human readable. CBOR is not by default.
+    pub fn human_readable(mut self, human_readable: bool) -> SerializerBuilder {
+        self.human_readable = human_readable;
+        self
+    }
+
+    /// Creates a serializer with these options, writing the self-describe tag if requested.
+    pub fn build<W>(&self, writer: W) -> Result<Serializer<W>>
+    where
+        W: Write,
+    {
+        let mut ser = Serializer {
+            writer,
+            options: *self,
+            raw: false,
+        };
+        if self.self_describe {
+            ser.self_describe()?;
+        }
+        Ok(ser)
+    }
+
+    /// Serializes a value to a writer.
+    pub fn to_writer<W, T>(&self, writer: W, value: &T) -> Result<()>
+    where
+        W: Write,
+        T: ser::Serialize,
+    {
+        value.serialize(&mut self.build(writer)?)
+    }
+
+    /// Serializes a value into a slice and returns the number of bytes written.
+    ///
+    /// If the slice is too small the error reports the number of bytes needed.
+    pub fn to_slice<T>(&self, slice: &mut [u8], value: &T) -> Result<usize>
+    where
+        T: ser::Serialize,
+    {
+        let mut writer = SliceWrite::new(slice);
+        self.to_writer(&mut writer, value)?;
+        writer.finish()
+    }
+
+    /// Serializes a value to a vector.
+    #[cfg(feature = "alloc")]
+    pub fn to_vec<T>(&self, value: &T) -> Result<Vec<u8>>
+    where
+        T: ser::Serialize,
+    {
+        let mut vec = Vec::new();
+        self.to_writer(&mut vec, value)?;
+        Ok(vec)
+    }
 }
 
 /// A structure for serializing Rust values to CBOR.
 pub struct Serializer<W> {
     writer: W,
-    packed: bool,
+    options: SerializerBuilder,
+    // Set while a `RawValue` is serialized, whose bytes are written as they are.
+    raw: bool,
 }
 
-impl<W> Serializer<W>
+#[cfg(feature = "std")]
+impl<W> Serializer<IoWrite<W>>
 where
     W: io::Write,
+{
+    /// Creates a new CBOR serializer that writes to a `std::io::Write`.
+    ///
+    /// This is a shorthand for `Serializer::new(IoWrite::new(writer))`.
+    #[inline]
+    pub fn from_io_writer(writer: W) -> Serializer<IoWrite<W>> {
+        Serializer::new(IoWrite::new(writer))
+    }
+}
+
+impl<W> Serializer<W>
+where
+    W: Write,
 {
     /// Creates a new CBOR serializer.
+    ///
+    /// The writer is a `ser::Write`. A `std::io::Write`, which could be passed here directly
+    /// before `ser::Write` became public, is written to with `from_io_writer`.
     #[inline]
     pub fn new(writer: W) -> Serializer<W> {
         Serializer {
             writer,
-            packed: false,
+            options: SerializerBuilder::new(),
+            raw: false,
         }
     }
 
@@ -124,10 +391,61 @@ where
     pub fn packed(writer: W) -> Serializer<W> {
         Serializer {
             writer,
-            packed: true,
+            options: SerializerBuilder::new().packed(true),
+            raw: false,
         }
     }
 
+    /// Encodes floats with the width they are declared with.
+    ///
+    /// By default floats are shrunk to the smallest width that represents them exactly. With this
+    /// option `f32` values are always written with single and `f64` values with double
+    /// precision, and a `Value` is written with the width it was decoded from, so that decoding
+    /// and encoding a `Value` reproduces the input.
+    ///
+    /// This is a shorthand for `float_policy(FloatPolicy::Declared)`.
+    #[inline]
+    pub fn preserve_float_width(self, preserve: bool) -> Serializer<W> {
+        self.float_policy(if preserve {
+            FloatPolicy::Declared
+        } else {
+            FloatPolicy::Shortest
+        })
+    }
+
+    /// Sets how the width of floats is chosen.
+    #[inline]
+    pub fn float_policy(mut self, policy: FloatPolicy) -> Serializer<W> {
+        self.options.float_policy = policy;
+        self
+    }
+
+    /// Writes floats without a fractional part as integers, like `1.0` as `1`.
+    ///
+    /// This is the numeric reduction of dCBOR. It applies to floats between -2^64 and 2^64 - 1,
+    /// negative zero is written as `0`. Other floats are written following the float policy.
+    #[inline]
+    pub fn reduce_integral_floats(mut self, reduce: bool) -> Serializer<W> {
+        self.options.reduce_integral_floats = reduce;
+        self
+    }
+
+    /// Uses the core deterministic encoding of RFC 8949 section 4.2.
+    ///
+    /// The entries of maps and structs are sorted by the bytewise order of their encoded keys
+    /// and a map with two equal keys is an error. Sequences and maps of unknown length are
+    /// buffered so their length can be written up front, and floats are always shrunk to the
+    /// smallest width that represents them exactly, ignoring the float policy. Heads are
+    /// always as short as possible. This makes the output depend only on the data, not on the
+    /// order a `HashMap` or a `Value` iterates over its entries, which is necessary to hash or
+    /// sign encoded values.
+    #[cfg(feature = "alloc")]
+    #[inline]
+    pub fn deterministic(mut self, deterministic: bool) -> Serializer<W> {
+        self.options.deterministic = deterministic;
+        self
+    }
+
     /// Writes a CBOR self-describe tag to the stream.
     ///
     /// Tagging allows a decoder to distinguish different file formats based on their content
@@ -136,7 +454,106 @@ where
     pub fn self_describe(&mut self) -> Result<()> {
         let mut buf = [6 << 5 | 25, 0, 0];
         BigEndian::write_u16(&mut buf[1..], 55799);
-        self.writer.write_all(&buf).map_err(Error::io)
+        self.write_all(&buf)
+    }
+
+    /// Writes the contents of a reader as an indefinite length byte string.
+    ///
+    /// The reader is copied in chunks of at most `chunk_len` bytes, so a blob of any size is
+    /// written without holding it in memory. The deterministic encoding does not allow
+    /// indefinite length strings, so this is an error in deterministic mode.
+    ///
+    /// ```rust
+    /// use serde_cbor::ser::Serializer;
+    ///
+    /// let mut ser = Serializer::new(Vec::new());
+    /// ser.serialize_bytes_chunked(&b"\x01\x02\x03"[..], 2).unwrap();
+    /// assert_eq!(ser.into_inner(), b"\x5f\x42\x01\x02\x41\x03\xff");
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chunk_len` is zero.
+    #[cfg(feature = "std")]
+    pub fn serialize_bytes_chunked<R>(&mut self, reader: R, chunk_len: usize) -> Result<()>
+    where
+        R: io::Read,
+    {
+        assert!(chunk_len > 0, "chunk length must not be zero");
+        self.write_chunked(2, reader, chunk_len)
+    }
+
+    /// Writes the contents of a reader as an indefinite length text string.
+    ///
+    /// Like `serialize_bytes_chunked`, but the contents must be UTF-8. Chunks are split between
+    /// characters, so every chunk is valid UTF-8 on its own as CBOR requires. A chunk always
+    /// holds at least one character, even if it is longer than `chunk_len`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chunk_len` is zero.
+    #[cfg(feature = "std")]
+    pub fn serialize_str_chunked<R>(&mut self, reader: R, chunk_len: usize) -> Result<()>
+    where
+        R: io::Read,
+    {
+        assert!(chunk_len > 0, "chunk length must not be zero");
+        // A UTF-8 character takes 4 bytes at most.
+        self.write_chunked(3, reader, cmp::max(chunk_len, 4))
+    }
+
+    #[cfg(feature = "std")]
+    fn write_chunked<R>(&mut self, major: u8, mut reader: R, chunk_len: usize) -> Result<()>
+    where
+        R: io::Read,
+    {
+        if self.options.deterministic {
+            return Err(ser::Error::custom(
+                "indefinite length strings are not allowed in the deterministic encoding",
+            ));
+        }
+        self.write_all(&[major << 5 | 31])?;
+
+        let mut buf = vec![0; chunk_len];
+        // Bytes at the start of `buf`, an incomplete character carried over to the next chunk.
+        let mut carry = 0;
+        let mut offset = 0u64;
+        loop {
+            let mut len = carry;
+            while len < buf.len() {
+                match reader.read(&mut buf[len..]) {
+                    Ok(0) => break,
+                    Ok(n) => len += n,
+                    Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+                    Err(e) => return Err(Error::io(e)),
+                }
+            }
+            let eof = len < buf.len();
+
+            let mut end = len;
+            if major == 3 {
+                if let Err(e) = str::from_utf8(&buf[..len]) {
+                    if eof || e.error_len().is_some() {
+                        let offset = offset + e.valid_up_to() as u64;
+                        return Err(Error::syntax(ErrorCode::InvalidUtf8, offset));
+                    }
+                    end = e.valid_up_to();
+                }
+            }
+
+            if end > 0 {
+                self.write_u64(major, end as u64)?;
+                self.write_all(&buf[..end])?;
+            }
+            offset += end as u64;
+            if eof {
+                break;
+            }
+            carry = len - end;
+            buf.copy_within(end..len, 0);
+        }
+
+        self.write_all(&[0xff])
     }
 
     /// Unwrap the `Writer` from the `Serializer`.
@@ -145,14 +562,19 @@ where
         self.writer
     }
 
+    #[inline]
+    pub(crate) fn write_all(&mut self, buf: &[u8]) -> Result<()> {
+        self.writer.write_all(buf).map_err(Into::into)
+    }
+
     #[inline]
     fn write_u8(&mut self, major: u8, value: u8) -> Result<()> {
         if value <= 0x17 {
-            self.writer.write_all(&[major << 5 | value])
+            self.write_all(&[major << 5 | value])
         } else {
             let buf = [major << 5 | 24, value];
-            self.writer.write_all(&buf)
-        }.map_err(Error::io)
+            self.write_all(&buf)
+        }
     }
 
     #[inline]
@@ -162,7 +584,7 @@ where
         } else {
             let mut buf = [major << 5 | 25, 0, 0];
             BigEndian::write_u16(&mut buf[1..], value);
-            self.writer.write_all(&buf).map_err(Error::io)
+            self.write_all(&buf)
         }
     }
 
@@ -173,18 +595,118 @@ where
         } else {
             let mut buf = [major << 5 | 26, 0, 0, 0, 0];
             BigEndian::write_u32(&mut buf[1..], value);
-            self.writer.write_all(&buf).map_err(Error::io)
+            self.write_all(&buf)
         }
     }
 
     #[inline]
-    fn write_u64(&mut self, major: u8, value: u64) -> Result<()> {
+    pub(crate) fn write_u64(&mut self, major: u8, value: u64) -> Result<()> {
         if value <= u64::from(u32::max_value()) {
             self.write_u32(major, value as u32)
         } else {
             let mut buf = [major << 5 | 27, 0, 0, 0, 0, 0, 0, 0, 0];
             BigEndian::write_u64(&mut buf[1..], value);
-            self.writer.write_all(&buf).map_err(Error::io)
+            self.write_all(&buf)
+        }
+    }
+
+    /// Writes the head that precedes the variant of an enum with content.
+    #[inline]
+    fn write_variant_header(&mut self) -> Result<()> {
+        let head = match self.options.enum_representation {
+            EnumRepresentation::Array => 4 << 5 | 2,
+            EnumRepresentation::Map => 5 << 5 | 1,
+        };
+        self.write_all(&[head])
+    }
+
+    #[inline]
+    fn effective_float_policy(&self) -> FloatPolicy {
+        if self.options.deterministic {
+            FloatPolicy::Shortest
+        } else {
+            self.options.float_policy
+        }
+    }
+
+    /// Writes a float as an integer if it has no fractional part and fits.
+    fn write_integral(&mut self, value: f64) -> Result<bool> {
+        // 2^64, the first value that no longer fits.
+        const LIMIT: i128 = 1 << 64;
+        // NaN and infinities never convert back to the same value.
+        let integral = value as i128;
+        if !self.options.reduce_integral_floats
+            || integral as f64 != value
+            || !(-LIMIT..LIMIT).contains(&integral)
+        {
+            return Ok(false);
+        }
+        if integral >= 0 {
+            self.write_u64(0, integral as u64)?;
+        } else {
+            self.write_u64(1, (-1 - integral) as u64)?;
+        }
+        Ok(true)
+    }
+
+    #[inline]
+    pub(crate) fn write_half(&mut self, bits: u16) -> Result<()> {
+        let mut buf = [0xf9, 0, 0];
+        BigEndian::write_u16(&mut buf[1..], bits);
+        self.write_all(&buf)
+    }
+
+    #[inline]
+    pub(crate) fn write_single(&mut self, bits: u32) -> Result<()> {
+        let mut buf = [0xfa, 0, 0, 0, 0];
+        BigEndian::write_u32(&mut buf[1..], bits);
+        self.write_all(&buf)
+    }
+
+    #[inline]
+    pub(crate) fn write_double(&mut self, bits: u64) -> Result<()> {
+        let mut buf = [0xfb, 0, 0, 0, 0, 0, 0, 0, 0];
+        BigEndian::write_u64(&mut buf[1..], bits);
+        self.write_all(&buf)
+    }
+
+    /// Writes a number, which is not NaN, with the smallest width that represents it exactly.
+    fn write_shortest(&mut self, value: f64, policy: FloatPolicy) -> Result<()> {
+        let half = f16::from_f64(value);
+        if policy != FloatPolicy::NoHalf && half.to_f64() == value {
+            self.write_half(half.to_bits())
+        } else if f64::from(value as f32) == value {
+            self.write_single((value as f32).to_bits())
+        } else {
+            self.write_double(value.to_bits())
+        }
+    }
+
+    /// Writes a NaN following the policy, given its bits in single precision.
+    fn write_nan_single(&mut self, bits: u32, policy: FloatPolicy) -> Result<()> {
+        match policy {
+            FloatPolicy::Declared => self.write_single(bits),
+            FloatPolicy::PreserveNan if bits & 0x1fff == 0 => {
+                let sign = (bits >> 16) as u16 & 0x8000;
+                self.write_half(sign | 0x7c00 | (bits >> 13) as u16 & 0x3ff)
+            }
+            FloatPolicy::PreserveNan => self.write_single(bits),
+            FloatPolicy::NoHalf => self.write_single(0x7fc0_0000),
+            FloatPolicy::Shortest => self.write_half(0x7e00),
+        }
+    }
+
+    /// Writes a NaN following the policy, given its bits in double precision.
+    fn write_nan_double(&mut self, bits: u64, policy: FloatPolicy) -> Result<()> {
+        match policy {
+            FloatPolicy::Declared => self.write_double(bits),
+            FloatPolicy::PreserveNan if bits & 0x1fff_ffff == 0 => {
+                let sign = (bits >> 32) as u32 & 0x8000_0000;
+                let single = sign | 0x7f80_0000 | (bits >> 29) as u32 & 0x7f_ffff;
+                self.write_nan_single(single, policy)
+            }
+            FloatPolicy::PreserveNan => self.write_double(bits),
+            _ => self.write_nan_single(0, policy),
         }
     }
 
@@ -194,27 +716,29 @@ where
         major: u8,
         len: Option<usize>,
     ) -> Result<CollectionSerializer<'a, W>> {
-        let needs_eof = match len {
-            Some(len) => {
-                self.write_u64(major, len as u64)?;
-                false
-            }
-            None => {
-                self.writer.write_all(&[major << 5 | 31]).map_err(Error::io)?;
-                true
-            }
-        };
+        let mut buffer = None;
+        let mut needs_eof = false;
+        if self.options.deterministic && (major == 5 || len.is_none()) {
+            buffer = Entries::buffer(len.unwrap_or(0));
+        } else if let Some(len) = len {
+            self.write_u64(major, len as u64)?;
+        } else {
+            self.write_all(&[major << 5 | 31])?;
+            needs_eof = true;
+        }
 
         Ok(CollectionSerializer {
             ser: self,
+            major,
             needs_eof,
+            buffer,
         })
     }
 }
 
 impl<'a, W> ser::Serializer for &'a mut Serializer<W>
 where
-    W: io::Write,
+    W: Write,
 {
     type Ok = ();
     type Error = Error;
@@ -230,7 +754,7 @@ where
     #[inline]
     fn serialize_bool(self, value: bool) -> Result<()> {
         let value = if value { 0xf5 } else { 0xf4 };
-        self.writer.write_all(&[value]).map_err(Error::io)
+        self.write_all(&[value])
     }
 
     #[inline]
@@ -291,33 +815,31 @@ where
 
     #[inline]
     fn serialize_f32(self, value: f32) -> Result<()> {
-        if value.is_infinite() {
-            if value.is_sign_positive() {
-                self.writer.write_all(&[0xf9, 0x7c, 0x00])
-            } else {
-                self.writer.write_all(&[0xf9, 0xfc, 0x00])
-            }
-        } else if value.is_nan() {
-            self.writer.write_all(&[0xf9, 0x7e, 0x00])
-        } else if f32::from(f16::from_f32(value)) == value {
-            let mut buf = [0xf9, 0, 0];
-            BigEndian::write_u16(&mut buf[1..], f16::from_f32(value).to_bits());
-            self.writer.write_all(&buf)
+        if self.write_integral(f64::from(value))? {
+            return Ok(());
+        }
+        let policy = self.effective_float_policy();
+        if value.is_nan() {
+            self.write_nan_single(value.to_bits(), policy)
+        } else if policy == FloatPolicy::Declared {
+            self.write_single(value.to_bits())
         } else {
-            let mut buf = [0xfa, 0, 0, 0, 0];
-            BigEndian::write_f32(&mut buf[1..], value);
-            self.writer.write_all(&buf)
-        }.map_err(Error::io)
+            self.write_shortest(f64::from(value), policy)
+        }
     }
 
     #[inline]
     fn serialize_f64(self, value: f64) -> Result<()> {
-        if !value.is_finite() || value as f32 as f64 == value {
-            self.serialize_f32(value as f32)
+        if self.write_integral(value)? {
+            return Ok(());
+        }
+        let policy = self.effective_float_policy();
+        if value.is_nan() {
+            self.write_nan_double(value.to_bits(), policy)
+        } else if policy == FloatPolicy::Declared {
+            self.write_double(value.to_bits())
         } else {
-            let mut buf = [0xfb, 0, 0, 0, 0, 0, 0, 0, 0];
-            BigEndian::write_f64(&mut buf[1..], value);
-            self.writer.write_all(&buf).map_err(Error::io)
+            self.write_shortest(value, policy)
         }
     }
 
@@ -331,13 +853,17 @@ where
     #[inline]
     fn serialize_str(self, value: &str) -> Result<()> {
         self.write_u64(3, value.len() as u64)?;
-        self.writer.write_all(value.as_bytes()).map_err(Error::io)
+        self.write_all(value.as_bytes())
     }
 
     #[inline]
     fn serialize_bytes(self, value: &[u8]) -> Result<()> {
+        if self.raw {
+            self.raw = false;
+            return self.write_all(value);
+        }
         self.write_u64(2, value.len() as u64)?;
-        self.writer.write_all(value).map_err(Error::io)
+        self.write_all(value)
     }
 
     #[inline]
@@ -355,7 +881,7 @@ where
 
     #[inline]
     fn serialize_none(self) -> Result<()> {
-        self.writer.write_all(&[0xf6]).map_err(Error::io)
+        self.write_all(&[0xf6])
     }
 
     #[inline]
@@ -370,7 +896,7 @@ where
         variant_index: u32,
         variant: &'static str,
     ) -> Result<()> {
-        if self.packed {
+        if self.options.packed {
             self.serialize_u32(variant_index)
         } else {
             self.serialize_str(variant)
@@ -378,11 +904,24 @@ where
     }
 
     #[inline]
-    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<()>
+    fn serialize_newtype_struct<T>(self, name: &'static str, value: &T) -> Result<()>
     where
         T: ?Sized + ser::Serialize,
     {
-        value.serialize(self)
+        if name == F16_TOKEN && self.options.float_policy == FloatPolicy::Declared {
+            // The float is exactly representable as a half, so shrinking it restores the width.
+            self.options.float_policy = FloatPolicy::PreserveNan;
+            let result = value.serialize(&mut *self);
+            self.options.float_policy = FloatPolicy::Declared;
+            result
+        } else if name == RAW_VALUE_TOKEN {
+            self.raw = true;
+            let result = value.serialize(&mut *self);
+            self.raw = false;
+            result
+        } else {
+            value.serialize(self)
+        }
     }
 
     #[inline]
@@ -396,7 +935,7 @@ where
     where
         T: ?Sized + ser::Serialize,
     {
-        self.writer.write_all(&[4 << 5 | 2]).map_err(Error::io)?;
+        self.write_variant_header()?;
         self.serialize_unit_variant(name, variant_index, variant)?;
         value.serialize(self)
     }
@@ -429,8 +968,17 @@ where
         variant: &'static str,
         len: usize,
     ) -> Result<&'a mut Serializer<W>> {
-        self.write_u64(4, (len + 1) as u64)?;
-        self.serialize_unit_variant(name, variant_index, variant)?;
+        match self.options.enum_representation {
+            EnumRepresentation::Array => {
+                self.write_u64(4, (len + 1) as u64)?;
+                self.serialize_unit_variant(name, variant_index, variant)?;
+            }
+            EnumRepresentation::Map => {
+                self.write_variant_header()?;
+                self.serialize_unit_variant(name, variant_index, variant)?;
+                self.write_u64(4, len as u64)?;
+            }
+        }
         Ok(self)
     }
 
@@ -441,8 +989,22 @@ where
 
     #[inline]
     fn serialize_struct(self, _name: &'static str, len: usize) -> Result<StructSerializer<'a, W>> {
-        self.write_u64(5, len as u64)?;
-        Ok(StructSerializer { ser: self, idx: 0 })
+        // Field indices are written in ascending order, which is also the order of their
+        // encodings, but field names have to be sorted.
+        let buffer = if self.options.struct_as_array {
+            self.write_u64(4, len as u64)?;
+            None
+        } else if self.options.deterministic && !self.options.packed {
+            Entries::buffer(len)
+        } else {
+            self.write_u64(5, len as u64)?;
+            None
+        };
+        Ok(StructSerializer {
+            ser: self,
+            idx: 0,
+            buffer,
+        })
     }
 
     #[inline]
@@ -453,20 +1015,20 @@ where
         variant: &'static str,
         len: usize,
     ) -> Result<StructSerializer<'a, W>> {
-        self.writer.write_all(&[4 << 5 | 2]).map_err(Error::io)?;
+        self.write_variant_header()?;
         self.serialize_unit_variant(name, variant_index, variant)?;
         self.serialize_struct(name, len)
     }
 
     #[inline]
     fn is_human_readable(&self) -> bool {
-        false
+        self.options.human_readable
     }
 }
 
 impl<'a, W> ser::SerializeTuple for &'a mut Serializer<W>
 where
-    W: io::Write,
+    W: Write,
 {
     type Ok = ();
     type Error = Error;
@@ -487,7 +1049,7 @@ where
 
 impl<'a, W> ser::SerializeTupleStruct for &'a mut Serializer<W>
 where
-    W: io::Write,
+    W: Write,
 {
     type Ok = ();
     type Error = Error;
@@ -508,7 +1070,7 @@ where
 
 impl<'a, W> ser::SerializeTupleVariant for &'a mut Serializer<W>
 where
-    W: io::Write,
+    W: Write,
 {
     type Ok = ();
     type Error = Error;
@@ -527,40 +1089,172 @@ where
     }
 }
 
+/// An encoded map entry. Array elements are stored as keys with an empty value.
+#[cfg(feature = "alloc")]
+struct Entry {
+    key: Vec<u8>,
+    value: Vec<u8>,
+}
+
+/// Entries buffered for the deterministic encoding.
+#[cfg(feature = "alloc")]
+struct Entries(Vec<Entry>);
+
+#[cfg(feature = "alloc")]
+impl Entries {
+    fn buffer(capacity: usize) -> Option<Entries> {
+        Some(Entries(Vec::with_capacity(capacity)))
+    }
+
+    /// Encodes a value with the options of `ser`.
+    fn encode<W, T>(ser: &Serializer<W>, value: &T) -> Result<Vec<u8>>
+    where
+        T: ?Sized + ser::Serialize,
+    {
+        let mut ser = Serializer {
+            writer: Vec::new(),
+            options: ser.options,
+            raw: false,
+        };
+        value.serialize(&mut ser)?;
+        Ok(ser.writer)
+    }
+
+    /// Adds an entry with the given key, or an array element.
+    fn push_key<W, T>(&mut self, ser: &Serializer<W>, key: &T) -> Result<()>
+    where
+        T: ?Sized + ser::Serialize,
+    {
+        let key = Entries::encode(ser, key)?;
+        self.0.push(Entry {
+            key,
+            value: Vec::new(),
+        });
+        Ok(())
+    }
+
+    /// Sets the value of the last entry.
+    fn push_value<W, T>(&mut self, ser: &Serializer<W>, value: &T) -> Result<()>
+    where
+        T: ?Sized + ser::Serialize,
+    {
+        let value = Entries::encode(ser, value)?;
+        if let Some(entry) = self.0.last_mut() {
+            entry.value = value;
+        }
+        Ok(())
+    }
+
+    /// Writes the entries, sorting them by their keys if they belong to a map.
+    fn write<W>(self, major: u8, ser: &mut Serializer<W>) -> Result<()>
+    where
+        W: Write,
+    {
+        let mut entries = self.0;
+        if major == 5 {
+            entries.sort_by(|a, b| a.key.cmp(&b.key));
+            if entries.windows(2).any(|pair| pair[0].key == pair[1].key) {
+                return Err(ser::Error::custom("duplicate map key"));
+            }
+        }
+        ser.write_u64(major, entries.len() as u64)?;
+        for entry in entries {
+            ser.write_all(&entry.key)?;
+            ser.write_all(&entry.value)?;
+        }
+        Ok(())
+    }
+}
+
+/// Without `alloc` the deterministic encoding is unavailable and nothing is ever buffered.
+#[cfg(not(feature = "alloc"))]
+enum Entries {}
+
+#[cfg(not(feature = "alloc"))]
+impl Entries {
+    fn buffer(_capacity: usize) -> Option<Entries> {
+        None
+    }
+
+    fn push_key<W, T>(&mut self, _ser: &Serializer<W>, _key: &T) -> Result<()>
+    where
+        T: ?Sized + ser::Serialize,
+    {
+        match *self {}
+    }
+
+    fn push_value<W, T>(&mut self, _ser: &Serializer<W>, _value: &T) -> Result<()>
+    where
+        T: ?Sized + ser::Serialize,
+    {
+        match *self {}
+    }
+
+    fn write<W>(self, _major: u8, _ser: &mut Serializer<W>) -> Result<()>
+    where
+        W: Write,
+    {
+        match self {}
+    }
+}
+
 #[doc(hidden)]
 pub struct StructSerializer<'a, W: 'a> {
     ser: &'a mut Serializer<W>,
     idx: u32,
+    buffer: Option<Entries>,
 }
 
 impl<'a, W> StructSerializer<'a, W>
 where
-    W: io::Write,
+    W: Write,
 {
     #[inline]
     fn serialize_field_inner<T>(&mut self, key: &'static str, value: &T) -> Result<()>
     where
         T: ?Sized + ser::Serialize,
     {
-        if self.ser.packed {
-            self.idx.serialize(&mut *self.ser)?;
-        } else {
-            key.serialize(&mut *self.ser)?;
+        if let Some(ref mut buffer) = self.buffer {
+            buffer.push_key(self.ser, key)?;
+            return buffer.push_value(self.ser, value);
+        }
+        // In arrays fields are identified by their position alone.
+        if !self.ser.options.struct_as_array {
+            if self.ser.options.packed {
+                self.idx.serialize(&mut *self.ser)?;
+            } else {
+                key.serialize(&mut *self.ser)?;
+            }
         }
         self.idx += 1;
         value.serialize(&mut *self.ser)
     }
 
     #[inline]
-    fn skip_field_inner(&mut self, _: &'static str) -> Result<()> {
+    fn skip_field_inner(&mut self, key: &'static str) -> Result<()> {
+        if self.ser.options.struct_as_array {
+            // The following fields would move to the wrong positions.
+            return Err(ser::Error::custom(format_args!(
+                "cannot skip field `{}` of a struct written as an array",
+                key
+            )));
+        }
         self.idx += 1;
         Ok(())
     }
+
+    #[inline]
+    fn end_inner(self) -> Result<()> {
+        match self.buffer {
+            Some(entries) => entries.write(5, self.ser),
+            None => Ok(()),
+        }
+    }
 }
 
 impl<'a, W> ser::SerializeStruct for StructSerializer<'a, W>
 where
-    W: io::Write,
+    W: Write,
 {
     type Ok = ();
     type Error = Error;
@@ -580,13 +1274,13 @@ where
 
     #[inline]
     fn end(self) -> Result<()> {
-        Ok(())
+        self.end_inner()
     }
 }
 
 impl<'a, W> ser::SerializeStructVariant for StructSerializer<'a, W>
 where
-    W: io::Write,
+    W: Write,
 {
     type Ok = ();
     type Error = Error;
@@ -606,24 +1300,45 @@ where
 
     #[inline]
     fn end(self) -> Result<()> {
-        Ok(())
+        self.end_inner()
     }
 }
 
 #[doc(hidden)]
 pub struct CollectionSerializer<'a, W: 'a> {
     ser: &'a mut Serializer<W>,
+    major: u8,
     needs_eof: bool,
+    buffer: Option<Entries>,
 }
 
 impl<'a, W> CollectionSerializer<'a, W>
 where
-    W: io::Write,
+    W: Write,
 {
+    #[inline]
+    fn serialize_inner<T>(&mut self, value: &T, is_key: bool) -> Result<()>
+    where
+        T: ?Sized + ser::Serialize,
+    {
+        match self.buffer {
+            Some(ref mut buffer) => {
+                if is_key || self.major != 5 {
+                    buffer.push_key(self.ser, value)
+                } else {
+                    buffer.push_value(self.ser, value)
+                }
+            }
+            None => value.serialize(&mut *self.ser),
+        }
+    }
+
     #[inline]
     fn end_inner(self) -> Result<()> {
-        if self.needs_eof {
-            self.ser.writer.write_all(&[0xff]).map_err(Error::io)
+        if let Some(entries) = self.buffer {
+            entries.write(self.major, self.ser)
+        } else if self.needs_eof {
+            self.ser.write_all(&[0xff])
         } else {
             Ok(())
         }
@@ -632,7 +1347,7 @@ where
 
 impl<'a, W> ser::SerializeSeq for CollectionSerializer<'a, W>
 where
-    W: io::Write,
+    W: Write,
 {
     type Ok = ();
     type Error = Error;
@@ -642,7 +1357,7 @@ where
     where
         T: ?Sized + ser::Serialize,
     {
-        value.serialize(&mut *self.ser)
+        self.serialize_inner(value, false)
     }
 
     #[inline]
@@ -653,7 +1368,7 @@ where
 
 impl<'a, W> ser::SerializeMap for CollectionSerializer<'a, W>
 where
-    W: io::Write,
+    W: Write,
 {
     type Ok = ();
     type Error = Error;
@@ -663,7 +1378,7 @@ where
     where
         T: ?Sized + ser::Serialize,
     {
-        key.serialize(&mut *self.ser)
+        self.serialize_inner(key, true)
     }
 
     #[inline]
@@ -671,7 +1386,7 @@ where
     where
         T: ?Sized + ser::Serialize,
     {
-        value.serialize(&mut *self.ser)
+        self.serialize_inner(value, false)
     }
 
     #[inline]
@@ -0,0 +1,796 @@
+//! Encoding-preserving CBOR syntax trees.
+//!
+//! CBOR allows the same data to be encoded in many ways: integers and lengths may use longer
+//! heads than necessary, strings may be split into chunks, floats may be wider than needed and
+//! maps may contain duplicate keys in any order. `Value` only keeps the data. An `Item` keeps
+//! every one of these choices, so writing it back reproduces the input byte for byte.
+//!
+//! ```rust
+//! use serde_cbor::syntax::{self, Item, Width};
+//!
+//! // The number 5 with a needlessly long two byte argument.
+//! let bytes = b"\x19\x00\x05";
+//! let item = syntax::from_slice(bytes).unwrap();
+//! match item {
+//!     Item::Unsigned(ref argument) => {
+//!         assert_eq!(argument.value, 5);
+//!         assert_eq!(argument.width, Width::U16);
+//!     }
+//!     _ => unreachable!(),
+//! }
+//! assert_eq!(syntax::to_vec(&item).unwrap(), bytes);
+//! ```
+
+use byteorder::{BigEndian, ByteOrder};
+use core::cmp;
+use half::f16;
+use serde::de::Error as DeError;
+#[cfg(feature = "std")]
+use std::io;
+
+use alloc::collections::BTreeMap;
+#[cfg(not(feature = "std"))]
+use alloc::boxed::Box;
+#[cfg(not(feature = "std"))]
+use alloc::string::String;
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+use error::{Error, ErrorCode, Result};
+#[cfg(feature = "std")]
+use read::IoRead;
+use read::{Read, Reference, SliceRead};
+use value::{FloatWidth, ObjectKey, Value};
+#[cfg(feature = "std")]
+use write::IoWrite;
+use write::Write;
+
+/// Parses a single data item from a slice.
+///
+/// It is an error if the slice contains anything after the data item.
+pub fn from_slice(slice: &[u8]) -> Result<Item> {
+    let mut parser = Parser::new(SliceRead::new(slice));
+    let item = parser.parse_item()?;
+    parser.end()?;
+    Ok(item)
+}
+
+/// Parses a single data item from a reader.
+///
+/// It is an error if the reader contains anything after the data item.
+#[cfg(feature = "std")]
+pub fn from_reader<R>(reader: R) -> Result<Item>
+where
+    R: io::Read,
+{
+    let mut parser = Parser::new(IoRead::new(reader));
+    let item = parser.parse_item()?;
+    parser.end()?;
+    Ok(item)
+}
+
+/// Parses a sequence of data items as defined by RFC 8742 from a slice.
+#[cfg(feature = "std")]
+pub(crate) fn seq_from_slice(slice: &[u8]) -> Result<Vec<Item>> {
+    let mut parser = Parser::new(SliceRead::new(slice));
+    let mut items = Vec::new();
+    while parser.read.peek()?.is_some() {
+        items.push(parser.parse_item()?);
+    }
+    Ok(items)
+}
+
+/// Writes a data item with exactly the encoding recorded in it.
+#[cfg(feature = "std")]
+pub fn to_writer<W>(writer: &mut W, item: &Item) -> Result<()>
+where
+    W: io::Write,
+{
+    item.encode(&mut IoWrite::new(writer))
+}
+
+/// Writes a data item with exactly the encoding recorded in it to a vector.
+pub fn to_vec(item: &Item) -> Result<Vec<u8>> {
+    let mut vec = Vec::new();
+    item.encode(&mut vec)?;
+    Ok(vec)
+}
+
+/// The number of bytes used for the argument of a data item's head.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Width {
+    /// The argument is stored in the low five bits of the initial byte.
+    Immediate,
+    /// The argument is stored in one byte following the initial byte.
+    U8,
+    /// The argument is stored in two bytes following the initial byte.
+    U16,
+    /// The argument is stored in four bytes following the initial byte.
+    U32,
+    /// The argument is stored in eight bytes following the initial byte.
+    U64,
+}
+
+impl Width {
+    /// The smallest width able to hold the given argument.
+    pub fn minimal(value: u64) -> Width {
+        if value < 24 {
+            Width::Immediate
+        } else if value <= u64::from(u8::max_value()) {
+            Width::U8
+        } else if value <= u64::from(u16::max_value()) {
+            Width::U16
+        } else if value <= u64::from(u32::max_value()) {
+            Width::U32
+        } else {
+            Width::U64
+        }
+    }
+
+    fn holds(self, value: u64) -> bool {
+        self >= Width::minimal(value)
+    }
+}
+
+/// The argument of a data item's head together with the width it is encoded with.
+///
+/// When an item is written and the value does not fit into the recorded width, the smallest
+/// width that fits is used instead.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Argument {
+    /// The value of the argument.
+    pub value: u64,
+    /// The number of bytes the argument is encoded with.
+    pub width: Width,
+}
+
+impl Argument {
+    /// Creates an argument with the smallest possible encoding.
+    pub fn new(value: u64) -> Argument {
+        Argument {
+            value,
+            width: Width::minimal(value),
+        }
+    }
+}
+
+/// The length of an array or map.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Length {
+    /// The number of elements is encoded in the head with the given width.
+    Definite(Width),
+    /// The elements are terminated by a break code.
+    Indefinite,
+}
+
+/// A piece of a byte or text string together with the width of its length.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Chunk<T> {
+    /// The number of bytes the length of the chunk is encoded with.
+    pub width: Width,
+    /// The content of the chunk.
+    pub data: T,
+}
+
+impl<T> Chunk<T>
+where
+    T: AsRef<[u8]>,
+{
+    /// Creates a chunk with the smallest possible encoding of its length.
+    pub fn new(data: T) -> Chunk<T> {
+        Chunk {
+            width: Width::minimal(data.as_ref().len() as u64),
+            data,
+        }
+    }
+}
+
+/// A byte or text string.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Str<T> {
+    /// A string encoded in one piece.
+    Definite(Chunk<T>),
+    /// A string encoded as a sequence of chunks terminated by a break code.
+    Indefinite(Vec<Chunk<T>>),
+}
+
+/// A floating point number with the exact bits it is encoded with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Float {
+    /// A half precision float.
+    Half(u16),
+    /// A single precision float.
+    Single(u32),
+    /// A double precision float.
+    Double(u64),
+}
+
+impl Float {
+    /// The precision of the float.
+    pub fn width(&self) -> FloatWidth {
+        match *self {
+            Float::Half(_) => FloatWidth::Half,
+            Float::Single(_) => FloatWidth::Single,
+            Float::Double(_) => FloatWidth::Double,
+        }
+    }
+
+    /// The value of the float.
+    pub fn to_f64(&self) -> f64 {
+        match *self {
+            Float::Half(bits) => f64::from(f32::from(f16::from_bits(bits))),
+            Float::Single(bits) => f64::from(f32::from_bits(bits)),
+            Float::Double(bits) => f64::from_bits(bits),
+        }
+    }
+}
+
+/// An array together with the encoding of its length.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Array {
+    /// How the length of the array is encoded.
+    pub length: Length,
+    /// The elements of the array.
+    pub items: Vec<Item>,
+}
+
+/// A map together with the encoding of its length.
+///
+/// Entries are kept in the order they were encoded in, including duplicate keys.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Map {
+    /// How the length of the map is encoded.
+    pub length: Length,
+    /// The keys and values of the map.
+    pub entries: Vec<(Item, Item)>,
+}
+
+impl Map {
+    /// Returns the value of the first entry whose key has the given value, independent of how
+    /// the key is encoded.
+    pub fn get(&self, key: &Value) -> Option<&Item> {
+        self.entries
+            .iter()
+            .find(|entry| entry.0.to_value().ok().as_ref() == Some(key))
+            .map(|entry| &entry.1)
+    }
+
+    /// Returns the value of the first entry whose key has the given value, independent of how
+    /// the key is encoded.
+    pub fn get_mut(&mut self, key: &Value) -> Option<&mut Item> {
+        self.entries
+            .iter_mut()
+            .find(|entry| entry.0.to_value().ok().as_ref() == Some(key))
+            .map(|entry| &mut entry.1)
+    }
+}
+
+/// A CBOR data item with all details of its encoding.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Item {
+    /// An unsigned integer (major type 0).
+    Unsigned(Argument),
+    /// A negative integer (major type 1). The value of the integer is -1 minus the argument.
+    Negative(Argument),
+    /// A byte string (major type 2).
+    Bytes(Str<Vec<u8>>),
+    /// A text string (major type 3).
+    Text(Str<String>),
+    /// An array (major type 4).
+    Array(Array),
+    /// A map (major type 5).
+    Map(Map),
+    /// A tag and the item it applies to (major type 6).
+    Tag(Argument, Box<Item>),
+    /// A simple value such as `false`, `true`, `null` or `undefined` (major type 7).
+    ///
+    /// Simple values are at most 255 and never between 24 and 31. They are written with at
+    /// most a one byte argument, since wider heads encode floats.
+    Simple(Argument),
+    /// A floating point number (major type 7).
+    Float(Float),
+}
+
+impl Item {
+    /// Creates an item with the shortest encoding of a value.
+    ///
+    /// Floats keep the width recorded in the value.
+    pub fn from_value(value: &Value) -> Item {
+        match *value {
+            Value::U64(v) => Item::Unsigned(Argument::new(v)),
+            Value::I64(v) if v < 0 => Item::Negative(Argument::new(-(v + 1) as u64)),
+            Value::I64(v) => Item::Unsigned(Argument::new(v as u64)),
+            Value::Bytes(ref v) => Item::Bytes(Str::Definite(Chunk::new(v.clone()))),
+            Value::String(ref v) => Item::Text(Str::Definite(Chunk::new(v.clone()))),
+            Value::Array(ref v) => {
+                Item::Array(Array {
+                    length: Length::Definite(Width::minimal(v.len() as u64)),
+                    items: v.iter().map(Item::from_value).collect(),
+                })
+            }
+            Value::Object(ref v) => {
+                Item::Map(Map {
+                    length: Length::Definite(Width::minimal(v.len() as u64)),
+                    entries: v.iter()
+                        .map(|(key, value)| {
+                            (
+                                Item::from_value(&Value::from(key.clone())),
+                                Item::from_value(value),
+                            )
+                        })
+                        .collect(),
+                })
+            }
+            Value::F64(v) => Item::Float(Float::Double(v.to_bits())),
+            Value::F32(v) => Item::Float(Float::Single(v.to_bits())),
+            Value::F16(v) => {
+                let half = f16::from_f32(v);
+                if v.is_nan() || f32::from(half) == v {
+                    Item::Float(Float::Half(half.to_bits()))
+                } else {
+                    Item::Float(Float::Single(v.to_bits()))
+                }
+            }
+            Value::Bool(false) => Item::Simple(Argument::new(20)),
+            Value::Bool(true) => Item::Simple(Argument::new(21)),
+            Value::Null => Item::Simple(Argument::new(22)),
+        }
+    }
+
+    /// Converts the item to a value, discarding the details of its encoding.
+    ///
+    /// Tags are dropped and for duplicate map keys the last entry wins, as they are when
+    /// deserializing a `Value`.
+    pub fn to_value(&self) -> Result<Value> {
+        Ok(match *self {
+            Item::Unsigned(ref argument) => Value::U64(argument.value),
+            Item::Negative(ref argument) => {
+                if argument.value > i64::max_value() as u64 {
+                    return Err(Error::syntax(ErrorCode::NumberOutOfRange, 0));
+                }
+                Value::I64(-1 - argument.value as i64)
+            }
+            Item::Bytes(ref string) => Value::Bytes(concat(string, Vec::new(), |buf, data| {
+                buf.extend_from_slice(data)
+            })),
+            Item::Text(ref string) => Value::String(concat(string, String::new(), |buf, data| {
+                buf.push_str(data)
+            })),
+            Item::Array(ref array) => {
+                Value::Array(array.items.iter().map(Item::to_value).collect::<Result<_>>()?)
+            }
+            Item::Map(ref map) => {
+                let mut object = BTreeMap::new();
+                for (key, value) in &map.entries {
+                    object.insert(to_key(key.to_value()?)?, value.to_value()?);
+                }
+                Value::Object(object)
+            }
+            Item::Tag(_, ref item) => item.to_value()?,
+            Item::Simple(ref argument) => {
+                match argument.value {
+                    20 => Value::Bool(false),
+                    21 => Value::Bool(true),
+                    22 | 23 => Value::Null,
+                    _ => return Err(Error::syntax(ErrorCode::UnassignedCode, 0)),
+                }
+            }
+            Item::Float(Float::Half(bits)) => Value::F16(f32::from(f16::from_bits(bits))),
+            Item::Float(Float::Single(bits)) => Value::F32(f32::from_bits(bits)),
+            Item::Float(Float::Double(bits)) => Value::F64(f64::from_bits(bits)),
+        })
+    }
+
+    fn encode<W>(&self, writer: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        match *self {
+            Item::Unsigned(argument) => write_head(writer, 0, argument),
+            Item::Negative(argument) => write_head(writer, 1, argument),
+            Item::Bytes(ref string) => encode_str(writer, 2, string),
+            Item::Text(ref string) => encode_str(writer, 3, string),
+            Item::Array(ref array) => {
+                write_length(writer, 4, array.length, array.items.len())?;
+                for item in &array.items {
+                    item.encode(writer)?;
+                }
+                write_break(writer, array.length)
+            }
+            Item::Map(ref map) => {
+                write_length(writer, 5, map.length, map.entries.len())?;
+                for (key, value) in &map.entries {
+                    key.encode(writer)?;
+                    value.encode(writer)?;
+                }
+                write_break(writer, map.length)
+            }
+            Item::Tag(argument, ref item) => {
+                write_head(writer, 6, argument)?;
+                item.encode(writer)
+            }
+            Item::Simple(argument) => {
+                // Wider heads of major type 7 are floats and 24 to 31 are reserved.
+                if argument.value > 255 || (24..32).contains(&argument.value) {
+                    return Err(Error::syntax(ErrorCode::UnassignedCode, 0));
+                }
+                let width = cmp::min(argument.width, Width::U8);
+                write_head(writer, 7, Argument { width, ..argument })
+            }
+            Item::Float(Float::Half(bits)) => {
+                let mut buf = [0xf9, 0, 0];
+                BigEndian::write_u16(&mut buf[1..], bits);
+                write_all(writer, &buf)
+            }
+            Item::Float(Float::Single(bits)) => {
+                let mut buf = [0xfa, 0, 0, 0, 0];
+                BigEndian::write_u32(&mut buf[1..], bits);
+                write_all(writer, &buf)
+            }
+            Item::Float(Float::Double(bits)) => {
+                let mut buf = [0xfb, 0, 0, 0, 0, 0, 0, 0, 0];
+                BigEndian::write_u64(&mut buf[1..], bits);
+                write_all(writer, &buf)
+            }
+        }
+    }
+}
+
+fn concat<T, B, F>(string: &Str<T>, mut buf: B, mut append: F) -> B
+where
+    F: FnMut(&mut B, &T),
+{
+    match *string {
+        Str::Definite(ref chunk) => append(&mut buf, &chunk.data),
+        Str::Indefinite(ref chunks) => {
+            for chunk in chunks {
+                append(&mut buf, &chunk.data);
+            }
+        }
+    }
+    buf
+}
+
+fn to_key(value: Value) -> Result<ObjectKey> {
+    match value {
+        Value::Array(_) | Value::Object(_) | Value::F64(_) | Value::F32(_) | Value::F16(_) => {
+            Err(Error::custom("invalid type: map key, expected any valid CBOR key"))
+        }
+        value => Ok(ObjectKey::from(value)),
+    }
+}
+
+fn write_all<W>(writer: &mut W, buf: &[u8]) -> Result<()>
+where
+    W: Write,
+{
+    writer.write_all(buf).map_err(Into::into)
+}
+
+fn write_head<W>(writer: &mut W, major: u8, argument: Argument) -> Result<()>
+where
+    W: Write,
+{
+    let width = if argument.width.holds(argument.value) {
+        argument.width
+    } else {
+        Width::minimal(argument.value)
+    };
+    let major = major << 5;
+    let value = argument.value;
+    match width {
+        Width::Immediate => write_all(writer, &[major | value as u8]),
+        Width::U8 => write_all(writer, &[major | 24, value as u8]),
+        Width::U16 => {
+            let mut buf = [major | 25, 0, 0];
+            BigEndian::write_u16(&mut buf[1..], value as u16);
+            write_all(writer, &buf)
+        }
+        Width::U32 => {
+            let mut buf = [major | 26, 0, 0, 0, 0];
+            BigEndian::write_u32(&mut buf[1..], value as u32);
+            write_all(writer, &buf)
+        }
+        Width::U64 => {
+            let mut buf = [major | 27, 0, 0, 0, 0, 0, 0, 0, 0];
+            BigEndian::write_u64(&mut buf[1..], value);
+            write_all(writer, &buf)
+        }
+    }
+}
+
+fn write_length<W>(writer: &mut W, major: u8, length: Length, len: usize) -> Result<()>
+where
+    W: Write,
+{
+    match length {
+        Length::Definite(width) => {
+            write_head(
+                writer,
+                major,
+                Argument {
+                    value: len as u64,
+                    width,
+                },
+            )
+        }
+        Length::Indefinite => write_all(writer, &[major << 5 | 31]),
+    }
+}
+
+fn write_break<W>(writer: &mut W, length: Length) -> Result<()>
+where
+    W: Write,
+{
+    match length {
+        Length::Definite(_) => Ok(()),
+        Length::Indefinite => write_all(writer, &[0xff]),
+    }
+}
+
+fn encode_str<W, T>(writer: &mut W, major: u8, string: &Str<T>) -> Result<()>
+where
+    W: Write,
+    T: AsRef<[u8]>,
+{
+    let encode_chunk = |writer: &mut W, chunk: &Chunk<T>| {
+        let data = chunk.data.as_ref();
+        write_head(
+            writer,
+            major,
+            Argument {
+                value: data.len() as u64,
+                width: chunk.width,
+            },
+        )?;
+        write_all(writer, data)
+    };
+    match *string {
+        Str::Definite(ref chunk) => encode_chunk(writer, chunk),
+        Str::Indefinite(ref chunks) => {
+            write_all(writer, &[major << 5 | 31])?;
+            for chunk in chunks {
+                encode_chunk(writer, chunk)?;
+            }
+            write_all(writer, &[0xff])
+        }
+    }
+}
+
+struct Parser<R> {
+    read: R,
+    remaining_depth: u8,
+}
+
+impl<'de, R> Parser<R>
+where
+    R: Read<'de>,
+{
+    fn new(read: R) -> Parser<R> {
+        Parser {
+            read,
+            remaining_depth: 128,
+        }
+    }
+
+    fn end(&mut self) -> Result<()> {
+        match self.read.next()? {
+            Some(_) => Err(self.error(ErrorCode::TrailingData)),
+            None => Ok(()),
+        }
+    }
+
+    fn error(&self, reason: ErrorCode) -> Error {
+        Error::syntax(reason, self.read.offset())
+    }
+
+    fn parse_u8(&mut self) -> Result<u8> {
+        match self.read.next()? {
+            Some(byte) => Ok(byte),
+            None => Err(self.error(ErrorCode::EofWhileParsingValue)),
+        }
+    }
+
+    fn peek_break(&mut self, eof: ErrorCode) -> Result<bool> {
+        match self.read.peek()? {
+            Some(0xff) => {
+                self.read.discard();
+                Ok(true)
+            }
+            Some(_) => Ok(false),
+            None => Err(self.error(eof)),
+        }
+    }
+
+    fn parse_argument(&mut self, info: u8) -> Result<Argument> {
+        let (value, width) = match info {
+            0...23 => (u64::from(info), Width::Immediate),
+            24 => (u64::from(self.parse_u8()?), Width::U8),
+            25 => {
+                let mut buf = [0; 2];
+                self.read.read_into(&mut buf)?;
+                (u64::from(BigEndian::read_u16(&buf)), Width::U16)
+            }
+            26 => {
+                let mut buf = [0; 4];
+                self.read.read_into(&mut buf)?;
+                (u64::from(BigEndian::read_u32(&buf)), Width::U32)
+            }
+            27 => {
+                let mut buf = [0; 8];
+                self.read.read_into(&mut buf)?;
+                (BigEndian::read_u64(&buf), Width::U64)
+            }
+            _ => return Err(self.error(ErrorCode::UnassignedCode)),
+        };
+        Ok(Argument { value, width })
+    }
+
+    fn parse_len(&mut self, argument: Argument) -> Result<usize> {
+        if argument.value > usize::max_value() as u64 {
+            return Err(self.error(ErrorCode::LengthOutOfRange));
+        }
+        Ok(argument.value as usize)
+    }
+
+    fn parse_data(&mut self, argument: Argument) -> Result<Vec<u8>> {
+        let len = self.parse_len(argument)?;
+        Ok(match self.read.read(len)? {
+            Reference::Borrowed(buf) => buf.to_vec(),
+            Reference::Copied(buf) => buf.to_vec(),
+        })
+    }
+
+    fn parse_text(&mut self, argument: Argument) -> Result<String> {
+        let data = self.parse_data(argument)?;
+        String::from_utf8(data).map_err(|e| {
+            let shift = e.as_bytes().len() - e.utf8_error().valid_up_to();
+            Error::syntax(ErrorCode::InvalidUtf8, self.read.offset() - shift as u64)
+        })
+    }
+
+    fn parse_chunks<T, F>(&mut self, major: u8, mut parse: F) -> Result<Vec<Chunk<T>>>
+    where
+        F: FnMut(&mut Parser<R>, Argument) -> Result<T>,
+    {
+        let mut chunks = Vec::new();
+        while !self.peek_break(ErrorCode::EofWhileParsingValue)? {
+            let byte = self.parse_u8()?;
+            if byte >> 5 != major || byte & 0x1f == 31 {
+                return Err(self.error(ErrorCode::UnexpectedCode));
+            }
+            let argument = self.parse_argument(byte & 0x1f)?;
+            let data = parse(self, argument)?;
+            chunks.push(Chunk {
+                width: argument.width,
+                data,
+            });
+        }
+        Ok(chunks)
+    }
+
+    fn recursion_checked<F, T>(&mut self, f: F) -> Result<T>
+    where
+        F: FnOnce(&mut Parser<R>) -> Result<T>,
+    {
+        self.remaining_depth -= 1;
+        if self.remaining_depth == 0 {
+            return Err(self.error(ErrorCode::RecursionLimitExceeded));
+        }
+        let r = f(self);
+        self.remaining_depth += 1;
+        r
+    }
+
+    fn parse_array(&mut self, length: Length, len: Option<usize>) -> Result<Item> {
+        self.recursion_checked(|parser| {
+            let mut items = Vec::new();
+            match len {
+                Some(len) => {
+                    for _ in 0..len {
+                        items.push(parser.parse_item()?);
+                    }
+                }
+                None => {
+                    while !parser.peek_break(ErrorCode::EofWhileParsingArray)? {
+                        items.push(parser.parse_item()?);
+                    }
+                }
+            }
+            Ok(Item::Array(Array { length, items }))
+        })
+    }
+
+    fn parse_map(&mut self, length: Length, len: Option<usize>) -> Result<Item> {
+        self.recursion_checked(|parser| {
+            let mut entries = Vec::new();
+            match len {
+                Some(len) => {
+                    for _ in 0..len {
+                        let key = parser.parse_item()?;
+                        entries.push((key, parser.parse_item()?));
+                    }
+                }
+                None => {
+                    while !parser.peek_break(ErrorCode::EofWhileParsingMap)? {
+                        let key = parser.parse_item()?;
+                        entries.push((key, parser.parse_item()?));
+                    }
+                }
+            }
+            Ok(Item::Map(Map { length, entries }))
+        })
+    }
+
+    fn parse_item(&mut self) -> Result<Item> {
+        let byte = self.parse_u8()?;
+        let major = byte >> 5;
+        let info = byte & 0x1f;
+
+        if info == 31 {
+            return match major {
+                2 => Ok(Item::Bytes(Str::Indefinite(self.parse_chunks(2, Parser::parse_data)?))),
+                3 => Ok(Item::Text(Str::Indefinite(self.parse_chunks(3, Parser::parse_text)?))),
+                4 => self.parse_array(Length::Indefinite, None),
+                5 => self.parse_map(Length::Indefinite, None),
+                _ => Err(self.error(ErrorCode::UnexpectedCode)),
+            };
+        }
+
+        if major == 7 {
+            return match info {
+                25 => {
+                    let argument = self.parse_argument(info)?;
+                    Ok(Item::Float(Float::Half(argument.value as u16)))
+                }
+                26 => {
+                    let argument = self.parse_argument(info)?;
+                    Ok(Item::Float(Float::Single(argument.value as u32)))
+                }
+                27 => {
+                    let argument = self.parse_argument(info)?;
+                    Ok(Item::Float(Float::Double(argument.value)))
+                }
+                _ => {
+                    let argument = self.parse_argument(info)?;
+                    if (24..32).contains(&argument.value) {
+                        return Err(self.error(ErrorCode::UnassignedCode));
+                    }
+                    Ok(Item::Simple(argument))
+                }
+            };
+        }
+
+        let argument = self.parse_argument(info)?;
+        match major {
+            0 => Ok(Item::Unsigned(argument)),
+            1 => Ok(Item::Negative(argument)),
+            2 => {
+                let data = self.parse_data(argument)?;
+                Ok(Item::Bytes(Str::Definite(Chunk {
+                    width: argument.width,
+                    data,
+                })))
+            }
+            3 => {
+                let data = self.parse_text(argument)?;
+                Ok(Item::Text(Str::Definite(Chunk {
+                    width: argument.width,
+                    data,
+                })))
+            }
+            4 => {
+                let len = self.parse_len(argument)?;
+                self.parse_array(Length::Definite(argument.width), Some(len))
+            }
+            5 => {
+                let len = self.parse_len(argument)?;
+                self.parse_map(Length::Definite(argument.width), Some(len))
+            }
+            _ => {
+                let item = self.recursion_checked(Parser::parse_item)?;
+                Ok(Item::Tag(argument, Box::new(item)))
+            }
+        }
+    }
+}
@@ -1,7 +1,44 @@
 //! CBOR values, keys and serialization routines.
 
+#[cfg(feature = "alloc")]
 pub mod value;
+#[cfg(feature = "alloc")]
 pub mod ser;
+#[cfg(feature = "alloc")]
+pub mod visit;
+mod raw;
 
+#[cfg(feature = "alloc")]
 pub use self::value::{ObjectKey, Value, from_value};
+#[cfg(feature = "alloc")]
 pub use self::ser::to_value;
+pub use self::raw::RawValue;
+#[cfg(feature = "alloc")]
+pub use self::raw::RawValueBuf;
+
+/// The precision a floating point value is encoded with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum FloatWidth {
+    /// A 16 bit half precision float.
+    Half,
+    /// A 32 bit single precision float.
+    Single,
+    /// A 64 bit double precision float.
+    Double,
+}
+
+/// Name of the newtype struct `Value` deserializes itself from.
+///
+/// It lets the CBOR deserializer report the width of half precision floats, which serde's data
+/// model has no way to express.
+pub(crate) const VALUE_TOKEN: &str = "\0cbor_value";
+
+/// Name of the newtype struct and enum variant used to pass half precision floats between the
+/// CBOR (de)serializers and `Value`.
+pub(crate) const F16_TOKEN: &str = "\0cbor_f16";
+
+/// Name of the newtype struct `RawValue` passes its bytes in.
+///
+/// The CBOR deserializer captures the bytes of the next item for it, and the CBOR serializer
+/// writes them back without a byte string head.
+pub(crate) const RAW_VALUE_TOKEN: &str = "\0cbor_raw_value";
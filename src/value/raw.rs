@@ -0,0 +1,226 @@
+//! Undecoded CBOR items.
+
+#[cfg(feature = "alloc")]
+use alloc::borrow::{Borrow, ToOwned};
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+use alloc::vec::Vec;
+use core::fmt;
+use core::result;
+#[cfg(feature = "alloc")]
+use core::ops::Deref;
+use serde::de;
+use serde::ser;
+
+use de::Deserializer;
+use error::Result;
+use value::RAW_VALUE_TOKEN;
+
+/// The encoded bytes of a single CBOR item, borrowed from the input.
+///
+/// Deserializing a `&RawValue` checks that the next item is well-formed and captures its bytes
+/// without decoding it. Serializing one writes the bytes back as they are, so a message can be
+/// forwarded, or a signature checked, over exactly the bytes it was received as. Text strings
+/// inside are not checked to be UTF-8.
+///
+/// Only input from slices can be borrowed, `RawValueBuf` also works with readers. Serializers
+/// other than the CBOR one receive the bytes as a byte string, except for `to_value`, which
+/// decodes them.
+///
+/// ```rust
+/// #[macro_use]
+/// extern crate serde_derive;
+/// extern crate serde_cbor;
+///
+/// use serde_cbor::value::RawValue;
+///
+/// #[derive(Deserialize, Serialize)]
+/// struct Envelope<'a> {
+///     to: &'a str,
+///     #[serde(borrow)]
+///     body: &'a RawValue,
+/// }
+///
+/// fn main() {
+///     // {"to": "b", "body": [1, 2.5]}
+///     let input = b"\xa2\x62to\x61b\x64body\x82\x01\xf9\x41\x00";
+///     let envelope: Envelope = serde_cbor::from_slice(input).unwrap();
+///     assert_eq!(envelope.to, "b");
+///     assert_eq!(envelope.body.as_bytes(), b"\x82\x01\xf9\x41\x00");
+///     assert_eq!(serde_cbor::to_vec(&envelope).unwrap(), &input[..]);
+/// }
+/// ```
+#[repr(transparent)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct RawValue {
+    bytes: [u8],
+}
+
+impl RawValue {
+    fn from_bytes_unchecked(bytes: &[u8]) -> &RawValue {
+        // `RawValue` is a transparent wrapper around `[u8]`, so the pointer casts keep the
+        // layout and the length.
+        unsafe { &*(bytes as *const [u8] as *const RawValue) }
+    }
+
+    /// Checks that a slice holds exactly one well-formed item and wraps it.
+    pub fn from_slice(bytes: &[u8]) -> Result<&RawValue> {
+        let mut de = Deserializer::from_slice(bytes);
+        de.skip_value(false)?;
+        de.end()?;
+        Ok(RawValue::from_bytes_unchecked(bytes))
+    }
+
+    /// Returns the encoded item.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+impl ser::Serialize for RawValue {
+    fn serialize<S>(&self, serializer: S) -> result::Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        serializer.serialize_newtype_struct(RAW_VALUE_TOKEN, &RawBytes(&self.bytes))
+    }
+}
+
+impl<'de: 'a, 'a> de::Deserialize<'de> for &'a RawValue {
+    fn deserialize<D>(deserializer: D) -> result::Result<&'a RawValue, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        struct RawValueVisitor;
+
+        impl<'de> de::Visitor<'de> for RawValueVisitor {
+            type Value = &'de RawValue;
+
+            fn expecting(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+                fmt.write_str("a CBOR item borrowed from the input")
+            }
+
+            fn visit_borrowed_bytes<E>(self, v: &'de [u8]) -> result::Result<&'de RawValue, E>
+            where
+                E: de::Error,
+            {
+                Ok(RawValue::from_bytes_unchecked(v))
+            }
+        }
+
+        deserializer.deserialize_newtype_struct(RAW_VALUE_TOKEN, RawValueVisitor)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl ToOwned for RawValue {
+    type Owned = RawValueBuf;
+
+    fn to_owned(&self) -> RawValueBuf {
+        RawValueBuf {
+            bytes: self.bytes.to_vec(),
+        }
+    }
+}
+
+/// The encoded bytes of a single CBOR item, owned.
+///
+/// The owned counterpart of `&RawValue`, which it dereferences to. It can be deserialized from
+/// any input source.
+#[cfg(feature = "alloc")]
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct RawValueBuf {
+    bytes: Vec<u8>,
+}
+
+#[cfg(feature = "alloc")]
+impl RawValueBuf {
+    /// Checks that a vector holds exactly one well-formed item and wraps it.
+    pub fn from_vec(bytes: Vec<u8>) -> Result<RawValueBuf> {
+        RawValue::from_slice(&bytes)?;
+        Ok(RawValueBuf { bytes })
+    }
+
+    /// Returns the encoded item.
+    pub fn into_vec(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Deref for RawValueBuf {
+    type Target = RawValue;
+
+    fn deref(&self) -> &RawValue {
+        RawValue::from_bytes_unchecked(&self.bytes)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Borrow<RawValue> for RawValueBuf {
+    fn borrow(&self) -> &RawValue {
+        self
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<'a> From<&'a RawValue> for RawValueBuf {
+    fn from(raw: &'a RawValue) -> RawValueBuf {
+        raw.to_owned()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl ser::Serialize for RawValueBuf {
+    fn serialize<S>(&self, serializer: S) -> result::Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        (**self).serialize(serializer)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<'de> de::Deserialize<'de> for RawValueBuf {
+    fn deserialize<D>(deserializer: D) -> result::Result<RawValueBuf, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        struct RawValueBufVisitor;
+
+        impl<'de> de::Visitor<'de> for RawValueBufVisitor {
+            type Value = RawValueBuf;
+
+            fn expecting(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+                fmt.write_str("a CBOR item")
+            }
+
+            fn visit_bytes<E>(self, v: &[u8]) -> result::Result<RawValueBuf, E>
+            where
+                E: de::Error,
+            {
+                Ok(RawValueBuf { bytes: v.to_vec() })
+            }
+
+            fn visit_byte_buf<E>(self, v: Vec<u8>) -> result::Result<RawValueBuf, E>
+            where
+                E: de::Error,
+            {
+                Ok(RawValueBuf { bytes: v })
+            }
+        }
+
+        deserializer.deserialize_newtype_struct(RAW_VALUE_TOKEN, RawValueBufVisitor)
+    }
+}
+
+/// Passes the bytes of a raw value on as a byte string.
+struct RawBytes<'a>(&'a [u8]);
+
+impl<'a> ser::Serialize for RawBytes<'a> {
+    fn serialize<S>(&self, serializer: S) -> result::Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        serializer.serialize_bytes(self.0)
+    }
+}
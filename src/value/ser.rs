@@ -8,13 +8,20 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use std::collections::BTreeMap;
+use alloc::collections::BTreeMap;
+#[cfg(not(feature = "std"))]
+use alloc::borrow::ToOwned;
+#[cfg(not(feature = "std"))]
+use alloc::string::String;
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
 
 use serde::{self, Serialize};
 use error::Error;
 
 use value::Value;
 use value::ObjectKey;
+use value::{F16_TOKEN, RAW_VALUE_TOKEN};
 
 struct Serializer;
 
@@ -76,7 +83,7 @@ impl serde::Serializer for Serializer {
 
     #[inline]
     fn serialize_f32(self, value: f32) -> Result<Value, Error> {
-        self.serialize_f64(f64::from(value))
+        Ok(Value::F32(value))
     }
 
     #[inline]
@@ -123,13 +130,18 @@ impl serde::Serializer for Serializer {
     #[inline]
     fn serialize_newtype_struct<T: ?Sized>(
         self,
-        _name: &'static str,
+        name: &'static str,
         value: &T,
     ) -> Result<Value, Error>
     where
         T: Serialize,
     {
-        value.serialize(self)
+        match value.serialize(self)? {
+            Value::F32(v) if name == F16_TOKEN => Ok(Value::F16(v)),
+            // Raw values hold a single well-formed item.
+            Value::Bytes(ref bytes) if name == RAW_VALUE_TOKEN => ::from_slice(bytes),
+            v => Ok(v),
+        }
     }
 
     fn serialize_newtype_variant<T: ?Sized>(
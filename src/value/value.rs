@@ -1,11 +1,20 @@
 //! CBOR values and keys.
 
-use std::collections::BTreeMap;
-use std::fmt;
+use alloc::collections::BTreeMap;
+use core::fmt;
+#[cfg(not(feature = "std"))]
+use alloc::borrow::ToOwned;
+#[cfg(not(feature = "std"))]
+use alloc::string::String;
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
 
 use serde::de;
 use serde::ser;
 
+pub use value::FloatWidth;
+use value::{F16_TOKEN, VALUE_TOKEN};
+
 /// An enum over all possible CBOR types.
 #[derive(Clone, Debug, PartialEq)]
 pub enum Value {
@@ -21,8 +30,15 @@ pub enum Value {
     Array(Vec<Value>),
     /// Represents a map.
     Object(BTreeMap<ObjectKey, Value>),
-    /// Represents a floating point value.
+    /// Represents a floating point value that was encoded with double precision.
     F64(f64),
+    /// Represents a floating point value that was encoded with single precision.
+    F32(f32),
+    /// Represents a floating point value that was encoded with half precision.
+    ///
+    /// Every half precision float can be represented exactly as an `f32`. Values that cannot be
+    /// represented as a half are encoded with single precision.
+    F16(f32),
     /// Represents a boolean value.
     Bool(bool),
     /// Represents the absence of a value or the value undefined.
@@ -126,7 +142,7 @@ impl Value {
     /// Retrns true if the value is a number.
     pub fn is_number(&self) -> bool {
         match *self {
-            Value::U64(_) | Value::I64(_) | Value::F64(_) => true,
+            Value::U64(_) | Value::I64(_) | Value::F64(_) | Value::F32(_) | Value::F16(_) => true,
             _ => false,
         }
     }
@@ -155,6 +171,33 @@ impl Value {
         }
     }
 
+    /// Returns true if the `Value` is a f32. Returns false otherwise.
+    pub fn is_f32(&self) -> bool {
+        match *self {
+            Value::F32(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Returns true if the `Value` is a f16. Returns false otherwise.
+    pub fn is_f16(&self) -> bool {
+        match *self {
+            Value::F16(_) => true,
+            _ => false,
+        }
+    }
+
+    /// If the `Value` is a float, returns the precision it is encoded with. Returns None
+    /// otherwise.
+    pub fn float_width(&self) -> Option<FloatWidth> {
+        match *self {
+            Value::F64(_) => Some(FloatWidth::Double),
+            Value::F32(_) => Some(FloatWidth::Single),
+            Value::F16(_) => Some(FloatWidth::Half),
+            _ => None,
+        }
+    }
+
     /// If the `Value` is a number, return or cast it to a i64. Returns None otherwise.
     pub fn as_i64(&self) -> Option<i64> {
         match *self {
@@ -179,6 +222,7 @@ impl Value {
             Value::I64(n) => Some(n as f64),
             Value::U64(n) => Some(n as f64),
             Value::F64(n) => Some(n),
+            Value::F32(n) | Value::F16(n) => Some(f64::from(n)),
             _ => None,
         }
     }
@@ -326,6 +370,14 @@ impl<'de> de::Deserialize<'de> for Value {
                 Ok(Value::Object(values))
             }
 
+            #[inline]
+            fn visit_f32<E>(self, v: f32) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                Ok(Value::F32(v))
+            }
+
             #[inline]
             fn visit_f64<E>(self, v: f64) -> Result<Self::Value, E>
             where
@@ -333,9 +385,28 @@ impl<'de> de::Deserialize<'de> for Value {
             {
                 Ok(Value::F64(v))
             }
+
+            #[inline]
+            fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+            where
+                D: de::Deserializer<'de>,
+            {
+                // Deserializers other than the CBOR one do not know about `VALUE_TOKEN`.
+                deserializer.deserialize_any(self)
+            }
+
+            #[inline]
+            fn visit_enum<A>(self, data: A) -> Result<Self::Value, A::Error>
+            where
+                A: de::EnumAccess<'de>,
+            {
+                let (_, variant) = data.variant_seed(HalfFloatSeed)?;
+                let v = de::VariantAccess::newtype_variant(variant)?;
+                Ok(Value::F16(v))
+            }
         }
 
-        deserializer.deserialize_any(ValueVisitor)
+        deserializer.deserialize_newtype_struct(VALUE_TOKEN, ValueVisitor)
     }
 }
 
@@ -353,12 +424,47 @@ impl ser::Serialize for Value {
             Value::Array(ref v) => v.serialize(serializer),
             Value::Object(ref v) => v.serialize(serializer),
             Value::F64(v) => serializer.serialize_f64(v),
+            Value::F32(v) => serializer.serialize_f32(v),
+            Value::F16(v) => serializer.serialize_newtype_struct(F16_TOKEN, &v),
             Value::Bool(v) => serializer.serialize_bool(v),
             Value::Null => serializer.serialize_unit(),
         }
     }
 }
 
+/// Accepts only the variant name the CBOR deserializer uses for half precision floats.
+struct HalfFloatSeed;
+
+impl<'de> de::DeserializeSeed<'de> for HalfFloatSeed {
+    type Value = ();
+
+    fn deserialize<D>(self, deserializer: D) -> Result<(), D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        deserializer.deserialize_identifier(self)
+    }
+}
+
+impl<'de> de::Visitor<'de> for HalfFloatSeed {
+    type Value = ();
+
+    fn expecting(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.write_str("a half precision float")
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<(), E>
+    where
+        E: de::Error,
+    {
+        if value == F16_TOKEN {
+            Ok(())
+        } else {
+            Err(de::Error::invalid_value(de::Unexpected::Str(value), &self))
+        }
+    }
+}
+
 /// A simplified CBOR value containing only types useful for keys.
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum ObjectKey {
@@ -631,6 +737,7 @@ impl_from!(Value, String, String);
 impl_from!(Value, Array, Vec<Value>);
 impl_from!(Value, Object, BTreeMap<ObjectKey, Value>);
 impl_from!(Value, F64, f64);
+impl_from!(Value, F32, f32);
 impl_from!(Value, Bool, bool);
 
 /// Convert a `serde_cbor::Value` into a type `T`
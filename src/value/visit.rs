@@ -0,0 +1,247 @@
+//! Traversal of `Value` trees.
+//!
+//! A `Visitor` receives every node of a value tree together with the path that leads to it,
+//! once before its children are walked (`enter`) and once afterwards (`leave`). A `VisitorMut`
+//! gets mutable access to the nodes and can modify or replace them in place.
+//!
+//! ```rust
+//! use std::collections::BTreeMap;
+//! use serde_cbor::{ObjectKey, Value};
+//! use serde_cbor::value::visit::{Path, PathSegment, VisitorMut, Walk};
+//!
+//! struct Redact;
+//!
+//! impl VisitorMut for Redact {
+//!     fn enter(&mut self, path: &Path, value: &mut Value) -> Walk {
+//!         if let Some(PathSegment::Key(ObjectKey::String(key))) = path.last() {
+//!             if key == "password" {
+//!                 *value = Value::Null;
+//!                 return Walk::SkipChildren;
+//!             }
+//!         }
+//!         Walk::Continue
+//!     }
+//! }
+//!
+//! let mut object = BTreeMap::new();
+//! object.insert(ObjectKey::String("password".to_owned()), Value::String("hunter2".to_owned()));
+//! let mut value = Value::Array(vec![Value::Object(object)]);
+//! value.walk_mut(&mut Redact);
+//!
+//! let redacted = &value.as_array().unwrap()[0].as_object().unwrap();
+//! assert_eq!(redacted[&ObjectKey::String("password".to_owned())], Value::Null);
+//! ```
+
+use core::fmt;
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+use value::{ObjectKey, Value};
+
+/// A single step from a container to one of its children.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PathSegment<'a> {
+    /// The value stored under the given key of a map.
+    Key(&'a ObjectKey),
+    /// The element at the given position of an array.
+    Index(usize),
+}
+
+/// The location of a node in a value tree, relative to the value the walk started at.
+///
+/// Paths are built on the stack while walking, so they are cheap to create but only valid for
+/// the duration of a single visitor call.
+pub struct Path<'a> {
+    parent: Option<&'a Path<'a>>,
+    segment: Option<PathSegment<'a>>,
+}
+
+impl<'a> Path<'a> {
+    fn root() -> Path<'static> {
+        Path {
+            parent: None,
+            segment: None,
+        }
+    }
+
+    fn child(&'a self, segment: PathSegment<'a>) -> Path<'a> {
+        Path {
+            parent: Some(self),
+            segment: Some(segment),
+        }
+    }
+
+    /// Returns true if this is the path of the value the walk started at.
+    pub fn is_root(&self) -> bool {
+        self.segment.is_none()
+    }
+
+    /// The number of segments in the path. The root has a depth of zero.
+    pub fn depth(&self) -> usize {
+        match self.parent {
+            Some(parent) => parent.depth() + 1,
+            None => 0,
+        }
+    }
+
+    /// The last segment of the path, or `None` for the root.
+    pub fn last(&self) -> Option<PathSegment<'a>> {
+        self.segment
+    }
+
+    /// The path of the container holding this node, or `None` for the root.
+    pub fn parent(&self) -> Option<&'a Path<'a>> {
+        self.parent
+    }
+
+    /// Collects the segments of the path, starting at the root.
+    pub fn segments(&self) -> Vec<PathSegment<'a>> {
+        let mut segments = Vec::with_capacity(self.depth());
+        let mut path = Some(self);
+        while let Some(p) = path {
+            if let Some(segment) = p.segment {
+                segments.push(segment);
+            }
+            path = p.parent;
+        }
+        segments.reverse();
+        segments
+    }
+}
+
+impl<'a> fmt::Debug for Path<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.segments()).finish()
+    }
+}
+
+/// Tells the walker how to continue after a node has been entered.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Walk {
+    /// Walk the children of the node.
+    Continue,
+    /// Do not walk the children of the node. `leave` is still called for the node.
+    SkipChildren,
+    /// Abort the walk. No further methods are called.
+    Stop,
+}
+
+/// Receives the nodes of a value tree in pre- and post-order.
+pub trait Visitor {
+    /// Called for a node before its children are visited.
+    fn enter(&mut self, _path: &Path, _value: &Value) -> Walk {
+        Walk::Continue
+    }
+
+    /// Called for a node after all of its children have been visited.
+    fn leave(&mut self, _path: &Path, _value: &Value) {}
+}
+
+/// Receives the nodes of a value tree in pre- and post-order and may modify them.
+///
+/// Assigning a new value to a node in `enter` makes the walker descend into the new value.
+/// Assigning a new value in `leave` replaces the node after its children have been processed.
+pub trait VisitorMut {
+    /// Called for a node before its children are visited.
+    fn enter(&mut self, _path: &Path, _value: &mut Value) -> Walk {
+        Walk::Continue
+    }
+
+    /// Called for a node after all of its children have been visited.
+    fn leave(&mut self, _path: &Path, _value: &mut Value) {}
+}
+
+impl Value {
+    /// Walks the value and all values nested inside of it, depth-first.
+    ///
+    /// Map entries are visited in key order.
+    pub fn walk<V>(&self, visitor: &mut V)
+    where
+        V: Visitor + ?Sized,
+    {
+        walk(visitor, &Path::root(), self);
+    }
+
+    /// Walks the value and all values nested inside of it, depth-first, allowing the visitor to
+    /// modify them.
+    ///
+    /// Map entries are visited in key order.
+    pub fn walk_mut<V>(&mut self, visitor: &mut V)
+    where
+        V: VisitorMut + ?Sized,
+    {
+        walk_mut(visitor, &Path::root(), self);
+    }
+}
+
+// Both walkers return false once the visitor asked to stop.
+fn walk<V>(visitor: &mut V, path: &Path, value: &Value) -> bool
+where
+    V: Visitor + ?Sized,
+{
+    match visitor.enter(path, value) {
+        Walk::Continue => {}
+        Walk::SkipChildren => {
+            visitor.leave(path, value);
+            return true;
+        }
+        Walk::Stop => return false,
+    }
+
+    match *value {
+        Value::Array(ref array) => {
+            for (i, element) in array.iter().enumerate() {
+                if !walk(visitor, &path.child(PathSegment::Index(i)), element) {
+                    return false;
+                }
+            }
+        }
+        Value::Object(ref object) => {
+            for (key, element) in object {
+                if !walk(visitor, &path.child(PathSegment::Key(key)), element) {
+                    return false;
+                }
+            }
+        }
+        _ => {}
+    }
+
+    visitor.leave(path, value);
+    true
+}
+
+fn walk_mut<V>(visitor: &mut V, path: &Path, value: &mut Value) -> bool
+where
+    V: VisitorMut + ?Sized,
+{
+    match visitor.enter(path, value) {
+        Walk::Continue => {}
+        Walk::SkipChildren => {
+            visitor.leave(path, value);
+            return true;
+        }
+        Walk::Stop => return false,
+    }
+
+    match *value {
+        Value::Array(ref mut array) => {
+            for (i, element) in array.iter_mut().enumerate() {
+                if !walk_mut(visitor, &path.child(PathSegment::Index(i)), element) {
+                    return false;
+                }
+            }
+        }
+        Value::Object(ref mut object) => {
+            for (key, element) in object.iter_mut() {
+                if !walk_mut(visitor, &path.child(PathSegment::Key(key)), element) {
+                    return false;
+                }
+            }
+        }
+        _ => {}
+    }
+
+    visitor.leave(path, value);
+    true
+}
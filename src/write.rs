@@ -0,0 +1,177 @@
+use core::cmp;
+use core::convert::Infallible;
+use core::result;
+#[cfg(feature = "std")]
+use std::io;
+
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+use alloc::vec::Vec;
+
+use error::{Error, Result};
+
+/// A sink for CBOR output, the counterpart of `de::Read`.
+///
+/// Implementations exist for `Vec<u8>`, `SliceWrite`, `CountWrite` and, through `IoWrite`, for
+/// every `std::io::Write`. Sinks that cannot fail use `Infallible` as their error type, so the
+/// serializer does not spend any work on error handling for them.
+pub trait Write {
+    /// The error returned when writing fails.
+    type Error: Into<Error>;
+
+    /// Writes all of `buf`.
+    fn write_all(&mut self, buf: &[u8]) -> result::Result<(), Self::Error>;
+}
+
+impl<'a, W> Write for &'a mut W
+where
+    W: Write + ?Sized,
+{
+    type Error = W::Error;
+
+    #[inline]
+    fn write_all(&mut self, buf: &[u8]) -> result::Result<(), W::Error> {
+        (**self).write_all(buf)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Write for Vec<u8> {
+    type Error = Infallible;
+
+    #[inline]
+    fn write_all(&mut self, buf: &[u8]) -> result::Result<(), Infallible> {
+        self.extend_from_slice(buf);
+        Ok(())
+    }
+}
+
+/// CBOR output target that writes to a std::io output stream.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct IoWrite<W>(W);
+
+#[cfg(feature = "std")]
+impl<W> IoWrite<W>
+where
+    W: io::Write,
+{
+    /// Creates a new CBOR output target that writes to a std::io output stream.
+    pub fn new(writer: W) -> IoWrite<W> {
+        IoWrite(writer)
+    }
+
+    /// Unwraps the output stream.
+    pub fn into_inner(self) -> W {
+        self.0
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W> Write for IoWrite<W>
+where
+    W: io::Write,
+{
+    type Error = Error;
+
+    #[inline]
+    fn write_all(&mut self, buf: &[u8]) -> Result<()> {
+        self.0.write_all(buf).map_err(Error::io)
+    }
+}
+
+/// CBOR output target that only counts the bytes written to it.
+///
+/// Serializing into it computes the length of an encoding without storing it.
+#[derive(Debug, Default)]
+pub struct CountWrite {
+    count: usize,
+}
+
+impl CountWrite {
+    /// Creates a new counter starting at zero.
+    pub fn new() -> CountWrite {
+        CountWrite { count: 0 }
+    }
+
+    /// Returns the number of bytes written so far.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+}
+
+impl Write for CountWrite {
+    type Error = Infallible;
+
+    #[inline]
+    fn write_all(&mut self, buf: &[u8]) -> result::Result<(), Infallible> {
+        self.count = self.count.saturating_add(buf.len());
+        Ok(())
+    }
+}
+
+/// CBOR output target that writes into a mutable byte slice.
+///
+/// Writing never fails. Bytes that do not fit into the slice are dropped but counted, so after
+/// serializing a value `finish` reports either the number of bytes used or how large the slice
+/// would have to be.
+#[derive(Debug)]
+pub struct SliceWrite<'a> {
+    slice: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> SliceWrite<'a> {
+    /// Creates a new CBOR output target that writes into a slice.
+    pub fn new(slice: &'a mut [u8]) -> SliceWrite<'a> {
+        SliceWrite { slice, len: 0 }
+    }
+
+    /// Returns the number of bytes written so far, including those that did not fit.
+    pub fn bytes_written(&self) -> usize {
+        self.len
+    }
+
+    /// Returns the number of bytes written, or a "buffer too small" error with the number of
+    /// bytes that would have been needed.
+    pub fn finish(self) -> Result<usize> {
+        if self.len > self.slice.len() {
+            Err(Error::buffer_too_small(self.len))
+        } else {
+            Ok(self.len)
+        }
+    }
+
+    /// Unwraps the slice, dropping the count of bytes written.
+    pub fn into_inner(self) -> &'a mut [u8] {
+        self.slice
+    }
+
+    fn copy_from(&mut self, buf: &[u8]) {
+        let start = cmp::min(self.len, self.slice.len());
+        let end = cmp::min(self.len.saturating_add(buf.len()), self.slice.len());
+        self.slice[start..end].copy_from_slice(&buf[..end - start]);
+        self.len = self.len.saturating_add(buf.len());
+    }
+}
+
+impl<'a> Write for SliceWrite<'a> {
+    type Error = Infallible;
+
+    #[inline]
+    fn write_all(&mut self, buf: &[u8]) -> result::Result<(), Infallible> {
+        self.copy_from(buf);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'a> io::Write for SliceWrite<'a> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.copy_from(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
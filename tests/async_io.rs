@@ -0,0 +1,114 @@
+#![cfg(feature = "async")]
+// The `async` feature needs Rust 1.85, above the minimum version of the crate.
+#![allow(clippy::incompatible_msrv)]
+
+extern crate futures_core;
+extern crate serde_cbor;
+extern crate tokio;
+
+use futures_core::Stream;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll, Waker};
+use tokio::io::{duplex, AsyncWrite};
+
+use serde_cbor::async_io::{from_async_reader, stream_from_async_reader, to_async_writer};
+use serde_cbor::Value;
+
+fn poll<F: Future + Unpin>(future: &mut F) -> Poll<F::Output> {
+    Pin::new(future).poll(&mut Context::from_waker(Waker::noop()))
+}
+
+fn poll_next<S: Stream + Unpin>(stream: &mut S) -> Poll<Option<S::Item>> {
+    Pin::new(stream).poll_next(&mut Context::from_waker(Waker::noop()))
+}
+
+fn write<W: AsyncWrite + Unpin>(writer: &mut W, buf: &[u8]) {
+    let mut cx = Context::from_waker(Waker::noop());
+    match Pin::new(writer).poll_write(&mut cx, buf) {
+        Poll::Ready(Ok(n)) => assert_eq!(n, buf.len()),
+        _ => panic!("the pipe is full"),
+    }
+}
+
+#[test]
+fn test_read_item_suspends() {
+    let (mut tx, rx) = duplex(64);
+    let mut rx = rx;
+    let mut item = from_async_reader::<_, Vec<String>>(&mut rx);
+    assert!(poll(&mut item).is_pending());
+    write(&mut tx, b"\x82\x63foo\x7f\x62ba");
+    assert!(poll(&mut item).is_pending());
+    write(&mut tx, b"\x61r\xff\x01");
+    match poll(&mut item) {
+        Poll::Ready(Ok(value)) => assert_eq!(value, vec!["foo", "bar"]),
+        other => panic!("unexpected {:?}", other),
+    }
+
+    // Nothing beyond the item has been read.
+    drop(tx);
+    let mut item = from_async_reader::<_, u8>(&mut rx);
+    match poll(&mut item) {
+        Poll::Ready(Ok(value)) => assert_eq!(value, 1),
+        other => panic!("unexpected {:?}", other),
+    }
+    let mut item = from_async_reader::<_, u8>(&mut rx);
+    match poll(&mut item) {
+        Poll::Ready(Err(e)) => assert!(e.is_eof()),
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn test_stream() {
+    let (mut tx, rx) = duplex(64);
+    let mut stream = stream_from_async_reader::<_, Value>(rx);
+    write(&mut tx, b"\x01\xa1\x61a");
+    match poll_next(&mut stream) {
+        Poll::Ready(Some(Ok(value))) => assert_eq!(value, Value::U64(1)),
+        other => panic!("unexpected {:?}", other),
+    }
+    assert!(poll_next(&mut stream).is_pending());
+    write(&mut tx, b"\xf6\x20");
+    match poll_next(&mut stream) {
+        Poll::Ready(Some(Ok(Value::Object(_)))) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    match poll_next(&mut stream) {
+        Poll::Ready(Some(Ok(value))) => assert_eq!(value, Value::I64(-1)),
+        other => panic!("unexpected {:?}", other),
+    }
+
+    // The input may end between items, but not within one.
+    write(&mut tx, b"\x82\x01");
+    drop(tx);
+    match poll_next(&mut stream) {
+        Poll::Ready(Some(Err(e))) => assert!(e.is_eof()),
+        other => panic!("unexpected {:?}", other),
+    }
+    assert!(match poll_next(&mut stream) {
+        Poll::Ready(None) => true,
+        _ => false,
+    });
+}
+
+#[test]
+fn test_write() {
+    let (tx, mut rx) = duplex(64);
+    let mut tx = tx;
+    let mut item = to_async_writer(&mut tx, &vec![1, 2, 3]);
+    assert!(match poll(&mut item) {
+        Poll::Ready(Ok(())) => true,
+        _ => false,
+    });
+    drop(tx);
+    let mut stream = stream_from_async_reader::<_, Vec<u8>>(&mut rx);
+    match poll_next(&mut stream) {
+        Poll::Ready(Some(Ok(value))) => assert_eq!(value, vec![1, 2, 3]),
+        other => panic!("unexpected {:?}", other),
+    }
+    assert!(match poll_next(&mut stream) {
+        Poll::Ready(None) => true,
+        _ => false,
+    });
+}
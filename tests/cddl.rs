@@ -0,0 +1,627 @@
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_bytes;
+extern crate serde_cbor;
+
+use std::collections::BTreeMap;
+
+use serde_bytes::ByteBuf;
+use serde_cbor::cddl::Schema;
+use serde_cbor::{to_value, to_vec, ObjectKey, Value};
+
+#[derive(Serialize)]
+struct Person {
+    name: String,
+    age: u64,
+}
+
+#[derive(Serialize)]
+struct Team {
+    name: String,
+    members: Vec<Person>,
+}
+
+const PEOPLE: &str = r#"
+    ; A team of people.
+    team = {
+        name: tstr,
+        members: [* person],
+    }
+
+    person = {
+        name: tstr .size (1..32),
+        ? age: uint .le 150,
+    }
+"#;
+
+fn person(name: &str, age: u64) -> Person {
+    Person {
+        name: name.to_owned(),
+        age,
+    }
+}
+
+fn map(entries: Vec<(ObjectKey, Value)>) -> Value {
+    Value::Object(entries.into_iter().collect::<BTreeMap<_, _>>())
+}
+
+#[test]
+fn test_map() {
+    let schema = Schema::parse(PEOPLE).unwrap();
+    let team = Team {
+        name: "red".to_owned(),
+        members: vec![person("Alice", 30), person("Bob", 40)],
+    };
+    schema
+        .validate_slice(&to_vec(&team).unwrap(), "team")
+        .unwrap();
+    schema.validate(&to_value(&team).unwrap(), "team").unwrap();
+
+    let mut alice = BTreeMap::new();
+    alice.insert("name", "Alice");
+    schema
+        .validate_slice(&to_vec(&alice).unwrap(), "person")
+        .unwrap();
+}
+
+#[test]
+fn test_error_path() {
+    let schema = Schema::parse(PEOPLE).unwrap();
+    let team = Team {
+        name: "red".to_owned(),
+        members: vec![person("Alice", 30), person("Bob", 400)],
+    };
+    let error = schema
+        .validate_slice(&to_vec(&team).unwrap(), "team")
+        .unwrap_err();
+    assert_eq!(error.path(), "/members/1/age");
+    assert_eq!(error.rule(), "person");
+    assert_eq!(
+        error.message(),
+        "expected `uint .le 150`, found unsigned integer 400"
+    );
+    assert_eq!(
+        error.to_string(),
+        "expected `uint .le 150`, found unsigned integer 400 at /members/1/age (rule `person`)"
+    );
+
+    let team = Team {
+        name: "red".to_owned(),
+        members: vec![person("", 30)],
+    };
+    let error = schema
+        .validate_slice(&to_vec(&team).unwrap(), "team")
+        .unwrap_err();
+    assert_eq!(error.path(), "/members/0/name");
+
+    let error = schema.validate(&Value::U64(1), "team").unwrap_err();
+    assert_eq!(error.path(), "/");
+    assert_eq!(error.message(), "expected a map, found unsigned integer 1");
+}
+
+#[test]
+fn test_map_entries() {
+    let schema = Schema::parse(PEOPLE).unwrap();
+
+    let mut extra = BTreeMap::new();
+    extra.insert("name", "Alice");
+    extra.insert("email", "alice@example.com");
+    let error = schema
+        .validate_slice(&to_vec(&extra).unwrap(), "person")
+        .unwrap_err();
+    assert_eq!(error.path(), "/email");
+    assert_eq!(error.message(), "unexpected map entry");
+
+    let empty: BTreeMap<String, String> = BTreeMap::new();
+    let error = schema
+        .validate_slice(&to_vec(&empty).unwrap(), "person")
+        .unwrap_err();
+    assert_eq!(error.path(), "/");
+    assert_eq!(error.message(), "missing map entry `\"name\"`");
+
+    let open = Schema::parse("open = { name: tstr, * tstr => any }").unwrap();
+    open.validate_slice(&to_vec(&extra).unwrap(), "open")
+        .unwrap();
+}
+
+#[test]
+fn test_integer_keys() {
+    let schema = Schema::parse(
+        r#"
+        header-map = {
+            ? 1 => int / tstr,     ; algorithm
+            ? 4 ^ => bstr,         ; key id, no fallback to the catch-all
+            * label => values,
+        }
+        label = int / tstr
+        values = any
+        "#,
+    )
+    .unwrap();
+
+    let header = map(vec![
+        (ObjectKey::Integer(1), Value::I64(-7)),
+        (ObjectKey::Integer(4), Value::Bytes(b"11".to_vec())),
+        (ObjectKey::Integer(-1), Value::Null),
+    ]);
+    schema.validate(&header, "header-map").unwrap();
+
+    let header = map(vec![(
+        ObjectKey::Integer(4),
+        Value::String("11".to_owned()),
+    )]);
+    let error = schema.validate(&header, "header-map").unwrap_err();
+    assert_eq!(error.path(), "/4");
+    assert_eq!(error.rule(), "header-map");
+}
+
+#[test]
+fn test_arrays() {
+    let schema = Schema::parse(
+        r#"
+        numbers = [+ int]
+        pair = [int, tstr]
+        tail = [* any, tstr]
+        bounded = [2*3 uint]
+        keyed = [x: float, y: float]
+        "#,
+    )
+    .unwrap();
+
+    schema
+        .validate_slice(&to_vec(&vec![1, -2, 3]).unwrap(), "numbers")
+        .unwrap();
+    let error = schema
+        .validate_slice(&to_vec(&Vec::<i32>::new()).unwrap(), "numbers")
+        .unwrap_err();
+    assert_eq!(error.path(), "/0");
+    assert_eq!(error.message(), "missing array element `int`");
+
+    schema
+        .validate_slice(&to_vec(&(1, "a")).unwrap(), "pair")
+        .unwrap();
+    let error = schema
+        .validate_slice(&to_vec(&(1, 2)).unwrap(), "pair")
+        .unwrap_err();
+    assert_eq!(error.path(), "/1");
+    assert_eq!(error.message(), "expected `tstr`, found unsigned integer 2");
+    let error = schema
+        .validate_slice(&to_vec(&(1, "a", 3)).unwrap(), "pair")
+        .unwrap_err();
+    assert_eq!(error.path(), "/2");
+    assert_eq!(error.message(), "unexpected array element");
+
+    // Matching `* any` needs to give back the last element.
+    schema
+        .validate_slice(&to_vec(&("a", "b", "c")).unwrap(), "tail")
+        .unwrap();
+    schema
+        .validate_slice(&to_vec(&("c",)).unwrap(), "tail")
+        .unwrap();
+    assert!(schema
+        .validate_slice(&to_vec(&(1,)).unwrap(), "tail")
+        .is_err());
+
+    assert!(schema
+        .validate_slice(&to_vec(&vec![1]).unwrap(), "bounded")
+        .is_err());
+    schema
+        .validate_slice(&to_vec(&vec![1, 2]).unwrap(), "bounded")
+        .unwrap();
+    schema
+        .validate_slice(&to_vec(&vec![1, 2, 3]).unwrap(), "bounded")
+        .unwrap();
+    assert!(schema
+        .validate_slice(&to_vec(&vec![1, 2, 3, 4]).unwrap(), "bounded")
+        .is_err());
+
+    schema
+        .validate_slice(&to_vec(&(1.5, 2.5)).unwrap(), "keyed")
+        .unwrap();
+}
+
+#[test]
+fn test_groups() {
+    let schema = Schema::parse(
+        r#"
+        shape = circle / rectangle
+        circle = { common, radius: uint }
+        rectangle = { common, width: uint, height: uint }
+        common = (
+            name: tstr,
+            ? color: color,
+        )
+        color = "red" / "green" / "blue"
+
+        point = [coordinates // (x: int, y: int, z: int)]
+        coordinates = (x: int, y: int)
+
+        pairs = [* (tstr, int)]
+        "#,
+    )
+    .unwrap();
+
+    let circle = map(vec![
+        (
+            ObjectKey::String("name".to_owned()),
+            Value::String("c".to_owned()),
+        ),
+        (ObjectKey::String("radius".to_owned()), Value::U64(3)),
+    ]);
+    schema.validate(&circle, "shape").unwrap();
+
+    let rectangle = map(vec![
+        (
+            ObjectKey::String("name".to_owned()),
+            Value::String("r".to_owned()),
+        ),
+        (
+            ObjectKey::String("color".to_owned()),
+            Value::String("red".to_owned()),
+        ),
+        (ObjectKey::String("width".to_owned()), Value::U64(3)),
+        (ObjectKey::String("height".to_owned()), Value::U64(4)),
+    ]);
+    schema.validate(&rectangle, "shape").unwrap();
+
+    let purple = map(vec![
+        (
+            ObjectKey::String("name".to_owned()),
+            Value::String("c".to_owned()),
+        ),
+        (
+            ObjectKey::String("color".to_owned()),
+            Value::String("purple".to_owned()),
+        ),
+        (ObjectKey::String("radius".to_owned()), Value::U64(3)),
+    ]);
+    let error = schema.validate(&purple, "shape").unwrap_err();
+    assert_eq!(error.path(), "/color");
+    assert_eq!(error.rule(), "color");
+    assert_eq!(
+        error.message(),
+        "expected `\"red\" / \"green\" / \"blue\"`, found text string"
+    );
+
+    schema
+        .validate_slice(&to_vec(&(1, 2)).unwrap(), "point")
+        .unwrap();
+    schema
+        .validate_slice(&to_vec(&(1, 2, 3)).unwrap(), "point")
+        .unwrap();
+    assert!(schema
+        .validate_slice(&to_vec(&(1, 2, 3, 4)).unwrap(), "point")
+        .is_err());
+
+    schema
+        .validate_slice(&to_vec(&("a", 1, "b", 2)).unwrap(), "pairs")
+        .unwrap();
+    assert!(schema
+        .validate_slice(&to_vec(&("a", 1, "b")).unwrap(), "pairs")
+        .is_err());
+}
+
+#[test]
+fn test_ranges_and_enumerations() {
+    let schema = Schema::parse(
+        r#"
+        percent = 0..100
+        exclusive = 0...100
+        unit = -1.0..1.0
+        port = min-port .. max-port
+        min-port = 1024
+        max-port = 65535
+        weekday = &weekdays
+        weekdays = (monday: 1, tuesday: 2, wednesday: 3)
+        "#,
+    )
+    .unwrap();
+
+    schema.validate(&Value::U64(100), "percent").unwrap();
+    assert!(schema.validate(&Value::U64(101), "percent").is_err());
+    assert!(schema.validate(&Value::U64(100), "exclusive").is_err());
+    assert!(schema.validate(&Value::I64(-1), "percent").is_err());
+    schema.validate(&Value::F64(-0.5), "unit").unwrap();
+    assert!(schema.validate(&Value::F64(1.5), "unit").is_err());
+    schema.validate(&Value::U64(8080), "port").unwrap();
+    assert!(schema.validate(&Value::U64(80), "port").is_err());
+
+    schema.validate(&Value::U64(2), "weekday").unwrap();
+    let error = schema.validate(&Value::U64(7), "weekday").unwrap_err();
+    assert_eq!(
+        error.message(),
+        "expected `&(weekdays)`, found unsigned integer 7"
+    );
+}
+
+#[test]
+fn test_unwrap() {
+    let schema = Schema::parse(
+        r#"
+        base = { id: uint }
+        extended = { ~base, label: tstr }
+        "#,
+    )
+    .unwrap();
+
+    let value = map(vec![
+        (ObjectKey::String("id".to_owned()), Value::U64(1)),
+        (
+            ObjectKey::String("label".to_owned()),
+            Value::String("x".to_owned()),
+        ),
+    ]);
+    schema.validate(&value, "extended").unwrap();
+    assert!(schema.validate(&value, "base").is_err());
+}
+
+#[test]
+fn test_size() {
+    let schema = Schema::parse(
+        r#"
+        key-id = bstr .size 4
+        short = tstr .size (0..3)
+        byte = uint .size 1
+        "#,
+    )
+    .unwrap();
+
+    schema
+        .validate(&Value::Bytes(vec![0; 4]), "key-id")
+        .unwrap();
+    assert!(schema
+        .validate(&Value::Bytes(vec![0; 5]), "key-id")
+        .is_err());
+    schema
+        .validate(&Value::String("abc".to_owned()), "short")
+        .unwrap();
+    assert!(schema
+        .validate(&Value::String("abcd".to_owned()), "short")
+        .is_err());
+    schema.validate(&Value::U64(255), "byte").unwrap();
+    assert!(schema.validate(&Value::U64(256), "byte").is_err());
+
+    // An indefinite length byte string of two chunks with four bytes in total.
+    schema
+        .validate_slice(b"\x5f\x42\x01\x02\x42\x03\x04\xff", "key-id")
+        .unwrap();
+}
+
+#[test]
+fn test_bits() {
+    let schema = Schema::parse(
+        r#"
+        permissions = uint .bits flags
+        flags = &(read: 0, write: 1, execute: 2)
+        mask = bstr .bits (0 / 9)
+        "#,
+    )
+    .unwrap();
+
+    schema.validate(&Value::U64(0b101), "permissions").unwrap();
+    assert!(schema.validate(&Value::U64(0b1000), "permissions").is_err());
+    schema
+        .validate(&Value::Bytes(vec![0x01, 0x02]), "mask")
+        .unwrap();
+    assert!(schema.validate(&Value::Bytes(vec![0x02]), "mask").is_err());
+}
+
+#[test]
+fn test_cbor() {
+    let schema = Schema::parse(
+        r#"
+        envelope = [protected: bstr .cbor header, payload: bstr]
+        header = { 1 => int }
+        sequence = bstr .cborseq [* uint]
+        "#,
+    )
+    .unwrap();
+
+    let mut header = BTreeMap::new();
+    header.insert(1, -7);
+    let header = ByteBuf::from(to_vec(&header).unwrap());
+    let envelope = (&header, ByteBuf::from(vec![]));
+    schema
+        .validate_slice(&to_vec(&envelope).unwrap(), "envelope")
+        .unwrap();
+
+    let mut header = BTreeMap::new();
+    header.insert(1, "ES256");
+    let header = ByteBuf::from(to_vec(&header).unwrap());
+    let envelope = (&header, ByteBuf::from(vec![]));
+    let error = schema
+        .validate_slice(&to_vec(&envelope).unwrap(), "envelope")
+        .unwrap_err();
+    assert_eq!(error.path(), "/0/1");
+    assert_eq!(error.rule(), "header");
+
+    let envelope = (ByteBuf::from(vec![0xff]), ByteBuf::from(vec![]));
+    let error = schema
+        .validate_slice(&to_vec(&envelope).unwrap(), "envelope")
+        .unwrap_err();
+    assert_eq!(error.path(), "/0");
+    assert!(error.message().starts_with("invalid embedded CBOR"));
+
+    schema
+        .validate(&Value::Bytes(vec![0x01, 0x02, 0x18, 0x20]), "sequence")
+        .unwrap();
+    assert!(schema
+        .validate(&Value::Bytes(vec![0x01, 0x61, 0x61]), "sequence")
+        .is_err());
+}
+
+#[test]
+fn test_comparisons() {
+    let schema = Schema::parse(
+        r#"
+        positive = int .gt 0
+        small = number .lt 1.5
+        not-zero = int .ne 0
+        retries = uint .default 3
+        even-small = uint .and (0 / 2 / 4)
+        "#,
+    )
+    .unwrap();
+
+    schema.validate(&Value::U64(1), "positive").unwrap();
+    assert!(schema.validate(&Value::I64(0), "positive").is_err());
+    schema.validate(&Value::F64(1.0), "small").unwrap();
+    schema.validate(&Value::U64(1), "small").unwrap();
+    assert!(schema.validate(&Value::U64(2), "small").is_err());
+    assert!(schema.validate(&Value::U64(0), "not-zero").is_err());
+    schema.validate(&Value::U64(7), "retries").unwrap();
+    schema.validate(&Value::U64(4), "even-small").unwrap();
+    assert!(schema.validate(&Value::U64(3), "even-small").is_err());
+}
+
+#[cfg(feature = "regex")]
+#[test]
+fn test_regexp() {
+    let schema = Schema::parse(r#"version = tstr .regexp "[0-9]+\\.[0-9]+""#).unwrap();
+    schema
+        .validate(&Value::String("1.10".to_owned()), "version")
+        .unwrap();
+    // The expression has to match the whole string.
+    assert!(schema
+        .validate(&Value::String("v1.10".to_owned()), "version")
+        .is_err());
+
+    let error = Schema::parse(r#"bad = tstr .regexp "(""#).unwrap_err();
+    assert_eq!((error.line(), error.column()), (1, 20));
+}
+
+#[cfg(not(feature = "regex"))]
+#[test]
+fn test_regexp() {
+    let error = Schema::parse(r#"version = tstr .regexp "[0-9]+""#).unwrap_err();
+    assert_eq!(error.message(), "`.regexp` requires the `regex` feature");
+}
+
+#[test]
+fn test_sockets() {
+    let schema = Schema::parse(
+        r#"
+        message = { kind: $kind, * $$extension }
+        $kind /= "ping"
+        $kind /= "pong"
+        $$extension //= (trace: bool)
+        $$extension //= (priority: uint)
+
+        closed = { ? value: $undefined }
+        open = { * $$undefined }
+        "#,
+    )
+    .unwrap();
+
+    let message = map(vec![
+        (
+            ObjectKey::String("kind".to_owned()),
+            Value::String("pong".to_owned()),
+        ),
+        (ObjectKey::String("trace".to_owned()), Value::Bool(true)),
+        (ObjectKey::String("priority".to_owned()), Value::U64(1)),
+    ]);
+    schema.validate(&message, "message").unwrap();
+
+    let message = map(vec![(
+        ObjectKey::String("kind".to_owned()),
+        Value::String("pang".to_owned()),
+    )]);
+    let error = schema.validate(&message, "message").unwrap_err();
+    assert_eq!(error.path(), "/kind");
+    assert_eq!(error.rule(), "$kind");
+
+    let empty = map(vec![]);
+    schema.validate(&empty, "closed").unwrap();
+    schema.validate(&empty, "open").unwrap();
+    let value = map(vec![(ObjectKey::String("value".to_owned()), Value::Null)]);
+    let error = schema.validate(&value, "closed").unwrap_err();
+    assert_eq!(error.message(), "socket `$undefined` has no definitions");
+}
+
+#[test]
+fn test_tags() {
+    let schema = Schema::parse(
+        r#"
+        link = uri
+        timestamp = tdate / time
+        any-tag = #6
+        "#,
+    )
+    .unwrap();
+
+    schema.validate_slice(b"\xd8\x20\x63a:b", "link").unwrap();
+    let error = schema.validate_slice(b"\x63a:b", "link").unwrap_err();
+    assert_eq!(error.message(), "expected `uri`, found text string");
+    schema
+        .validate_slice(b"\xc1\x1a\x51\x4b\x67\xb0", "timestamp")
+        .unwrap();
+    schema
+        .validate_slice(b"\xd9\xd9\xf7\x00", "any-tag")
+        .unwrap();
+    // Values carry no tags.
+    assert!(schema
+        .validate(&Value::String("a:b".to_owned()), "link")
+        .is_err());
+}
+
+#[test]
+fn test_prelude() {
+    let schema = Schema::parse("everything = [bool, null, float16, float64, bytes, nint]").unwrap();
+    schema
+        .validate_slice(
+            b"\x86\xf5\xf6\xf9\x3c\x00\xfb\x3f\xf0\0\0\0\0\0\0\x40\x20",
+            "everything",
+        )
+        .unwrap();
+    assert!(schema.has_rule("everything"));
+    assert!(schema.has_rule("tstr"));
+    assert!(!schema.has_rule("nothing"));
+
+    let error = schema.validate(&Value::Null, "nothing").unwrap_err();
+    assert_eq!(error.message(), "no rule named `nothing`");
+}
+
+#[test]
+fn test_literals() {
+    let schema = Schema::parse(
+        r#"
+        values = [0x10, 0b11, -1, 1.5, "a\"b", h'01 02', 'ab', b64'AQI']
+        "#,
+    )
+    .unwrap();
+    let value = Value::Array(vec![
+        Value::U64(16),
+        Value::U64(3),
+        Value::I64(-1),
+        Value::F64(1.5),
+        Value::String("a\"b".to_owned()),
+        Value::Bytes(vec![1, 2]),
+        Value::Bytes(b"ab".to_vec()),
+        Value::Bytes(vec![1, 2]),
+    ]);
+    schema.validate(&value, "values").unwrap();
+}
+
+#[test]
+fn test_parse_errors() {
+    let error = Schema::parse("a = int\nb = [c]").unwrap_err();
+    assert_eq!(error.message(), "undefined rule `c`");
+    assert_eq!((error.line(), error.column()), (2, 6));
+    assert_eq!(error.to_string(), "undefined rule `c` at line 2 column 6");
+
+    let error = Schema::parse("a = int .frobnicate 3").unwrap_err();
+    assert_eq!(error.message(), "unknown control operator `.frobnicate`");
+    assert_eq!(error.column(), 9);
+
+    let error = Schema::parse("a = int\na = tstr").unwrap_err();
+    assert_eq!(error.message(), "rule `a` is defined more than once");
+
+    let error = Schema::parse("pair<T> = [T, T]").unwrap_err();
+    assert_eq!(error.message(), "generic rules are not supported");
+
+    let error = Schema::parse("a = [int").unwrap_err();
+    assert_eq!(error.message(), "unexpected end of input");
+
+    assert!("a = { b: int }".parse::<Schema>().is_ok());
+}
@@ -0,0 +1,99 @@
+#![cfg(feature = "codec")]
+
+extern crate bytes;
+extern crate serde_cbor;
+extern crate tokio_util;
+
+use bytes::BytesMut;
+use tokio_util::codec::{Decoder, Encoder};
+
+use serde_cbor::codec::Codec;
+use serde_cbor::Value;
+
+#[test]
+fn test_decode_partial_reads() {
+    let input = b"\xd8\x20\xbf\x61a\x5f\x41x\x40\xff\x61b\x83\x01\x9f\xff\xf9\x3c\x00\xff\x18\x64";
+    // Feeding the input a byte at a time finds the same boundaries as feeding it at once.
+    let mut codec = Codec::<Value>::new();
+    let mut buf = BytesMut::new();
+    let mut items = Vec::new();
+    for &byte in input.iter() {
+        buf.extend_from_slice(&[byte]);
+        while let Some(item) = codec.decode(&mut buf).unwrap() {
+            items.push(item);
+        }
+    }
+    assert!(buf.is_empty());
+    assert_eq!(items.len(), 2);
+    assert_eq!(items[1], Value::U64(100));
+
+    let mut buf = BytesMut::from(&input[..]);
+    let mut codec = Codec::<Value>::new();
+    assert_eq!(codec.decode(&mut buf).unwrap(), Some(items[0].clone()));
+    assert_eq!(codec.decode_eof(&mut buf).unwrap(), Some(Value::U64(100)));
+    assert_eq!(codec.decode_eof(&mut buf).unwrap(), None);
+
+    let mut buf = BytesMut::from(&b"\x82\x01"[..]);
+    assert!(codec.decode_eof(&mut buf).unwrap_err().is_eof());
+}
+
+#[test]
+fn test_malformed() {
+    let inputs = [
+        &b"\xff"[..],
+        b"\x1c",
+        b"\x9f\x5f\x61a\xff\xff",
+        b"\x82\x01\xff",
+        // A tag has to be followed by an item, not by a break.
+        b"\x9f\xc1\xff",
+    ];
+    let mut codec = Codec::<Value>::new();
+    for input in &inputs {
+        let mut buf = BytesMut::from(*input);
+        assert!(codec.decode(&mut buf).unwrap_err().is_syntax());
+        // Nothing of the broken frame is left in the codec.
+        let mut buf = BytesMut::from(&b"\x01"[..]);
+        assert_eq!(codec.decode(&mut buf).unwrap(), Some(Value::U64(1)));
+    }
+}
+
+#[test]
+fn test_max_frame_len() {
+    let mut codec = Codec::<Value>::new().max_frame_len(4);
+    // The announced length is rejected before the string arrives.
+    let mut buf = BytesMut::from(&b"\x81\x44"[..]);
+    let error = codec.decode(&mut buf).unwrap_err();
+    assert!(error.is_data());
+    assert_eq!(error.offset(), 4);
+    // The codec starts over with the next frame.
+    let mut buf = BytesMut::from(&b"\x01"[..]);
+    assert_eq!(codec.decode(&mut buf).unwrap(), Some(Value::U64(1)));
+
+    // So is a complete item that arrives at once.
+    let mut codec = Codec::<Value>::new().max_frame_len(4);
+    let mut buf = BytesMut::from(&b"\x48abcdefgh"[..]);
+    assert!(codec.decode(&mut buf).unwrap_err().is_data());
+
+    let mut codec = Codec::<Value>::new().max_frame_len(4);
+    let mut buf = BytesMut::from(&b"\x43abc"[..]);
+    assert!(codec.decode(&mut buf).unwrap().is_some());
+
+    let mut out = BytesMut::new();
+    codec.encode("abc", &mut out).unwrap();
+    assert!(codec.encode("abcd", &mut out).unwrap_err().is_data());
+    assert_eq!(&out[..], b"\x63abc");
+}
+
+#[test]
+fn test_roundtrip() {
+    let mut codec = Codec::<Vec<String>>::new();
+    let mut buf = BytesMut::new();
+    codec.encode(vec!["a"], &mut buf).unwrap();
+    codec.encode(&["b", "c"], &mut buf).unwrap();
+    assert_eq!(codec.decode(&mut buf).unwrap(), Some(vec!["a".to_owned()]));
+    assert_eq!(
+        codec.decode(&mut buf).unwrap(),
+        Some(vec!["b".to_owned(), "c".to_owned()])
+    );
+    assert_eq!(codec.decode(&mut buf).unwrap(), None);
+}
@@ -0,0 +1,121 @@
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_bytes;
+extern crate serde_cbor;
+
+use std::collections::BTreeMap;
+
+use serde_bytes::ByteBuf;
+use serde_cbor::cddl::Schema;
+use serde_cbor::{from_slice, to_vec, ObjectKey, Value};
+
+#[allow(dead_code)]
+mod cose {
+    include!("codegen/cose.rs");
+}
+
+use cose::*;
+
+fn schema() -> Schema {
+    Schema::parse(include_str!("codegen/cose.cddl")).unwrap()
+}
+
+#[test]
+fn test_generated_code() {
+    let code = schema().to_rust().unwrap();
+    assert_eq!(code, include_str!("codegen/cose.rs"));
+}
+
+#[test]
+fn test_integer_keys() {
+    let mut extra = BTreeMap::new();
+    extra.insert(ObjectKey::String("x".to_owned()), Value::Bool(true));
+    let headers = HeaderMap {
+        alg: Some(HeaderMapAlg::Int(-7)),
+        crit: None,
+        content_type: None,
+        kid: Some(ByteBuf::from(vec![1, 2])),
+        partial_iv: None,
+        extra,
+    };
+    let bytes = to_vec(&headers).unwrap();
+    // {1: -7, 4: h'0102', "x": true}
+    assert_eq!(bytes, b"\xa3\x01\x26\x04\x42\x01\x02\x61x\xf5");
+    assert_eq!(from_slice::<HeaderMap>(&bytes).unwrap(), headers);
+    schema().validate_slice(&bytes, "header_map").unwrap();
+
+    assert!(from_slice::<HeaderMap>(b"\xa2\x01\x26\x01\x26").is_err());
+}
+
+#[test]
+fn test_roundtrip() {
+    let message = CoseSign1(
+        ByteBuf::from(vec![0xa0]),
+        HeaderMap {
+            alg: None,
+            crit: Some(HeaderMapCrit(vec![Label::Int(1), Label::Tstr("foo".to_owned())])),
+            content_type: Some(HeaderMapContentType::Tstr("text/plain".to_owned())),
+            kid: None,
+            partial_iv: None,
+            extra: BTreeMap::new(),
+        },
+        None,
+        ByteBuf::from(vec![0; 4]),
+    );
+    let bytes = to_vec(&message).unwrap();
+    schema().validate_slice(&bytes, "COSE_Sign1").unwrap();
+    assert_eq!(from_slice::<CoseSign1>(&bytes).unwrap(), message);
+
+    let key = CoseKey {
+        kty: KeyType::Ec2,
+        key_ops: Some(vec![KeyOp::Sign, KeyOp::Verify]),
+        base_iv: None,
+    };
+    let bytes = to_vec(&key).unwrap();
+    // {"kty": "EC2", "key-ops": [1, 2]}
+    assert_eq!(bytes, b"\xa2\x63kty\x63EC2\x67key-ops\x82\x01\x02");
+    schema().validate_slice(&bytes, "COSE_Key").unwrap();
+    assert_eq!(from_slice::<CoseKey>(&bytes).unwrap(), key);
+
+    assert!(from_slice::<KeyOp>(b"\x05").is_err());
+    assert!(from_slice::<CoseKey>(b"\xa2\x63kty\x63EC2\x61z\x00").is_err());
+
+    let point: Point = Some(Point2(1, -1));
+    let bytes = to_vec(&point).unwrap();
+    schema().validate_slice(&bytes, "point").unwrap();
+    assert_eq!(from_slice::<Point>(&bytes).unwrap(), point);
+    let level: Level = 200;
+    schema()
+        .validate_slice(&to_vec(&level).unwrap(), "level")
+        .unwrap();
+}
+
+#[test]
+fn test_occurrence() {
+    // `crit` holds at least one label.
+    let headers = HeaderMap {
+        alg: None,
+        crit: Some(HeaderMapCrit(vec![])),
+        content_type: None,
+        kid: None,
+        partial_iv: None,
+        extra: BTreeMap::new(),
+    };
+    assert!(to_vec(&headers).is_err());
+    // {2: []}
+    let error = from_slice::<HeaderMap>(b"\xa1\x02\x80").unwrap_err();
+    assert!(error.is_data());
+    assert!(from_slice::<HeaderMap>(b"\xa1\x02\x81\x01").is_ok());
+}
+
+#[test]
+fn test_unsupported() {
+    let schema = Schema::parse("tagged = #6.32(tstr)").unwrap();
+    let error = schema.to_rust().unwrap_err();
+    assert_eq!(error.rule(), "tagged");
+
+    let schema = Schema::parse("point = [x: int, ? y: int]").unwrap();
+    let error = schema.to_rust().unwrap_err();
+    assert_eq!(error.rule(), "point");
+}
@@ -0,0 +1,290 @@
+// Generated from CDDL by serde_cbor. Do not edit.
+
+/// Generated from the `COSE_Sign1` rule.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CoseSign1(
+    /// `protected`
+    pub ::serde_bytes::ByteBuf,
+    /// `unprotected`
+    pub HeaderMap,
+    /// `payload`
+    pub Option<::serde_bytes::ByteBuf>,
+    /// `signature`
+    pub ::serde_bytes::ByteBuf,
+);
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum HeaderMapAlg {
+    Int(i64),
+    Tstr(String),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct HeaderMapCrit(
+    /// Must hold at least 1 item.
+    pub Vec<Label>,
+);
+
+impl ::serde::Serialize for HeaderMapCrit {
+    fn serialize<S>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error>
+    where
+        S: ::serde::Serializer,
+    {
+        let len = self.0.len();
+        if len < 1 {
+            return Err(::serde::ser::Error::custom(format_args!(
+                "invalid length {}, expected at least 1 item",
+                len
+            )));
+        }
+        ::serde::Serialize::serialize(&self.0, serializer)
+    }
+}
+
+impl<'de> ::serde::Deserialize<'de> for HeaderMapCrit {
+    fn deserialize<D>(deserializer: D) -> ::std::result::Result<HeaderMapCrit, D::Error>
+    where
+        D: ::serde::Deserializer<'de>,
+    {
+        let items: Vec<Label> = ::serde::Deserialize::deserialize(deserializer)?;
+        let len = items.len();
+        if len < 1 {
+            return Err(::serde::de::Error::invalid_length(len, &"at least 1 item"));
+        }
+        Ok(HeaderMapCrit(items))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum HeaderMapContentType {
+    Tstr(String),
+    Uint(u64),
+}
+
+/// Generated from the `header_map` rule.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HeaderMap {
+    /// Map key `1`.
+    pub alg: Option<HeaderMapAlg>,
+    /// Map key `2`.
+    pub crit: Option<HeaderMapCrit>,
+    /// Map key `3`.
+    pub content_type: Option<HeaderMapContentType>,
+    /// Map key `4`.
+    pub kid: Option<::serde_bytes::ByteBuf>,
+    /// Map key `6`.
+    pub partial_iv: Option<::serde_bytes::ByteBuf>,
+    /// All other map entries.
+    pub extra: ::std::collections::BTreeMap<::serde_cbor::ObjectKey, Values>,
+}
+
+impl ::serde::Serialize for HeaderMap {
+    fn serialize<S>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error>
+    where
+        S: ::serde::Serializer,
+    {
+        use ::serde::ser::SerializeMap;
+
+        let mut map = serializer.serialize_map(Some(self.alg.is_some() as usize + self.crit.is_some() as usize + self.content_type.is_some() as usize + self.kid.is_some() as usize + self.partial_iv.is_some() as usize + self.extra.len()))?;
+        if let Some(ref value) = self.alg {
+            map.serialize_entry(&1i64, value)?;
+        }
+        if let Some(ref value) = self.crit {
+            map.serialize_entry(&2i64, value)?;
+        }
+        if let Some(ref value) = self.content_type {
+            map.serialize_entry(&3i64, value)?;
+        }
+        if let Some(ref value) = self.kid {
+            map.serialize_entry(&4i64, value)?;
+        }
+        if let Some(ref value) = self.partial_iv {
+            map.serialize_entry(&6i64, value)?;
+        }
+        for (key, value) in &self.extra {
+            map.serialize_entry(key, value)?;
+        }
+        map.end()
+    }
+}
+
+impl<'de> ::serde::Deserialize<'de> for HeaderMap {
+    fn deserialize<D>(deserializer: D) -> ::std::result::Result<HeaderMap, D::Error>
+    where
+        D: ::serde::Deserializer<'de>,
+    {
+        struct Visitor;
+
+        impl<'de> ::serde::de::Visitor<'de> for Visitor {
+            type Value = HeaderMap;
+
+            fn expecting(&self, formatter: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+                formatter.write_str("a HeaderMap map")
+            }
+
+            fn visit_map<A>(self, mut map: A) -> ::std::result::Result<HeaderMap, A::Error>
+            where
+                A: ::serde::de::MapAccess<'de>,
+            {
+                let mut alg = None;
+                let mut crit = None;
+                let mut content_type = None;
+                let mut kid = None;
+                let mut partial_iv = None;
+                let mut extra = ::std::collections::BTreeMap::new();
+                while let Some(key) = map.next_key::<::serde_cbor::ObjectKey>()? {
+                    match key {
+                        ::serde_cbor::ObjectKey::Integer(1) => {
+                            if alg.is_some() {
+                                return Err(::serde::de::Error::duplicate_field("1"));
+                            }
+                            alg = Some(map.next_value()?);
+                        }
+                        ::serde_cbor::ObjectKey::Integer(2) => {
+                            if crit.is_some() {
+                                return Err(::serde::de::Error::duplicate_field("2"));
+                            }
+                            crit = Some(map.next_value()?);
+                        }
+                        ::serde_cbor::ObjectKey::Integer(3) => {
+                            if content_type.is_some() {
+                                return Err(::serde::de::Error::duplicate_field("3"));
+                            }
+                            content_type = Some(map.next_value()?);
+                        }
+                        ::serde_cbor::ObjectKey::Integer(4) => {
+                            if kid.is_some() {
+                                return Err(::serde::de::Error::duplicate_field("4"));
+                            }
+                            kid = Some(map.next_value()?);
+                        }
+                        ::serde_cbor::ObjectKey::Integer(6) => {
+                            if partial_iv.is_some() {
+                                return Err(::serde::de::Error::duplicate_field("6"));
+                            }
+                            partial_iv = Some(map.next_value()?);
+                        }
+                        key => {
+                            extra.insert(key, map.next_value()?);
+                        }
+                    }
+                }
+                Ok(HeaderMap {
+                    alg,
+                    crit,
+                    content_type,
+                    kid,
+                    partial_iv,
+                    extra,
+                })
+            }
+        }
+
+        deserializer.deserialize_map(Visitor)
+    }
+}
+
+/// Generated from the `label` rule.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Label {
+    Int(i64),
+    Tstr(String),
+}
+
+/// Generated from the `values` rule.
+pub type Values = ::serde_cbor::Value;
+
+/// Generated from the `alg` rule.
+pub const ALG: i64 = 1;
+
+/// Generated from the `crit` rule.
+pub const CRIT: i64 = 2;
+
+/// Generated from the `content-type` rule.
+pub const CONTENT_TYPE: i64 = 3;
+
+/// Generated from the `kid` rule.
+pub const KID: i64 = 4;
+
+/// Generated from the `partial-iv` rule.
+pub const PARTIAL_IV: i64 = 6;
+
+/// Generated from the `COSE_Key` rule.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CoseKey {
+    pub kty: KeyType,
+    #[serde(rename = "key-ops", default, skip_serializing_if = "Option::is_none")]
+    pub key_ops: Option<Vec<KeyOp>>,
+    #[serde(rename = "base-iv", default, skip_serializing_if = "Option::is_none")]
+    pub base_iv: Option<::serde_bytes::ByteBuf>,
+}
+
+/// Generated from the `key-type` rule.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum KeyType {
+    #[serde(rename = "OKP")]
+    Okp,
+    #[serde(rename = "EC2")]
+    Ec2,
+    #[serde(rename = "Symmetric")]
+    Symmetric,
+}
+
+/// Generated from the `key-op` rule.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum KeyOp {
+    Sign,
+    Verify,
+    Encrypt,
+    Decrypt,
+}
+
+impl ::serde::Serialize for KeyOp {
+    fn serialize<S>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error>
+    where
+        S: ::serde::Serializer,
+    {
+        serializer.serialize_i64(match *self {
+            KeyOp::Sign => 1,
+            KeyOp::Verify => 2,
+            KeyOp::Encrypt => 3,
+            KeyOp::Decrypt => 4,
+        })
+    }
+}
+
+impl<'de> ::serde::Deserialize<'de> for KeyOp {
+    fn deserialize<D>(deserializer: D) -> ::std::result::Result<KeyOp, D::Error>
+    where
+        D: ::serde::Deserializer<'de>,
+    {
+        match <i64 as ::serde::Deserialize>::deserialize(deserializer)? {
+            1 => Ok(KeyOp::Sign),
+            2 => Ok(KeyOp::Verify),
+            3 => Ok(KeyOp::Encrypt),
+            4 => Ok(KeyOp::Decrypt),
+            value => Err(::serde::de::Error::invalid_value(
+                ::serde::de::Unexpected::Signed(value),
+                &"one of 1, 2, 3, 4",
+            )),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Point2(
+    /// `x`
+    pub i64,
+    /// `y`
+    pub i64,
+);
+
+/// Generated from the `point` rule.
+pub type Point = Option<Point2>;
+
+/// Generated from the `level` rule.
+pub type Level = u8;
@@ -1,10 +1,12 @@
 extern crate serde_cbor;
 extern crate serde_bytes;
+extern crate serde;
 
 use serde_bytes::ByteBuf;
 use std::collections::BTreeMap;
 
 use serde_cbor::{to_vec, Value, ObjectKey, error, de, Deserializer, from_reader};
+use serde_cbor::value::FloatWidth;
 
 #[test]
 fn test_string1() {
@@ -116,10 +118,25 @@ fn test_indefinite_byte_string() {
 #[test]
 fn test_float() {
     let value: error::Result<Value> = de::from_slice(b"\xfa\x47\xc3\x50\x00");
-    assert_eq!(value.unwrap(), Value::F64(100000.0));
+    assert_eq!(value.unwrap(), Value::F32(100000.0));
 }
 
 
+#[test]
+fn test_float_width() {
+    let value: Value = de::from_slice(b"\xf9\x3c\x00").unwrap();
+    assert_eq!(value.float_width(), Some(FloatWidth::Half));
+    let value: Value = de::from_slice(b"\xfa\x3f\x80\x00\x00").unwrap();
+    assert_eq!(value.float_width(), Some(FloatWidth::Single));
+    let value: Value = de::from_slice(b"\xfb\x3f\xf0\x00\x00\x00\x00\x00\x00").unwrap();
+    assert_eq!(value.float_width(), Some(FloatWidth::Double));
+    assert_eq!(value.as_f64(), Some(1.0));
+    // Tags in front of the float do not hide its width.
+    let value: Value = de::from_slice(b"\xc1\xf9\x3c\x00").unwrap();
+    assert_eq!(value, Value::F16(1.0));
+    assert_eq!(Value::U64(1).float_width(), None);
+}
+
 #[test]
 fn test_self_describing() {
     let value: error::Result<Value> = de::from_slice(&[0xd9, 0xd9, 0xf7, 0x66, 0x66, 0x6f, 0x6f, 0x62, 0x61, 0x72]);
@@ -130,13 +147,13 @@ fn test_self_describing() {
 #[test]
 fn test_f16() {
     let mut x: Value = de::from_slice(&[0xf9, 0x41, 0x00]).unwrap();
-    assert_eq!(x, Value::F64(2.5));
+    assert_eq!(x, Value::F16(2.5));
     x = de::from_slice(&[0xf9, 0x41, 0x90]).unwrap();
-    assert_eq!(x, Value::F64(2.78125));
+    assert_eq!(x, Value::F16(2.78125));
     x = de::from_slice(&[0xf9, 0x50, 0x90]).unwrap();
-    assert_eq!(x, Value::F64(36.5));
+    assert_eq!(x, Value::F16(36.5));
     x = de::from_slice(&[0xf9, 0xd0, 0x90]).unwrap();
-    assert_eq!(x, Value::F64(-36.5));
+    assert_eq!(x, Value::F16(-36.5));
 }
 
 #[test]
@@ -151,7 +168,7 @@ fn test_crazy_list() {
         Value::Null,
         Value::Null,
         Value::F64(23456543.5),
-        Value::F64(::std::f64::INFINITY)]);
+        Value::F16(::std::f32::INFINITY)]);
 }
 
 #[test]
@@ -242,3 +259,461 @@ fn test_large_bytes() {
     let actual = from_reader(&v[..]).unwrap();
     assert_eq!(expected, actual);
 }
+
+#[test]
+fn test_slice_with_scratch() {
+    let mut scratch = [0; 32];
+    let value: &[u8] = de::from_slice_with_scratch(b"\x43\x01\x02\x03", &mut scratch).unwrap();
+    assert_eq!(value, b"\x01\x02\x03");
+
+    let slice = b"\x7f\x65Mary \x64Had \x62a \x67Little \x64Lamb\xff";
+    let value: Value = de::from_slice_with_scratch(slice, &mut scratch[..]).unwrap();
+    assert_eq!(value, Value::String("Mary Had a Little Lamb".to_owned()));
+
+    let slice = b"\x5f\x42\x01\x23\x42\x45\x67\xff";
+    let value: ByteBuf = de::from_slice_with_scratch(slice, &mut scratch).unwrap();
+    assert_eq!(value, ByteBuf::from(b"\x01#Eg".to_vec()));
+
+    // The scratch buffer is only needed for indefinite length strings.
+    let value: Value = de::from_slice_with_scratch(b"\x82\x01\x61a", &mut []).unwrap();
+    assert_eq!(value, Value::Array(vec![Value::U64(1), Value::String("a".to_owned())]));
+
+    let error = de::from_slice_with_scratch::<Value>(slice, &mut scratch[..3]).unwrap_err();
+    assert!(error.is_buffer_too_small());
+    assert_eq!(error.required_size(), Some(4));
+}
+
+#[test]
+fn test_string_chunks() {
+    let slice = b"\x5f\x42\x01\x02\x40\x41\x03\xff\x01";
+    let mut de = Deserializer::from_slice(slice);
+    let chunks = de.byte_string_chunks().unwrap().collect::<Result<Vec<_>, _>>().unwrap();
+    // Empty chunks are skipped.
+    assert_eq!(chunks, vec![b"\x01\x02".to_vec(), b"\x03".to_vec()]);
+    assert_eq!(de.into_iter::<Value>().next().unwrap().unwrap(), Value::U64(1));
+
+    // Tags are skipped and definite strings are read as a single chunk.
+    let mut de = Deserializer::from_slice(b"\xd8\x18\x66foobar");
+    let mut chunks = de.text_string_chunks().unwrap();
+    assert_eq!(chunks.next_chunk().unwrap(), Some(&b"foobar"[..]));
+    assert_eq!(chunks.next_chunk().unwrap(), None);
+
+    let mut de = Deserializer::from_slice(b"\x43abc");
+    assert!(de.text_string_chunks().err().unwrap().is_data());
+
+    // Chunks of an indefinite string have to be of the same type.
+    let mut de = Deserializer::from_slice(b"\x7f\x61a\x41b\xff");
+    let mut chunks = de.text_string_chunks().unwrap();
+    assert_eq!(chunks.next_chunk().unwrap(), Some(&b"a"[..]));
+    assert!(chunks.next_chunk().unwrap_err().is_syntax());
+    assert_eq!(chunks.next_chunk().unwrap(), None);
+
+    // Every chunk of a text string has to be UTF-8 on its own.
+    let mut de = Deserializer::from_slice(b"\x7f\x61\xc3\x61\xa9\xff");
+    let error = de.copy_text_string(Vec::new()).unwrap_err();
+    assert!(error.is_syntax());
+}
+
+#[test]
+fn test_copy_large_string() {
+    use serde_cbor::ser::Serializer;
+
+    // A multi-byte character straddles the 16 KiB pieces the reader splits the string into.
+    let text = "é".repeat(20 * 1024);
+    let v = to_vec(&text).unwrap();
+    let mut out = Vec::new();
+    let len = Deserializer::from_reader(&v[..]).copy_text_string(&mut out).unwrap();
+    assert_eq!(len, text.len() as u64);
+    assert_eq!(out, text.as_bytes());
+
+    let blob = (0..100_000).map(|i| (i * 7) as u8).collect::<Vec<_>>();
+    let mut ser = Serializer::new(Vec::new());
+    ser.serialize_bytes_chunked(&blob[..], 4096).unwrap();
+    let v = ser.into_inner();
+    let mut out = Vec::new();
+    let mut de = Deserializer::from_reader(&v[..]);
+    de.copy_byte_string(&mut out).unwrap();
+    de.end().unwrap();
+    assert_eq!(out, blob);
+}
+
+#[test]
+fn test_decoder() {
+    use serde_cbor::de::{Decoder, Event};
+
+    let slice = b"\xbf\x39\x01\xf3\x5f\x41a\x41b\xff\x9f\xf5\xf8\x20\xf9\x3e\x00\xff\x00\xff";
+    let mut decoder = Decoder::from_reader(&slice[..]);
+    let mut events = Vec::new();
+    while let Some((offset, event)) = decoder.next_event().unwrap() {
+        events.push(format!("{} {:?}", offset, event));
+    }
+    assert_eq!(
+        events,
+        vec![
+            "0 MapStart(None)",
+            "1 NInt(499)",
+            "4 BytesStart(None)",
+            "5 Bytes([97])",
+            "7 Bytes([98])",
+            "9 Break",
+            "10 ArrayStart(None)",
+            "11 Simple(21)",
+            "12 Simple(32)",
+            "14 Float(1.5, Half)",
+            "17 Break",
+            "18 UInt(0)",
+            "19 Break",
+        ]
+    );
+
+    // Chunks have to be strings of the same type and definite length.
+    let mut decoder = Decoder::from_slice(b"\x7f\x61a\x41b\xff");
+    assert_eq!(decoder.next_event().unwrap(), Some((0, Event::TextStart(None))));
+    assert_eq!(decoder.next_event().unwrap(), Some((1, Event::Text("a"))));
+    assert!(decoder.next_event().unwrap_err().is_syntax());
+
+    for slice in &[&b"\x1c"[..], b"\xf8\x18", b"\x62\xc3\x28"] {
+        assert!(Decoder::from_slice(slice).next_event().unwrap_err().is_syntax());
+    }
+
+    fn last_error(slice: &[u8]) -> error::Error {
+        let mut decoder = Decoder::from_slice(slice);
+        loop {
+            match decoder.next_event() {
+                Ok(Some(_)) => {}
+                Ok(None) => panic!("{:?} was accepted", slice),
+                Err(error) => return error,
+            }
+        }
+    }
+
+    // Breaks have to end a container of indefinite length, and containers have to be complete.
+    let slices = [
+        &b"\xff"[..],
+        b"\x81\xff",
+        b"\x9f\xc1\xff",
+        b"\xbf\x01\xff",
+        b"\x9f\x81\x01\xff\xff",
+    ];
+    for slice in &slices {
+        assert!(last_error(slice).is_syntax(), "{:?}", slice);
+    }
+    for slice in &[&b"\x82\x01"[..], b"\x9f", b"\xc1", b"\xa1\x01", b"\x5f\x41a"] {
+        assert!(last_error(slice).is_eof(), "{:?}", slice);
+    }
+    let mut decoder = Decoder::from_slice(b"\x9f\x81\x01\xff\x02");
+    while decoder.next_event().unwrap().is_some() {}
+}
+
+#[test]
+fn test_decoder_long_strings() {
+    use serde_cbor::de::{Decoder, Event};
+
+    // A character is split between the first two pieces.
+    let text = format!("a{}", "\u{e9}".repeat(10000));
+    let mut input = to_vec(&text).unwrap();
+    input.extend_from_slice(b"\x5f");
+    input.extend(to_vec(&ByteBuf::from(vec![1; 20000])).unwrap());
+    input.push(0xff);
+
+    let mut decoder = Decoder::from_reader(&input[..]);
+    assert_eq!(decoder.next_event().unwrap(), Some((0, Event::TextStart(Some(20001)))));
+    let mut pieces = Vec::new();
+    while let Some((_, Event::Chunk(piece))) = decoder.next_event().unwrap() {
+        assert!(piece.len() <= 16 * 1024);
+        pieces.extend_from_slice(piece);
+        if pieces.len() == 20001 {
+            break;
+        }
+    }
+    assert_eq!(pieces, text.as_bytes());
+
+    // Long chunks of indefinite strings are split as well.
+    let event = decoder.next_event().unwrap().map(|(_, event)| event);
+    assert_eq!(event, Some(Event::BytesStart(None)));
+    let event = decoder.next_event().unwrap().map(|(_, event)| event);
+    assert_eq!(event, Some(Event::BytesStart(Some(20000))));
+    let mut len = 0;
+    while len < 20000 {
+        match decoder.next_event().unwrap() {
+            Some((_, Event::Chunk(piece))) => len += piece.len(),
+            event => panic!("unexpected {:?}", event),
+        }
+    }
+    assert_eq!(decoder.next_event().unwrap().map(|(_, event)| event), Some(Event::Break));
+    assert_eq!(decoder.next_event().unwrap(), None);
+
+    // Invalid UTF-8 is found across pieces.
+    let mut input = b"\x79\x40\x01".to_vec();
+    input.extend(vec![b'a'; 16 * 1024 - 1]);
+    input.extend_from_slice(b"\xc3\x28");
+    let mut decoder = Decoder::from_slice(&input);
+    decoder.next_event().unwrap();
+    decoder.next_event().unwrap();
+    assert!(decoder.next_event().unwrap_err().is_syntax());
+}
+
+#[test]
+fn test_buf_reader() {
+    use serde_cbor::de::from_buf_reader;
+    use std::io::{BufRead, BufReader};
+
+    let value = vec![
+        "a".to_owned(),
+        "x".repeat(100),
+        "é".repeat(10),
+        String::new(),
+    ];
+    let mut v = to_vec(&value).unwrap();
+    v.extend_from_slice(b"\x7f\x63foo\x63bar\xff\x01");
+    // Strings straddle the tiny buffer and exceed it.
+    for &capacity in &[1, 7, 64, 1024] {
+        let mut reader = BufReader::with_capacity(capacity, &v[..]);
+        let actual = Deserializer::from_buf_reader(&mut reader)
+            .into_iter::<Vec<String>>()
+            .next()
+            .unwrap()
+            .unwrap();
+        assert_eq!(actual, value);
+        let actual = Deserializer::from_buf_reader(&mut reader)
+            .into_iter::<String>()
+            .next()
+            .unwrap()
+            .unwrap();
+        assert_eq!(actual, "foobar");
+        // Input after the values is left to the reader.
+        assert_eq!(reader.fill_buf().unwrap(), b"\x01");
+        let actual: u8 = from_buf_reader(reader).unwrap();
+        assert_eq!(actual, 1);
+    }
+
+    let de = Deserializer::from_buf_reader(BufReader::with_capacity(4, &b"\x01\x66foob"[..]));
+    let mut it = de.into_iter::<Value>();
+    assert_eq!(it.next().unwrap().unwrap(), Value::U64(1));
+    let error = it.next().unwrap().unwrap_err();
+    assert!(error.is_eof());
+    assert_eq!(error.offset(), 6);
+
+    // Strings within the buffer are only lent to the visitor, so they cannot be borrowed.
+    let mut de = Deserializer::from_buf_reader(BufReader::new(&b"\x63foo\x63foo"[..]));
+    let error = <&str as serde::Deserialize>::deserialize(&mut de).unwrap_err();
+    assert!(error.is_data());
+    let actual: String = serde::Deserialize::deserialize(&mut de).unwrap();
+    assert_eq!(actual, "foo");
+}
+
+#[test]
+fn test_ignored_any() {
+    use serde::de::IgnoredAny;
+    use serde_cbor::de::from_buf_reader;
+    use std::io::BufReader;
+
+    // Ignored strings are not checked to be UTF-8.
+    let valid: &[&[u8]] = &[
+        b"\x1b\x00\x00\x00\x00\x00\x00\x00\x01",
+        b"\x62\xff\xfe",
+        b"\x7f\x61a\x60\xff",
+        b"\x5f\x41\x00\xff",
+        b"\xbf\x01\x9f\xff\xc1\xa1\x00\x00\x02\xff",
+        b"\xf8\x20",
+        b"\xf0",
+    ];
+    for input in valid {
+        de::from_slice::<IgnoredAny>(input).unwrap();
+        from_reader::<IgnoredAny, _>(*input).unwrap();
+    }
+
+    let invalid: &[(&[u8], u64)] = &[
+        (b"\x1c", 1),
+        (b"\x1f", 1),
+        (b"\xdf\x00", 1),
+        (b"\xff", 1),
+        (b"\xf8\x10", 2),
+        (b"\x82\x01", 2),
+        (b"\x43\x00", 2),
+        (b"\x7f\x41\x00\xff", 2),
+        (b"\x5f\x5f\xff\xff", 2),
+        (b"\xa1\x00\xff", 3),
+    ];
+    for &(input, offset) in invalid {
+        let error = de::from_slice::<IgnoredAny>(input).unwrap_err();
+        assert_eq!(error.offset(), offset, "{:?}", input);
+    }
+
+    let deep = [0x81; 200];
+    let error = de::from_slice::<IgnoredAny>(&deep).unwrap_err();
+    assert_eq!(error.to_string(), "recursion limit exceeded at offset 128");
+
+    // Skipping a large string leaves the reader at the next item.
+    let mut v = to_vec(&"x".repeat(100_000)).unwrap();
+    v.insert(0, 0x82);
+    v.push(0x07);
+    let (_, actual) = de::from_slice::<(IgnoredAny, u8)>(&v).unwrap();
+    assert_eq!(actual, 7);
+    let (_, actual) = from_reader::<(IgnoredAny, u8), _>(&v[..]).unwrap();
+    assert_eq!(actual, 7);
+    let (_, actual) =
+        from_buf_reader::<(IgnoredAny, u8), _>(BufReader::with_capacity(64, &v[..])).unwrap();
+    assert_eq!(actual, 7);
+}
+
+#[test]
+fn test_path() {
+    use serde::Deserialize;
+    use serde_cbor::de::{from_slice_at, range_at, Selector};
+
+    // {_ [1]: 0, 1("a"): 1, -3: 2, (_ "ch" "unk"): 3, h'6d657461': 4,
+    //    "meta": 1([_ 10, "x", {"k": 1}, {0: "zero", 7: "seven"}])}
+    let v = b"\xbf\x81\x01\x00\xc1\x61a\x01\x22\x02\x7f\x62ch\x63unk\xff\x03\x44meta\x04\
+        \x64meta\xc1\x9f\x0a\x61x\xa1\x61k\x01\xa2\x00\x64zero\x07\x65seven\xff\xff";
+
+    let path = [Selector::Key("meta"), Selector::Index(3), Selector::Int(7)];
+    let value: Option<String> = from_slice_at(v, &path).unwrap();
+    assert_eq!(value, Some("seven".to_owned()));
+    let range = range_at(v, &path).unwrap().unwrap();
+    assert_eq!(&v[range], b"\x65seven");
+
+    let value: Option<u8> = from_slice_at(v, &[Selector::Key("a")]).unwrap();
+    assert_eq!(value, Some(1));
+    let value: Option<u8> = from_slice_at(v, &[Selector::Int(-3)]).unwrap();
+    assert_eq!(value, Some(2));
+    // Keys of indefinite length are compared chunk by chunk.
+    let value: Option<u8> = from_slice_at(v, &[Selector::Key("chunk")]).unwrap();
+    assert_eq!(value, Some(3));
+    let value: Option<Value> = from_slice_at(v, &[Selector::Key("meta"), Selector::Index(2)]).unwrap();
+    let mut map = BTreeMap::new();
+    map.insert(ObjectKey::String("k".to_owned()), Value::U64(1));
+    assert_eq!(value, Some(Value::Object(map)));
+    let value: Option<serde::de::IgnoredAny> = from_slice_at(v, &[]).unwrap();
+    assert!(value.is_some());
+
+    let missing: &[&[Selector]] = &[
+        &[Selector::Int(3)],
+        &[Selector::Key("met")],
+        &[Selector::Key("chun")],
+        &[Selector::Key("chunks")],
+        &[Selector::Key("cunk")],
+        &[Selector::Index(0)],
+        &[Selector::Key("meta"), Selector::Index(4)],
+        &[Selector::Key("meta"), Selector::Key("k")],
+        &[Selector::Key("meta"), Selector::Index(1), Selector::Index(0)],
+        &[Selector::Key("meta"), Selector::Index(3), Selector::Int(1)],
+    ];
+    for path in missing {
+        assert_eq!(from_slice_at::<Value>(v, path).unwrap(), None, "{:?}", path);
+        assert_eq!(range_at(v, path).unwrap(), None, "{:?}", path);
+    }
+
+    // Readers are supported by the deserializer.
+    let mut de = Deserializer::from_reader(&v[..]);
+    assert!(de.find_path(&path).unwrap());
+    assert_eq!(String::deserialize(&mut de).unwrap(), "seven");
+
+    // Malformed items before the one looked for are errors.
+    let error = from_slice_at::<u8>(b"\xa2\x00\x1c\x01\x01", &[Selector::Int(1)]).unwrap_err();
+    assert_eq!(error.offset(), 3);
+    let error = range_at(b"\x82\x00\x9f\x01", &[Selector::Index(1)]).unwrap_err();
+    assert!(error.is_eof());
+}
+
+#[test]
+fn test_error_context() {
+    use serde_cbor::error::{ErrorKind, PathSegment};
+
+    // {"a": {"b": 1}, "c": {"d": "x"}}
+    let v = b"\xa2\x61a\xa1\x61b\x01\x61c\xa1\x61d\x61x";
+    // Keys are looked up in a slice, but copied from a reader.
+    let errors = [
+        de::from_slice::<BTreeMap<String, BTreeMap<String, u32>>>(v).unwrap_err(),
+        from_reader::<BTreeMap<String, BTreeMap<String, u32>>, _>(&v[..]).unwrap_err(),
+    ];
+    for error in &errors {
+        assert_eq!(error.kind(), ErrorKind::Message);
+        assert_eq!(error.offset(), 12);
+        assert_eq!(
+            error.path(),
+            &[PathSegment::Key("c".to_owned()), PathSegment::Key("d".to_owned())]
+        );
+        assert_eq!(
+            error.to_string(),
+            "invalid type: string \"x\", expected u32 at offset 12 in c.d"
+        );
+    }
+
+    // {"a": {(_ "b" "c"): 1, "d": "x"}}, where the indefinite key is copied out of a slice.
+    let v = b"\xa1\x61a\xa2\x7f\x61b\x61c\xff\x01\x61d\x61x";
+    let error = de::from_slice::<BTreeMap<String, BTreeMap<String, u32>>>(v).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "invalid type: string \"x\", expected u32 at offset 13 in a.d"
+    );
+    // {"a": {(_ "b" "c"): "x"}}
+    let v = b"\xa1\x61a\xa1\x7f\x61b\x61c\xff\x61x";
+    let error = de::from_slice::<BTreeMap<String, BTreeMap<String, u32>>>(v).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "invalid type: string \"x\", expected u32 at offset 10 in a.bc"
+    );
+
+    // {_ -2: [_ 0, 256, true]}
+    let v = b"\xbf\x21\x9f\x00\x19\x01\x00\xf5\xff\xff";
+    let errors = [
+        de::from_slice::<BTreeMap<i64, Vec<u16>>>(v).unwrap_err(),
+        from_reader::<BTreeMap<i64, Vec<u16>>, _>(&v[..]).unwrap_err(),
+    ];
+    for error in &errors {
+        assert_eq!(error.offset(), 7);
+        assert_eq!(error.path(), &[PathSegment::Int(-2), PathSegment::Index(2)]);
+        assert_eq!(
+            error.to_string(),
+            "invalid type: boolean `true`, expected u16 at offset 7 in -2[2]"
+        );
+    }
+
+    // {h'00': "x"}
+    let error = de::from_slice::<BTreeMap<ByteBuf, u8>>(b"\xa1\x41\x00\x61x").unwrap_err();
+    assert_eq!(error.offset(), 3);
+    assert_eq!(error.path(), &[PathSegment::Other]);
+
+    let error = de::from_slice::<u32>(b"\x61x").unwrap_err();
+    assert_eq!(error.offset(), 0);
+    assert!(error.path().is_empty());
+    assert_eq!(error.to_string(), "invalid type: string \"x\", expected u32 at offset 0");
+
+    let error = de::from_slice::<Value>(b"\x82\x01").unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::EofWhileParsingValue);
+    assert_eq!(error.offset(), 2);
+    assert_eq!(error.path(), &[PathSegment::Index(1)]);
+
+    let error = de::from_slice::<Value>(b"\x62\xc3\x28").unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidUtf8);
+}
+
+#[test]
+fn test_io_error_offset() {
+    use serde::Deserialize;
+    use serde_cbor::error::ErrorKind;
+    use std::io::{self, BufReader, Read};
+
+    struct Broken;
+
+    impl Read for Broken {
+        fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+            Err(io::Error::other("broken"))
+        }
+    }
+
+    // The error is placed where the reader failed, also outside of an item.
+    let mut de = Deserializer::from_reader((&b"\x01"[..]).chain(Broken));
+    assert_eq!(u8::deserialize(&mut de).unwrap(), 1);
+    let error = de.end().unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::Io);
+    assert_eq!(error.offset(), 1);
+
+    let mut reader = BufReader::new((&b"\x01\x43ab"[..]).chain(Broken));
+    let mut de = Deserializer::from_buf_reader(&mut reader);
+    assert_eq!(u8::deserialize(&mut de).unwrap(), 1);
+    let error = ByteBuf::deserialize(&mut de).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::Io);
+    assert_eq!(error.offset(), 4);
+}
@@ -1,3 +1,4 @@
+extern crate serde;
 extern crate serde_cbor;
 
 #[macro_use]
@@ -96,3 +97,74 @@ fn test_variable_length_array() {
     let value: Vec<Foo> = from_slice(slice).unwrap();
     assert_eq!(value, [Foo::Require]);
 }
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+enum Content {
+    Unit,
+    Newtype(u8),
+    Tuple(u8, String),
+    Struct { a: u8, b: Option<u8> },
+}
+
+#[test]
+fn test_map_representation() {
+    use serde::Deserialize;
+    use serde_cbor::ser::{EnumRepresentation, SerializerBuilder};
+    use serde_cbor::Deserializer;
+
+    let values = vec![
+        Content::Unit,
+        Content::Newtype(1),
+        Content::Tuple(2, "x".to_owned()),
+        Content::Struct { a: 3, b: None },
+    ];
+    let builder = SerializerBuilder::new().enum_representation(EnumRepresentation::Map);
+    for value in &values {
+        let bytes = builder.to_vec(value).unwrap();
+        assert_eq!(&from_slice::<Content>(&bytes).unwrap(), value);
+        let bytes = builder.packed(true).to_vec(value).unwrap();
+        assert_eq!(&from_slice::<Content>(&bytes).unwrap(), value);
+    }
+
+    // {"Newtype": 1}, as definite and indefinite map
+    assert_eq!(
+        from_slice::<Content>(b"\xa1\x67Newtype\x01").unwrap(),
+        Content::Newtype(1)
+    );
+    assert_eq!(
+        from_slice::<Content>(b"\xbf\x67Newtype\x01\xff").unwrap(),
+        Content::Newtype(1)
+    );
+    // {"Unit": null}
+    assert_eq!(
+        from_slice::<Content>(b"\xa1\x64Unit\xf6").unwrap(),
+        Content::Unit
+    );
+    assert!(from_slice::<Content>(b"\xa2\x67Newtype\x01\x64Unit\xf6").is_err());
+    assert!(from_slice::<Content>(b"\xbf\x67Newtype\x01\x64Unit\xf6\xff").is_err());
+    assert!(from_slice::<Content>(b"\xa0").is_err());
+    assert!(from_slice::<Content>(b"\xbf\xff").is_err());
+
+    // Strict decoding.
+    let map = b"\xa1\x67Newtype\x01";
+    let array = b"\x82\x67Newtype\x01";
+    let mut de = Deserializer::from_slice(map).enum_representation(EnumRepresentation::Map);
+    assert_eq!(Content::deserialize(&mut de).unwrap(), Content::Newtype(1));
+    let mut de = Deserializer::from_slice(array).enum_representation(EnumRepresentation::Map);
+    assert!(Content::deserialize(&mut de).is_err());
+    let mut de = Deserializer::from_slice(map).enum_representation(EnumRepresentation::Array);
+    assert!(Content::deserialize(&mut de).is_err());
+    let mut de = Deserializer::from_slice(b"\x64Unit").enum_representation(EnumRepresentation::Map);
+    assert_eq!(Content::deserialize(&mut de).unwrap(), Content::Unit);
+}
+
+#[test]
+fn test_enum_from_value() {
+    use serde_cbor::{from_value, to_value};
+
+    let value = Content::Tuple(2, "x".to_owned());
+    assert_eq!(
+        from_value::<Content>(to_value(&value).unwrap()).unwrap(),
+        value
+    );
+}
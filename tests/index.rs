@@ -0,0 +1,130 @@
+extern crate serde;
+extern crate serde_cbor;
+
+use std::io::Cursor;
+
+use serde_cbor::de::Selector;
+use serde_cbor::index::{Index, IndexItems};
+use serde_cbor::{Deserializer, ObjectKey, Value};
+
+// 1({_ "a": [1, 2(h'00'), [_ 3]], -2: {"b": "c"}, [0]: 4, h'6b': 5})
+const DOCUMENT: &[u8] = b"\xc1\xbf\x61a\x83\x01\xc2\x41\x00\x9f\x03\xff\x21\xa1\x61b\x61c\
+    \x81\x00\x04\x41k\x05\xff";
+
+#[test]
+fn test_index() {
+    let index = Index::from_slice(DOCUMENT, 2).unwrap();
+    assert_eq!(index.depth(), 2);
+    assert_eq!(index.root().range(), 0..DOCUMENT.len() as u64);
+
+    let entries = match *index.root().items() {
+        IndexItems::Map(ref entries) => entries,
+        ref items => panic!("{:?}", items),
+    };
+    let keys: Vec<_> = entries.iter().map(|entry| entry.0.clone()).collect();
+    assert_eq!(
+        keys,
+        vec![
+            Some(ObjectKey::String("a".to_owned())),
+            Some(ObjectKey::Integer(-2)),
+            None,
+            Some(ObjectKey::Bytes(b"k".to_vec())),
+        ]
+    );
+
+    let ranges = [
+        (vec![Selector::Key("a")], 4..12),
+        (vec![Selector::Key("a"), Selector::Index(1)], 6..9),
+        (vec![Selector::Key("a"), Selector::Index(2)], 9..12),
+        (vec![Selector::Int(-2)], 13..18),
+        (vec![Selector::Int(-2), Selector::Key("b")], 16..18),
+    ];
+    for (path, range) in &ranges {
+        assert_eq!(index.get(path).unwrap().range(), range.clone(), "{:?}", path);
+    }
+    // The third level is not indexed.
+    let path = [Selector::Key("a"), Selector::Index(2), Selector::Index(0)];
+    assert_eq!(index.get(&path), None);
+    assert_eq!(index.get(&[Selector::Key("b")]), None);
+    assert_eq!(index.get(&[Selector::Index(0)]), None);
+
+    let index = Index::from_slice(DOCUMENT, 0).unwrap();
+    assert_eq!(*index.root().items(), IndexItems::None);
+}
+
+#[test]
+fn test_index_duplicate_keys() {
+    // {"b": 0, 1: 1, "a": 2, h'61': 3, "b": 4, -1: 5, true: 6}
+    let document = b"\xa7\x61b\x00\x01\x01\x61a\x02\x41a\x03\x61b\x04\x20\x05\xf5\x06";
+    let index = Index::from_slice(document, 1).unwrap();
+    let value = |path: &[Selector]| {
+        index
+            .get(path)
+            .map(|entry| document[entry.range().start as usize])
+    };
+    assert_eq!(value(&[Selector::Key("b")]), Some(0));
+    assert_eq!(value(&[Selector::Key("a")]), Some(2));
+    assert_eq!(value(&[Selector::Int(1)]), Some(1));
+    assert_eq!(value(&[Selector::Int(-1)]), Some(5));
+    assert_eq!(value(&[Selector::Key("c")]), None);
+    assert_eq!(value(&[Selector::Key("")]), None);
+    assert_eq!(value(&[Selector::Int(0)]), None);
+    assert_eq!(value(&[Selector::Int(2)]), None);
+}
+
+#[test]
+fn test_index_roundtrip() {
+    let index = Index::from_slice(DOCUMENT, 3).unwrap();
+    let stored = serde_cbor::to_vec(&index).unwrap();
+    let loaded: Index = serde_cbor::from_slice(&stored).unwrap();
+    assert_eq!(loaded, index);
+
+    // Offsets are those of the deserializer, which may be positioned in a stream.
+    let mut input = b"\x00".to_vec();
+    input.extend_from_slice(DOCUMENT);
+    let mut de = Deserializer::from_reader(&input[..]);
+    let _: u8 = serde::Deserialize::deserialize(&mut de).unwrap();
+    let shifted = Index::new(&mut de, 3).unwrap();
+    assert_eq!(shifted.root().range(), 1..input.len() as u64);
+    assert_eq!(shifted.get(&[Selector::Int(-2)]).unwrap().start(), 14);
+}
+
+#[test]
+fn test_index_decode() {
+    let index = Index::from_slice(DOCUMENT, 2).unwrap();
+    let path = [Selector::Key("a"), Selector::Index(1)];
+    let value: Option<&[u8]> = index.decode_slice(DOCUMENT, &path).unwrap();
+    assert_eq!(value, Some(&b"\x00"[..]));
+    let value: Option<Value> = index.decode_reader(Cursor::new(DOCUMENT), &[Selector::Int(-2)]).unwrap();
+    let mut map = std::collections::BTreeMap::new();
+    map.insert(ObjectKey::String("b".to_owned()), Value::String("c".to_owned()));
+    assert_eq!(value, Some(Value::Object(map)));
+    let value: Option<Value> = index.decode_reader(Cursor::new(DOCUMENT), &[Selector::Key("x")]).unwrap();
+    assert_eq!(value, None);
+
+    // Errors report offsets in the whole document.
+    let mut changed = DOCUMENT.to_vec();
+    changed[16] = 0xff;
+    let error = index.decode_slice::<Value>(&changed, &[Selector::Int(-2)]).unwrap_err();
+    assert_eq!(error.offset(), 17);
+    let error = index
+        .decode_reader::<Value, _>(Cursor::new(&changed), &[Selector::Int(-2)])
+        .unwrap_err();
+    assert_eq!(error.offset(), 17);
+    let error = index.decode_slice::<Value>(&DOCUMENT[..10], &[Selector::Int(-2)]).unwrap_err();
+    assert!(error.is_eof());
+}
+
+#[test]
+fn test_index_errors() {
+    assert!(Index::from_slice(b"", 1).unwrap_err().is_eof());
+    assert!(Index::from_slice(b"\x82\x01", 1).unwrap_err().is_eof());
+    assert!(Index::from_slice(b"\xff", 1).is_err());
+    assert!(Index::from_slice(b"\xa1\x01\xff", 1).is_err());
+    assert!(Index::from_slice(b"\x81\xc1\xff", 1).is_err());
+    assert!(Index::from_slice(b"\xa1\x62\xff\xfe\x01", 1).is_err());
+
+    // [0, [5, 1, 0, null]] ends before it starts.
+    let err = serde_cbor::from_slice::<Index>(b"\x82\x00\x84\x05\x01\x00\xf6").unwrap_err();
+    assert!(err.is_data());
+}
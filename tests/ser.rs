@@ -1,6 +1,8 @@
 extern crate serde;
 extern crate serde_bytes;
 extern crate serde_cbor;
+#[macro_use]
+extern crate serde_derive;
 
 use std::collections::BTreeMap;
 
@@ -149,3 +151,466 @@ fn test_half() {
     assert_eq!(vec, b"\xF9\x51\x50");
     assert_eq!(from_slice::<f32>(&vec[..]).unwrap(), 42.5f32);
 }
+
+#[test]
+fn test_preserve_float_width() {
+    use serde::Serialize;
+    use serde_cbor::Value;
+
+    let inputs: &[&[u8]] = &[
+        b"\xf9\x3c\x00",
+        b"\xfa\x3f\x80\x00\x00",
+        b"\xfb\x3f\xf0\x00\x00\x00\x00\x00\x00",
+        b"\xfa\x7f\xc0\x00\x01",
+        b"\x82\xf9\x7c\x00\xfb\x7f\xf0\x00\x00\x00\x00\x00\x00",
+    ];
+    for input in inputs {
+        let value: Value = from_slice(input).unwrap();
+        let mut vec = Vec::new();
+        value
+            .serialize(&mut ser::Serializer::new(&mut vec).preserve_float_width(true))
+            .unwrap();
+        assert_eq!(&vec[..], *input);
+    }
+
+    // Without the option all of them shrink to a half.
+    let value: Value = from_slice(b"\xfb\x3f\xf0\x00\x00\x00\x00\x00\x00").unwrap();
+    assert_eq!(to_vec(&value).unwrap(), b"\xf9\x3c\x00");
+
+    let mut vec = Vec::new();
+    1.0f32
+        .serialize(&mut ser::Serializer::new(&mut vec).preserve_float_width(true))
+        .unwrap();
+    assert_eq!(vec, b"\xfa\x3f\x80\x00\x00");
+}
+
+#[test]
+fn test_deterministic() {
+    use serde::Serialize;
+    use serde_cbor::{ObjectKey, Value};
+    use std::collections::HashMap;
+
+    let mut map = HashMap::new();
+    for key in &[100, -1, 10, 1000, 0] {
+        map.insert(*key, ());
+    }
+    // {0: null, 10: null, 100: null, 1000: null, -1: null}
+    let expected = b"\xa5\x00\xf6\x0a\xf6\x18\x64\xf6\x19\x03\xe8\xf6\x20\xf6";
+    assert_eq!(ser::to_vec_deterministic(&map).unwrap(), &expected[..]);
+
+    // Shorter keys sort first, regardless of their content.
+    let mut map = BTreeMap::new();
+    map.insert(ObjectKey::String("aa".to_owned()), Value::Null);
+    map.insert(ObjectKey::String("b".to_owned()), Value::Null);
+    map.insert(ObjectKey::Integer(-1), Value::F64(1.5));
+    let value = Value::Object(map);
+    let mut vec = Vec::new();
+    value
+        .serialize(
+            &mut ser::Serializer::new(&mut vec)
+                .preserve_float_width(true)
+                .deterministic(true),
+        )
+        .unwrap();
+    assert_eq!(vec, b"\xa3\x20\xf9\x3e\x00\x61b\xf6\x62aa\xf6");
+
+    // Sequences of unknown length get a definite length.
+    let mut vec = Vec::new();
+    {
+        let mut ser = ser::Serializer::new(&mut vec).deterministic(true);
+        ser.collect_seq((0..30).filter(|i| i % 10 == 0)).unwrap();
+    }
+    assert_eq!(vec, b"\x83\x00\x0a\x14");
+
+    let mut ser = ser::Serializer::new(Vec::new()).deterministic(true);
+    assert!(ser.collect_map(vec![(1, 2), (1, 3)]).is_err());
+}
+
+#[test]
+fn test_deterministic_struct() {
+    use serde::Serialize;
+
+    #[derive(Serialize)]
+    struct Entry {
+        name: &'static str,
+        id: u32,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        z: Option<u32>,
+    }
+
+    let entry = Entry {
+        name: "a",
+        id: 7,
+        z: Some(1),
+    };
+    // {"z": 1, "id": 7, "name": "a"}
+    let expected = b"\xa3\x61z\x01\x62id\x07\x64name\x61a";
+    assert_eq!(ser::to_vec_deterministic(&entry).unwrap(), &expected[..]);
+
+    let mut vec = Vec::new();
+    entry
+        .serialize(&mut ser::Serializer::packed(&mut vec).deterministic(true))
+        .unwrap();
+    assert_eq!(vec, b"\xa3\x00\x61a\x01\x07\x02\x01");
+}
+
+#[test]
+fn test_float_policy() {
+    use serde::Serialize;
+    use serde_cbor::ser::FloatPolicy;
+
+    fn encode<T: Serialize>(value: T, policy: FloatPolicy) -> Vec<u8> {
+        let mut vec = Vec::new();
+        value
+            .serialize(&mut ser::Serializer::new(&mut vec).float_policy(policy))
+            .unwrap();
+        vec
+    }
+
+    assert_eq!(encode(1.5f64, FloatPolicy::Shortest), b"\xf9\x3e\x00");
+    assert_eq!(encode(1.5f64, FloatPolicy::NoHalf), b"\xfa\x3f\xc0\x00\x00");
+    assert_eq!(
+        encode(1.5f32, FloatPolicy::Declared),
+        b"\xfa\x3f\xc0\x00\x00"
+    );
+    assert_eq!(
+        encode(f64::INFINITY, FloatPolicy::NoHalf),
+        b"\xfa\x7f\x80\x00\x00"
+    );
+    assert_eq!(
+        encode(f64::NAN, FloatPolicy::NoHalf),
+        b"\xfa\x7f\xc0\x00\x00"
+    );
+
+    // A NaN with a payload that only fits in single precision.
+    let nan = f32::from_bits(0x7fc0_0001);
+    assert_eq!(encode(nan, FloatPolicy::Shortest), b"\xf9\x7e\x00");
+    assert_eq!(
+        encode(nan, FloatPolicy::PreserveNan),
+        b"\xfa\x7f\xc0\x00\x01"
+    );
+    let nan = f64::from_bits(0xfff8_0000_2000_0000);
+    assert_eq!(
+        encode(nan, FloatPolicy::PreserveNan),
+        b"\xfa\xff\xc0\x00\x01"
+    );
+    let nan = f64::from_bits(0x7ff8_0000_0000_0001);
+    assert_eq!(
+        encode(nan, FloatPolicy::PreserveNan),
+        b"\xfb\x7f\xf8\x00\x00\x00\x00\x00\x01"
+    );
+    let nan = f64::from_bits(0x7ffc_0000_0000_0000);
+    assert_eq!(encode(nan, FloatPolicy::PreserveNan), b"\xf9\x7f\x00");
+}
+
+#[test]
+fn test_reduce_integral_floats() {
+    use serde::Serialize;
+
+    fn encode<T: Serialize>(value: T) -> Vec<u8> {
+        let mut vec = Vec::new();
+        value
+            .serialize(&mut ser::Serializer::new(&mut vec).reduce_integral_floats(true))
+            .unwrap();
+        vec
+    }
+
+    assert_eq!(encode(1.0f64), b"\x01");
+    assert_eq!(encode(-0.0f64), b"\x00");
+    assert_eq!(encode(-1.0f32), b"\x20");
+    assert_eq!(encode(1.5f64), b"\xf9\x3e\x00");
+    assert_eq!(
+        encode(18_446_744_073_709_549_568.0f64),
+        b"\x1b\xff\xff\xff\xff\xff\xff\xf8\x00"
+    );
+    assert_eq!(
+        encode(-18_446_744_073_709_551_616.0f64),
+        b"\x3b\xff\xff\xff\xff\xff\xff\xff\xff"
+    );
+    assert_eq!(
+        encode(18_446_744_073_709_551_616.0f64),
+        b"\xfa\x5f\x80\x00\x00"
+    );
+    assert_eq!(encode(f64::INFINITY), b"\xf9\x7c\x00");
+}
+
+#[test]
+fn test_serializer_builder() {
+    use serde_cbor::ser::{FloatPolicy, SerializerBuilder};
+    use std::net::Ipv4Addr;
+
+    let builder = SerializerBuilder::new().self_describe(true);
+    assert_eq!(builder.to_vec(&9).unwrap(), b"\xd9\xd9\xf7\x09");
+    assert_eq!(builder.to_vec(&9).unwrap(), ser::to_vec_sd(&9).unwrap());
+
+    let builder = SerializerBuilder::new()
+        .float_policy(FloatPolicy::Declared)
+        .reduce_integral_floats(true);
+    assert_eq!(builder.to_vec(&1.5f32).unwrap(), b"\xfa\x3f\xc0\x00\x00");
+    assert_eq!(builder.to_vec(&2.0f32).unwrap(), b"\x02");
+
+    let addr = Ipv4Addr::new(8, 8, 8, 8);
+    let builder = SerializerBuilder::new().human_readable(true);
+    assert_eq!(builder.to_vec(&addr).unwrap(), b"\x678.8.8.8");
+
+    let mut vec = Vec::new();
+    {
+        let mut serializer = SerializerBuilder::new()
+            .packed(true)
+            .self_describe(true)
+            .build(&mut vec)
+            .unwrap();
+        serializer.serialize_u64(9).unwrap();
+    }
+    assert_eq!(vec, b"\xd9\xd9\xf7\x09");
+}
+
+#[test]
+fn test_enum_representation() {
+    use serde_cbor::ser::{EnumRepresentation, SerializerBuilder};
+
+    #[derive(Serialize)]
+    enum Enum {
+        Unit,
+        Newtype(u8),
+        Tuple(u8, u8),
+        Struct { a: u8 },
+    }
+
+    let builder = SerializerBuilder::new().enum_representation(EnumRepresentation::Map);
+    assert_eq!(builder.to_vec(&Enum::Unit).unwrap(), b"\x64Unit");
+    assert_eq!(
+        builder.to_vec(&Enum::Newtype(1)).unwrap(),
+        b"\xa1\x67Newtype\x01"
+    );
+    assert_eq!(
+        builder.to_vec(&Enum::Tuple(1, 2)).unwrap(),
+        b"\xa1\x65Tuple\x82\x01\x02"
+    );
+    assert_eq!(
+        builder.to_vec(&Enum::Struct { a: 1 }).unwrap(),
+        b"\xa1\x66Struct\xa1\x61a\x01"
+    );
+    let builder = builder.packed(true);
+    assert_eq!(
+        builder.to_vec(&Enum::Tuple(1, 2)).unwrap(),
+        b"\xa1\x02\x82\x01\x02"
+    );
+
+    assert_eq!(
+        to_vec(&Enum::Tuple(1, 2)).unwrap(),
+        b"\x83\x65Tuple\x01\x02"
+    );
+}
+
+#[test]
+fn test_struct_as_array() {
+    use serde_cbor::ser::SerializerBuilder;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Reading {
+        sensor: u8,
+        value: f32,
+        label: String,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Message {
+        Ping,
+        Reading { sensor: u8, value: f32 },
+    }
+
+    let reading = Reading {
+        sensor: 3,
+        value: 1.5,
+        label: "t".to_owned(),
+    };
+    let builder = SerializerBuilder::new().struct_as_array(true);
+    let bytes = builder.to_vec(&reading).unwrap();
+    assert_eq!(bytes, b"\x83\x03\xf9\x3e\x00\x61t");
+    assert_eq!(from_slice::<Reading>(&bytes).unwrap(), reading);
+    let bytes = builder.deterministic(true).to_vec(&reading).unwrap();
+    assert_eq!(bytes, b"\x83\x03\xf9\x3e\x00\x61t");
+
+    let message = Message::Reading {
+        sensor: 1,
+        value: 2.5,
+    };
+    let bytes = builder.packed(true).to_vec(&message).unwrap();
+    assert_eq!(bytes, b"\x82\x01\x82\x01\xf9\x41\x00");
+    assert_eq!(from_slice::<Message>(&bytes).unwrap(), message);
+
+    // Nested structs are written as they come, into a fixed buffer as well.
+    #[derive(Serialize)]
+    struct Batch {
+        id: u8,
+        first: Reading,
+    }
+
+    let batch = Batch {
+        id: 7,
+        first: reading,
+    };
+    let mut buf = [0; 9];
+    assert_eq!(builder.to_slice(&mut buf, &batch).unwrap(), 9);
+    assert_eq!(&buf, b"\x82\x07\x83\x03\xf9\x3e\x00\x61t");
+
+    #[derive(Serialize)]
+    struct Sparse {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        a: Option<u8>,
+        b: u8,
+    }
+
+    // Skipping a field would move the following ones.
+    assert!(builder.to_vec(&Sparse { a: None, b: 1 }).is_err());
+    assert_eq!(
+        builder.to_vec(&Sparse { a: Some(0), b: 1 }).unwrap(),
+        b"\x82\x00\x01"
+    );
+}
+
+#[test]
+fn test_to_slice() {
+    use serde_cbor::ser::{SerializerBuilder, SliceWrite};
+
+    let value = vec!["foo", "bar"];
+    let mut buf = [0xaa; 16];
+    let len = ser::to_slice(&mut buf, &value).unwrap();
+    assert_eq!(&buf[..len], b"\x82\x63foo\x63bar");
+    assert_eq!(buf[len], 0xaa);
+
+    let mut buf = [0; 5];
+    let error = ser::to_slice(&mut buf, &value).unwrap_err();
+    assert!(error.is_buffer_too_small());
+    assert!(!error.is_io());
+    assert_eq!(error.required_size(), Some(9));
+    assert_eq!(error.to_string(), "buffer too small, 9 bytes required");
+    assert_eq!(&buf, b"\x82\x63foo");
+
+    let mut buf = [0; 9];
+    let len = SerializerBuilder::new()
+        .self_describe(true)
+        .to_slice(&mut buf, &1)
+        .unwrap();
+    assert_eq!(&buf[..len], b"\xd9\xd9\xf7\x01");
+
+    let mut writer = SliceWrite::new(&mut buf);
+    ser::to_writer(&mut writer, &value).unwrap();
+    assert_eq!(writer.bytes_written(), 9);
+    assert_eq!(writer.finish().unwrap(), 9);
+
+    let mut buf = [];
+    let error = ser::to_slice(&mut buf, &()).unwrap_err();
+    assert_eq!(error.required_size(), Some(1));
+}
+
+#[test]
+fn test_write_targets() {
+    use serde::Serialize;
+    use serde_cbor::ser::{CountWrite, IoWrite, SliceWrite, Write};
+    use std::io::Cursor;
+
+    let value = vec!["foo", "bar"];
+    let expected = b"\x82\x63foo\x63bar";
+
+    let mut counter = CountWrite::new();
+    value.serialize(&mut ser::Serializer::new(&mut counter)).unwrap();
+    assert_eq!(counter.count(), expected.len());
+
+    let mut cursor = Cursor::new(Vec::new());
+    value
+        .serialize(&mut ser::Serializer::new(IoWrite::new(&mut cursor)))
+        .unwrap();
+    assert_eq!(cursor.into_inner(), expected);
+
+    let mut cursor = Cursor::new(Vec::new());
+    value
+        .serialize(&mut ser::Serializer::from_io_writer(&mut cursor))
+        .unwrap();
+    assert_eq!(cursor.into_inner(), expected);
+
+    let mut buf = [0; 9];
+    let mut writer = SliceWrite::new(&mut buf);
+    value.serialize(&mut ser::Serializer::new(&mut writer)).unwrap();
+    assert_eq!(writer.finish().unwrap(), 9);
+    assert_eq!(&buf, expected);
+
+    // Writes every byte twice, to show that other crates can provide sinks.
+    struct Doubler(Vec<u8>);
+
+    impl Write for Doubler {
+        type Error = serde_cbor::error::Error;
+
+        fn write_all(&mut self, buf: &[u8]) -> Result<(), Self::Error> {
+            for &byte in buf {
+                self.0.push(byte);
+                self.0.push(byte);
+            }
+            Ok(())
+        }
+    }
+
+    let mut doubler = Doubler(Vec::new());
+    true.serialize(&mut ser::Serializer::new(&mut doubler)).unwrap();
+    assert_eq!(doubler.0, b"\xf5\xf5");
+}
+
+#[test]
+fn test_chunked_strings() {
+    let mut ser = ser::Serializer::new(Vec::new());
+    ser.serialize_bytes_chunked(&b""[..], 3).unwrap();
+    ser.serialize_bytes_chunked(&b"abcdef"[..], 3).unwrap();
+    assert_eq!(ser.into_inner(), b"\x5f\xff\x5f\x43abc\x43def\xff");
+
+    // Chunks end between characters.
+    let mut ser = ser::Serializer::new(Vec::new());
+    ser.serialize_str_chunked("aéé".as_bytes(), 4).unwrap();
+    let v = ser.into_inner();
+    assert_eq!(v, b"\x7f\x63a\xc3\xa9\x62\xc3\xa9\xff");
+    assert_eq!(serde_cbor::from_slice::<String>(&v).unwrap(), "aéé");
+
+    let mut ser = ser::Serializer::new(Vec::new());
+    let error = ser.serialize_str_chunked(&b"ab\xc3"[..], 4).unwrap_err();
+    assert!(error.is_syntax());
+
+    let mut ser = ser::SerializerBuilder::new()
+        .deterministic(true)
+        .build(Vec::new())
+        .unwrap();
+    assert!(ser.serialize_bytes_chunked(&b"abc"[..], 4).is_err());
+}
+
+#[test]
+fn test_encoder() {
+    use serde_cbor::ser::Encoder;
+
+    let mut encoder = Encoder::new(Vec::new());
+    encoder.begin_indefinite_map().unwrap();
+    encoder.write_int(-500).unwrap();
+    encoder.write_nint(u64::max_value()).unwrap();
+    encoder.write_str("a").unwrap();
+    encoder.begin_indefinite_array().unwrap();
+    encoder.write_simple(16).unwrap();
+    encoder.write_simple(255).unwrap();
+    encoder.write_f16(1.5).unwrap();
+    encoder.write_f32(1.5).unwrap();
+    encoder.write_f64(1.5).unwrap();
+    encoder.write_break().unwrap();
+    encoder.write_break().unwrap();
+    assert_eq!(
+        encoder.into_inner(),
+        &b"\xbf\x39\x01\xf3\x3b\xff\xff\xff\xff\xff\xff\xff\xff\x61a\x9f\xf0\xf8\xff\
+           \xf9\x3e\x00\xfa\x3f\xc0\x00\x00\xfb\x3f\xf8\x00\x00\x00\x00\x00\x00\xff\xff"[..]
+    );
+
+    let mut encoder = Encoder::new(Vec::new());
+    assert!(encoder.write_simple(24).is_err());
+    assert!(encoder.write_f16(0.1).is_err());
+
+    // Subtrees are serialized with the options of the serializer.
+    let mut encoder = Encoder::from(ser::Serializer::packed(Vec::new()));
+    encoder.write_tag(1000).unwrap();
+    encoder.serialize(&Some(0.5f64)).unwrap();
+    assert_eq!(encoder.into_inner(), b"\xd9\x03\xe8\xf9\x38\x00");
+}
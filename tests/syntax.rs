@@ -0,0 +1,188 @@
+extern crate serde_cbor;
+
+use serde_cbor::syntax::{self, Argument, Chunk, Float, Item, Length, Str, Width};
+use serde_cbor::{to_vec, Value};
+
+fn from_hex(s: &str) -> Vec<u8> {
+    assert!(s.len() % 2 == 0);
+    (0..s.len() / 2)
+        .map(|i| u8::from_str_radix(&s[i * 2..(i + 1) * 2], 16).unwrap())
+        .collect()
+}
+
+fn roundtrip(bytes: &[u8]) -> Item {
+    let item = syntax::from_slice(bytes).unwrap();
+    assert_eq!(syntax::to_vec(&item).unwrap(), bytes);
+    let item_from_reader = syntax::from_reader(bytes).unwrap();
+    assert_eq!(item, item_from_reader);
+    item
+}
+
+#[test]
+fn test_appendix_a() {
+    let appendix = include_str!("../fuzz/appendix_a.json");
+    let mut count = 0;
+    for line in appendix.lines() {
+        let line = line.trim();
+        if line.starts_with("\"hex\": \"") {
+            let hex = &line[8..line.len() - 2];
+            // Simple values 24 to 31 are reserved and rejected.
+            if hex == "f818" {
+                assert!(syntax::from_slice(&from_hex(hex)).unwrap_err().is_syntax());
+            } else {
+                roundtrip(&from_hex(hex));
+            }
+            count += 1;
+        }
+    }
+    assert_eq!(count, 82);
+}
+
+#[test]
+fn test_non_minimal_heads() {
+    let item = roundtrip(b"\x19\x00\x05");
+    assert_eq!(
+        item,
+        Item::Unsigned(Argument {
+            value: 5,
+            width: Width::U16,
+        })
+    );
+    assert_eq!(item.to_value().unwrap(), Value::U64(5));
+
+    let item = roundtrip(b"\x9a\x00\x00\x00\x01\x38\x00");
+    match item {
+        Item::Array(ref array) => {
+            assert_eq!(array.length, Length::Definite(Width::U32));
+            assert_eq!(array.items.len(), 1);
+        }
+        _ => panic!("expected an array"),
+    }
+    assert_eq!(item.to_value().unwrap(), Value::Array(vec![Value::I64(-1)]));
+
+    roundtrip(b"\x83\x78\x01a\xd8\x01\x00\xdb\x00\x00\x00\x00\x00\x00\x00\x01\x00");
+    // Simple values may use the two byte form, too.
+    roundtrip(b"\xf8\x14");
+}
+
+#[test]
+fn test_indefinite_chunks() {
+    let bytes = b"\x7f\x65Mary \x78\x04Had \x60\xff";
+    let item = roundtrip(bytes);
+    assert_eq!(
+        item,
+        Item::Text(Str::Indefinite(vec![
+            Chunk {
+                width: Width::Immediate,
+                data: "Mary ".to_owned(),
+            },
+            Chunk {
+                width: Width::U8,
+                data: "Had ".to_owned(),
+            },
+            Chunk {
+                width: Width::Immediate,
+                data: String::new(),
+            },
+        ]))
+    );
+    assert_eq!(item.to_value().unwrap(), Value::String("Mary Had ".to_owned()));
+
+    roundtrip(b"\x5f\x42\x01\x23\x42\x45\x67\xff");
+    roundtrip(b"\xbf\x61a\x9f\x01\xff\xff");
+}
+
+#[test]
+fn test_float_width() {
+    let item = roundtrip(b"\xfb\x3f\xf0\x00\x00\x00\x00\x00\x00");
+    assert_eq!(item, Item::Float(Float::Double(0x3ff0_0000_0000_0000)));
+    assert_eq!(item.to_value().unwrap(), Value::F64(1.0));
+    // A NaN with a payload keeps its bits.
+    let item = roundtrip(b"\xfa\x7f\xc0\x00\x01");
+    assert_eq!(item, Item::Float(Float::Single(0x7fc0_0001)));
+    roundtrip(b"\xf9\x3c\x00");
+}
+
+#[test]
+fn test_duplicate_keys_and_order() {
+    let bytes = b"\xa3\x61b\x01\x61a\x02\x61b\x03";
+    let item = roundtrip(bytes);
+    match item {
+        Item::Map(ref map) => {
+            assert_eq!(map.entries.len(), 3);
+            assert_eq!(
+                map.get(&Value::String("b".to_owned())),
+                Some(&Item::Unsigned(Argument::new(1)))
+            );
+        }
+        _ => panic!("expected a map"),
+    }
+    // The semantic value only keeps the last duplicate.
+    let value = item.to_value().unwrap();
+    assert_eq!(value.as_object().unwrap().len(), 2);
+}
+
+#[test]
+fn test_edit_single_field() {
+    let bytes = b"\xbf\x64name\x7f\x62Jo\x61e\xff\x63age\x19\x00\x2a\xff";
+    let mut item = roundtrip(bytes);
+    match item {
+        Item::Map(ref mut map) => {
+            *map.get_mut(&Value::String("age".to_owned())).unwrap() =
+                Item::from_value(&Value::U64(43));
+        }
+        _ => panic!("expected a map"),
+    }
+    assert_eq!(
+        syntax::to_vec(&item).unwrap(),
+        &b"\xbf\x64name\x7f\x62Jo\x61e\xff\x63age\x18\x2b\xff"[..]
+    );
+}
+
+#[test]
+fn test_from_value() {
+    let value: Value = serde_cbor::from_slice(b"\x83\x20\xa1\x61a\xf5\xfa\x47\xc3\x50\x00").unwrap();
+    let item = Item::from_value(&value);
+    assert_eq!(syntax::to_vec(&item).unwrap(), to_vec(&value).unwrap());
+    assert_eq!(item.to_value().unwrap(), value);
+}
+
+#[test]
+fn test_from_value_half() {
+    let item = Item::from_value(&Value::F16(1.5));
+    assert_eq!(syntax::to_vec(&item).unwrap(), b"\xf9\x3e\x00");
+    // A value that is not exactly a half falls back to single precision.
+    let item = Item::from_value(&Value::F16(0.1));
+    assert_eq!(syntax::to_vec(&item).unwrap(), b"\xfa\x3d\xcc\xcc\xcd");
+}
+
+#[test]
+fn test_widening() {
+    // An argument that no longer fits its width is written with the smallest width that fits.
+    let item = Item::Unsigned(Argument {
+        value: 1000,
+        width: Width::Immediate,
+    });
+    assert_eq!(syntax::to_vec(&item).unwrap(), b"\x19\x03\xe8");
+}
+
+#[test]
+fn test_errors() {
+    assert!(syntax::from_slice(b"\x19\x00").unwrap_err().is_eof());
+    assert!(syntax::from_slice(b"\x01\x02").unwrap_err().is_syntax());
+    assert!(syntax::from_slice(b"\xfc").unwrap_err().is_syntax());
+    assert!(syntax::from_slice(b"\x7f\x41\x00\xff").unwrap_err().is_syntax());
+    assert!(syntax::from_slice(b"\x62\xc3\x28").unwrap_err().is_syntax());
+    assert!(syntax::from_slice(b"\xff").unwrap_err().is_syntax());
+    assert!(syntax::from_slice(b"\xf8\x18").unwrap_err().is_syntax());
+
+    // Wider heads of major type 7 are floats, not simple values.
+    for &value in &[24, 31, 256, 65536] {
+        assert!(syntax::to_vec(&Item::Simple(Argument::new(value))).is_err());
+    }
+    let item = Item::Simple(Argument {
+        value: 22,
+        width: Width::U16,
+    });
+    assert_eq!(syntax::to_vec(&item).unwrap(), b"\xf8\x16");
+}
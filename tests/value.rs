@@ -73,3 +73,192 @@ fn serde() {
 
     assert_eq!(value, data_de_value);
 }
+
+mod visit {
+    use std::collections::BTreeMap;
+
+    use serde_cbor::{ObjectKey, Value};
+    use serde_cbor::value::visit::{Path, PathSegment, Visitor, VisitorMut, Walk};
+
+    fn sample() -> Value {
+        let mut inner = BTreeMap::new();
+        inner.insert(ObjectKey::Integer(7), Value::String("seven".to_owned()));
+        inner.insert(ObjectKey::String("secret".to_owned()), Value::Bytes(vec![1, 2, 3]));
+        let mut outer = BTreeMap::new();
+        outer.insert(
+            ObjectKey::String("list".to_owned()),
+            Value::Array(vec![Value::U64(1), Value::Object(inner)]),
+        );
+        outer.insert(ObjectKey::String("flag".to_owned()), Value::Bool(true));
+        Value::Object(outer)
+    }
+
+    #[derive(Default)]
+    struct Recorder {
+        events: Vec<String>,
+    }
+
+    impl Visitor for Recorder {
+        fn enter(&mut self, path: &Path, _value: &Value) -> Walk {
+            self.events.push(format!("enter {:?}", path));
+            Walk::Continue
+        }
+
+        fn leave(&mut self, path: &Path, _value: &Value) {
+            self.events.push(format!("leave {}", path.depth()));
+        }
+    }
+
+    #[test]
+    fn test_walk_order() {
+        let mut recorder = Recorder::default();
+        sample().walk(&mut recorder);
+        assert_eq!(
+            recorder.events,
+            vec![
+                "enter []",
+                "enter [Key(String(\"flag\"))]",
+                "leave 1",
+                "enter [Key(String(\"list\"))]",
+                "enter [Key(String(\"list\")), Index(0)]",
+                "leave 2",
+                "enter [Key(String(\"list\")), Index(1)]",
+                "enter [Key(String(\"list\")), Index(1), Key(Integer(7))]",
+                "leave 3",
+                "enter [Key(String(\"list\")), Index(1), Key(String(\"secret\"))]",
+                "leave 3",
+                "leave 2",
+                "leave 1",
+                "leave 0",
+            ]
+        );
+    }
+
+    struct CountUntil {
+        seen: usize,
+        limit: usize,
+    }
+
+    impl Visitor for CountUntil {
+        fn enter(&mut self, _path: &Path, value: &Value) -> Walk {
+            self.seen += 1;
+            if self.seen == self.limit {
+                Walk::Stop
+            } else if value.is_array() {
+                Walk::SkipChildren
+            } else {
+                Walk::Continue
+            }
+        }
+    }
+
+    #[test]
+    fn test_walk_control() {
+        let mut counter = CountUntil { seen: 0, limit: 100 };
+        sample().walk(&mut counter);
+        // root, "flag" and "list", but not the elements of the skipped array
+        assert_eq!(counter.seen, 3);
+
+        let mut counter = CountUntil { seen: 0, limit: 2 };
+        sample().walk(&mut counter);
+        assert_eq!(counter.seen, 2);
+    }
+
+    struct Redact;
+
+    impl VisitorMut for Redact {
+        fn enter(&mut self, path: &Path, value: &mut Value) -> Walk {
+            if let Some(PathSegment::Key(ObjectKey::String(key))) = path.last() {
+                if key == "secret" {
+                    *value = Value::Null;
+                }
+            }
+            Walk::Continue
+        }
+
+        fn leave(&mut self, _path: &Path, value: &mut Value) {
+            // Post-order replacement sees the already transformed children.
+            let len = value.as_array().map(|array| array.len());
+            if let Some(len) = len {
+                value.as_array_mut().unwrap().push(Value::U64(len as u64));
+            }
+        }
+    }
+
+    #[test]
+    fn test_walk_mut() {
+        let mut value = sample();
+        value.walk_mut(&mut Redact);
+
+        let list = &value.as_object().unwrap()[&ObjectKey::String("list".to_owned())];
+        let list = list.as_array().unwrap();
+        assert_eq!(list.len(), 3);
+        assert_eq!(list[2], Value::U64(2));
+        let inner = list[1].as_object().unwrap();
+        assert_eq!(inner[&ObjectKey::String("secret".to_owned())], Value::Null);
+        assert_eq!(inner[&ObjectKey::Integer(7)], Value::String("seven".to_owned()));
+    }
+}
+
+mod raw {
+    use serde_cbor::{self, Value};
+    use serde_cbor::value::{RawValue, RawValueBuf};
+
+    #[derive(Debug, Deserialize, Serialize)]
+    struct Envelope<'a> {
+        to: u32,
+        #[serde(borrow)]
+        body: &'a RawValue,
+    }
+
+    #[derive(Debug, Deserialize, Serialize)]
+    struct OwnedEnvelope {
+        to: u32,
+        body: RawValueBuf,
+    }
+
+    // {"to": 1, "body": 1(["a" "b", h'01', {_ 0: 24}])}, with a non-minimal head, an
+    // indefinite length string and map, and a tag.
+    const MESSAGE: &[u8] = b"\xa2\x62to\x01\x64body\xc1\x83\x7f\x61a\x61b\xff\x41\x01\xbf\x00\x18\x18\xff";
+    const BODY: &[u8] = b"\xc1\x83\x7f\x61a\x61b\xff\x41\x01\xbf\x00\x18\x18\xff";
+
+    #[test]
+    fn test_raw_value() {
+        let envelope: Envelope = serde_cbor::from_slice(MESSAGE).unwrap();
+        assert_eq!(envelope.to, 1);
+        assert_eq!(envelope.body.as_bytes(), BODY);
+        assert_eq!(serde_cbor::to_vec(&envelope).unwrap(), MESSAGE);
+
+        let envelope: OwnedEnvelope = serde_cbor::from_reader(MESSAGE).unwrap();
+        assert_eq!(envelope.body.as_bytes(), BODY);
+        assert_eq!(serde_cbor::to_vec(&envelope).unwrap(), MESSAGE);
+        let envelope: OwnedEnvelope = serde_cbor::from_buf_reader(MESSAGE).unwrap();
+        assert_eq!(envelope.body.as_bytes(), BODY);
+
+        let value = serde_cbor::to_value(RawValue::from_slice(b"\x82\x01\x61a").unwrap()).unwrap();
+        assert_eq!(value, Value::Array(vec![Value::U64(1), Value::String("a".to_owned())]));
+    }
+
+    #[test]
+    fn test_raw_value_errors() {
+        assert!(RawValue::from_slice(b"").is_err());
+        assert!(RawValue::from_slice(b"\x82\x01").is_err());
+        assert!(RawValue::from_slice(b"\x01\x02").is_err());
+        assert!(RawValue::from_slice(b"\xff").is_err());
+        assert!(RawValue::from_slice(b"\x1c").is_err());
+        assert!(RawValue::from_slice(b"\x7f\x41\x00\xff").is_err());
+        assert!(RawValue::from_slice(b"\xf8\x10").is_err());
+        // A tag has to be followed by an item, not by a break.
+        assert!(RawValue::from_slice(b"\x9f\xc1\xff").is_err());
+        assert!(RawValueBuf::from_vec(b"\x9f\xc1\xc1\xff".to_vec()).is_err());
+        assert!(serde_cbor::from_slice::<serde::de::IgnoredAny>(b"\x9f\xc1\xff").is_err());
+        assert!(RawValueBuf::from_vec(b"\x9f\x01\xff".to_vec()).is_ok());
+
+        let err = serde_cbor::from_slice::<OwnedEnvelope>(b"\xa2\x62to\x01\x64body\x9f\x01").unwrap_err();
+        assert_eq!(err.offset(), 12);
+
+        // Borrowing needs a slice.
+        let mut de = serde_cbor::Deserializer::from_reader(BODY);
+        assert!(serde::Deserialize::deserialize(&mut de).map(|_: &RawValue| ()).is_err());
+    }
+}